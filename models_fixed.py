@@ -24,6 +24,11 @@ class Reading(Base):
     spread_type = Column(String(50), nullable=False)
     reading_date = Column(DateTime, default=datetime.utcnow, index=True)
     notes = Column(Text)
+    question = Column(Text)
+    quintessence = Column(String(100))
+    numerology = Column(Text)
+    moon_phase = Column(String(30))
+    sun_sign = Column(String(20))
     is_private = Column(Boolean, default=False)
     
     user = relationship("User", back_populates="readings")
@@ -39,6 +44,7 @@ class CardDrawn(Base):
     card_label = Column(String(100))
     is_reversed = Column(Boolean, default=False)
     interpretation = Column(Text)
+    dignity = Column(String(20))
     
     reading = relationship("Reading", back_populates="cards")
 
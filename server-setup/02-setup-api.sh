@@ -156,6 +156,11 @@ class Reading(Base):
     spread_type = Column(String(50), nullable=False)
     reading_date = Column(DateTime, default=datetime.utcnow, index=True)
     notes = Column(Text)
+    question = Column(Text)
+    quintessence = Column(String(100))
+    numerology = Column(Text)
+    moon_phase = Column(String(30))
+    sun_sign = Column(String(20))
     is_private = Column(Boolean, default=False)
     
     user = relationship("User", back_populates="readings")
@@ -171,6 +176,7 @@ class CardDrawn(Base):
     card_label = Column(String(100))
     is_reversed = Column(Boolean, default=False)
     interpretation = Column(Text)
+    dignity = Column(String(20))
     
     reading = relationship("Reading", back_populates="cards")
 
@@ -214,6 +220,7 @@ class CardDrawnCreate(BaseModel):
     card_label: Optional[str] = None
     is_reversed: bool = False
     interpretation: Optional[str] = None
+    dignity: Optional[str] = None  # strengthened, neutral, weakened or ill-dignified
 
 class CardDrawnResponse(CardDrawnCreate):
     card_id: int
@@ -228,6 +235,12 @@ class ReadingCreate(BaseModel):
     spread_type: str
     cards: List[CardDrawnCreate]
     notes: Optional[str] = None
+    question: Optional[str] = None
+    reading_date: Optional[datetime] = None  # set when importing past readings
+    quintessence: Optional[str] = None
+    numerology: Optional[str] = None
+    moon_phase: Optional[str] = None
+    sun_sign: Optional[str] = None
     is_private: bool = False
 
 class ReadingResponse(BaseModel):
@@ -237,6 +250,7 @@ class ReadingResponse(BaseModel):
     spread_type: str
     reading_date: datetime
     notes: Optional[str]
+    question: Optional[str] = None
     is_private: bool
     cards: List[CardDrawnResponse]
     
@@ -342,8 +356,15 @@ def create_reading(reading: ReadingCreate, db: Session = Depends(get_db)):
         room_id=reading.room_id,
         spread_type=reading.spread_type,
         notes=reading.notes,
+        question=reading.question,
+        quintessence=reading.quintessence,
+        numerology=reading.numerology,
+        moon_phase=reading.moon_phase,
+        sun_sign=reading.sun_sign,
         is_private=reading.is_private
     )
+    if reading.reading_date:
+        new_reading.reading_date = reading.reading_date
     db.add(new_reading)
     db.commit()
     db.refresh(new_reading)
@@ -359,8 +380,9 @@ def create_reading(reading: ReadingCreate, db: Session = Depends(get_db)):
     db.commit()
     db.refresh(new_reading)
     
-    # Update user's last reading time
-    user.last_reading_at = new_reading.reading_date
+    # Update user's last reading time (imported readings may be older)
+    if not user.last_reading_at or new_reading.reading_date > user.last_reading_at:
+        user.last_reading_at = new_reading.reading_date
     db.commit()
     
     return new_reading
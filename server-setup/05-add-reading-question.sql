@@ -0,0 +1,5 @@
+-- Record the querent's question with each reading
+
+ALTER TABLE readings ADD COLUMN IF NOT EXISTS question TEXT;
+
+COMMENT ON COLUMN readings.question IS 'The question asked for this reading, if any';
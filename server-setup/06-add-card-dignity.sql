@@ -0,0 +1,5 @@
+-- Record each card's elemental dignity from its neighbours in the spread
+
+ALTER TABLE cards_drawn ADD COLUMN IF NOT EXISTS dignity VARCHAR(20);
+
+COMMENT ON COLUMN cards_drawn.dignity IS 'Elemental dignity: strengthened, neutral, weakened or ill-dignified';
@@ -0,0 +1,7 @@
+-- Record each spread's quintessence and numerology summary
+
+ALTER TABLE readings ADD COLUMN IF NOT EXISTS quintessence VARCHAR(100);
+ALTER TABLE readings ADD COLUMN IF NOT EXISTS numerology TEXT;
+
+COMMENT ON COLUMN readings.quintessence IS 'Trump that the sum of the card numbers reduces to';
+COMMENT ON COLUMN readings.numerology IS 'Numerology summary as posted with the reading';
@@ -0,0 +1,7 @@
+-- Record the Moon's phase and the Sun's sign when each reading was drawn
+
+ALTER TABLE readings ADD COLUMN IF NOT EXISTS moon_phase VARCHAR(30);
+ALTER TABLE readings ADD COLUMN IF NOT EXISTS sun_sign VARCHAR(20);
+
+COMMENT ON COLUMN readings.moon_phase IS 'Phase of the Moon at the time of the reading, e.g. Full Moon';
+COMMENT ON COLUMN readings.sun_sign IS 'Zodiac sign the Sun was in at the time of the reading';
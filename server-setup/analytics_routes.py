@@ -40,8 +40,22 @@ def get_user_history(matrix_id: str, db: Session = Depends(get_db)):
             "spread_type": reading.spread_type,
             "reading_date": reading.reading_date.isoformat(),
             "card_count": len(cards),
-            "cards": [{"position": c.position, "card_name": c.card_name, "label": c.card_label} for c in cards],
-            "notes": reading.notes
+            "cards": [
+                {
+                    "position": c.position,
+                    "card_name": c.card_name,
+                    "label": c.card_label,
+                    "reversed": c.is_reversed,
+                    "dignity": c.dignity
+                }
+                for c in cards
+            ],
+            "notes": reading.notes,
+            "question": reading.question,
+            "room_id": reading.room_id,
+            "quintessence": reading.quintessence,
+            "moon_phase": reading.moon_phase,
+            "sun_sign": reading.sun_sign
         })
     
     return {
@@ -73,7 +87,8 @@ def get_reading_details(reading_id: int, db: Session = Depends(get_db)):
             "card_name": card_drawn.card_name,
             "label": card_drawn.card_label,
             "info": card_info.info if card_info and card_info.info else None,
-            "deepinfo": card_info.deepinfo if card_info and card_info.deepinfo else None
+            "deepinfo": card_info.deepinfo if card_info and card_info.deepinfo else None,
+            "dignity": card_drawn.dignity
         })
     
     # Get attribute counts
@@ -92,6 +107,10 @@ def get_reading_details(reading_id: int, db: Session = Depends(get_db)):
         "spread_type": reading.spread_type,
         "reading_date": reading.reading_date.isoformat(),
         "notes": reading.notes,
+        "question": reading.question,
+        "numerology": reading.numerology,
+        "moon_phase": reading.moon_phase,
+        "sun_sign": reading.sun_sign,
         "cards": card_details,
         "attributes": attribute_counts
     }
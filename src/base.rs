@@ -91,9 +91,21 @@ use modalkit::{
 };
 
 use crate::config::ImagePreviewProtocolValues;
+use crate::image_upload::ImageUpload;
 use crate::message::ImageStatus;
 use crate::notifications::NotificationHandle;
 use crate::preview::{source_from_event, spawn_insert_preview};
+use crate::tarot_annotations::Annotations;
+use crate::tarot_api::HttpStore;
+use crate::tarot_cards::{self, Translation};
+use crate::tarot_export::ExportFormat;
+use crate::tarot_learn::LearnSession;
+use crate::tarot_search::{SearchHit, SearchIndex, SearchResults};
+use crate::tarot_spreads::{FaceDown, PickSession, Spread, SpreadThread};
+use crate::tarot_store::ReadingStore;
+use crate::tarot_tree::TreeView;
+use crate::tarot_trends::{ReadingFilter, TrendQuery, TrendReport};
+use crate::tarot_wheel::{WheelQuery, WheelView};
 use crate::{
     message::{Message, MessageEvent, MessageKey, MessageTimeStamp, Messages},
     worker::Requester,
@@ -236,6 +248,33 @@ bitflags::bitflags! {
     }
 }
 
+bitflags::bitflags! {
+    /// Which card meanings to include when posting tarot cards.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub struct TarotFlags: u32 {
+        /// No flags specified.
+        const NONE = 0b00000000;
+
+        /// Include each card's short meaning.
+        const INFO = 0b00000001;
+
+        /// Include each card's full description.
+        const DEEPINFO = 0b00000010;
+
+        /// Post the Tree of Life with the spread's sephiroth and paths lit up.
+        const TREE = 0b00000100;
+
+        /// Post the zodiac wheel with the spread's cards placed on it.
+        const WHEEL = 0b00001000;
+
+        /// Post the spread face down, to be turned over with `:tarot reveal`.
+        const FACEDOWN = 0b00010000;
+
+        /// Let the querent choose the spread's cards from a face-down fan.
+        const PICK = 0b00100000;
+    }
+}
+
 /// Fields that rooms and spaces can be sorted by.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum SortFieldRoom {
@@ -484,14 +523,107 @@ pub enum SendAction {
     /// Upload the image data.
     UploadImage(usize, usize, Cow<'static, [u8]>),
 
-    /// Upload a tarot spread (multiple cards with labels).
-    TarotSpread(Vec<(String, String)>),
-    
+    /// Post a tarot spread as a thread, with each position's card as a reply to its root.
+    TarotSpread(Box<SpreadThread>),
+
     /// Upload a file and send accompanying text
     UploadWithText(String, String),
-    
+
+    /// Upload a file with a caption, optionally followed by accompanying text.
+    UploadWithCaption(String, String, Option<String>),
+
+    /// Upload an image held in memory, with an optional caption and accompanying text.
+    UploadBytes(Box<ImageUpload>, Option<String>, Option<String>),
+
     /// Send formatted text as a message
     SendText(String),
+
+    /// Upload a face-down spread, remembering the message so it can be revealed later.
+    UploadFaceDown(Box<ImageUpload>, Box<FaceDown>),
+
+    /// Edit a face-down spread's message to show the cards turned over in the given spread,
+    /// which replaces the room's spread once sent, and is followed by its finale if complete.
+    RevealFaceDown(Box<ImageUpload>, Box<FaceDown>),
+}
+
+/// An action that performs a tarot reading or looks over past ones.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TarotAction {
+    /// Draw a spread, recording the querent's question if one was given.
+    Spread(Spread, Option<String>, TarotFlags),
+
+    /// Post a card from the deck, named in English or the reader's language, with the meanings
+    /// asked for and the question.
+    Card(String, Option<String>, TarotFlags),
+
+    /// Post the small card ruling the Sun's current decan, with the trump of its sign.
+    Today(TarotFlags),
+
+    /// Look something up in the reading history for `:tarothistory`.
+    History(HistoryQuery),
+
+    /// Chart trends in the reading history in the `:tarothistory chart` window.
+    Trends(TrendQuery),
+
+    /// Show a reading from the history, by its number, in the `:tarothistory tree` window.
+    Tree(usize),
+
+    /// Show one reading or a range of them in the `:tarothistory wheel` window.
+    Wheel(WheelQuery),
+
+    /// Draw the card of the day, or show it again if it was already drawn today.
+    Daily(TarotFlags),
+
+    /// Turn over a card of the room's face-down spread, or the next one if none is given.
+    Reveal(Option<usize>),
+
+    /// Post the spread whose cards were chosen in the `:tarot <spread> pick` window.
+    Picked,
+
+    /// Quiz the user on the deck in the `:tarot learn` window.
+    Learn,
+
+    /// Edit the user's note on a card, named in English or the reader's language, in the external
+    /// editor.
+    Annotate(String),
+
+    /// Search the deck's meanings and the user's notes, showing the cards found in the
+    /// `:tarot search` window.
+    Search(String),
+}
+
+/// What `:tarothistory` looks up in the reading history, once its arguments have been checked.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HistoryQuery {
+    /// List every reading.
+    List,
+
+    /// Show a reading by its number, with the cards' meanings if asked for.
+    Details(usize, bool),
+
+    /// Graph how often each value of an attribute type was drawn.
+    Attribute(&'static str, ReadingFilter),
+
+    /// List the readings matching a filter.
+    Filtered(ReadingFilter),
+
+    /// Describe how an attribute type was drawn over time.
+    Trends(TrendQuery),
+
+    /// Total up the readings, spreads and most drawn attributes.
+    Summary,
+
+    /// List the readings whose question has these words.
+    Search(Vec<String>),
+
+    /// Group the questions by topic, or list the readings about one.
+    Topics(Option<String>),
+
+    /// Write the readings to a file.
+    Export(String, ExportFormat),
+
+    /// Add the readings from a file that aren't in the history yet.
+    Import(String, ExportFormat),
 }
 
 /// An action performed against the user's homeserver.
@@ -537,6 +669,9 @@ pub enum IambAction {
     /// Send a message to the currently focused room.
     Send(SendAction),
 
+    /// Perform a tarot reading.
+    Tarot(TarotAction),
+
     /// Perform an action for an in-progress verification.
     Verify(VerifyAction, String),
 
@@ -587,6 +722,12 @@ impl From<SendAction> for IambAction {
     }
 }
 
+impl From<TarotAction> for IambAction {
+    fn from(act: TarotAction) -> Self {
+        IambAction::Tarot(act)
+    }
+}
+
 impl ApplicationAction for IambAction {
     fn is_edit_sequence(&self, _: &EditContext) -> SequenceStatus {
         match self {
@@ -598,6 +739,7 @@ impl ApplicationAction for IambAction {
             IambAction::Room(..) => SequenceStatus::Break,
             IambAction::OpenLink(..) => SequenceStatus::Break,
             IambAction::Send(..) => SequenceStatus::Break,
+            IambAction::Tarot(..) => SequenceStatus::Break,
             IambAction::ToggleScrollbackFocus => SequenceStatus::Break,
             IambAction::Verify(..) => SequenceStatus::Break,
             IambAction::VerifyRequest(..) => SequenceStatus::Break,
@@ -614,6 +756,7 @@ impl ApplicationAction for IambAction {
             IambAction::OpenLink(..) => SequenceStatus::Atom,
             IambAction::Room(..) => SequenceStatus::Atom,
             IambAction::Send(..) => SequenceStatus::Atom,
+            IambAction::Tarot(..) => SequenceStatus::Atom,
             IambAction::ToggleScrollbackFocus => SequenceStatus::Atom,
             IambAction::Verify(..) => SequenceStatus::Atom,
             IambAction::VerifyRequest(..) => SequenceStatus::Atom,
@@ -630,6 +773,7 @@ impl ApplicationAction for IambAction {
             IambAction::Room(..) => SequenceStatus::Ignore,
             IambAction::OpenLink(..) => SequenceStatus::Ignore,
             IambAction::Send(..) => SequenceStatus::Ignore,
+            IambAction::Tarot(..) => SequenceStatus::Ignore,
             IambAction::ToggleScrollbackFocus => SequenceStatus::Ignore,
             IambAction::Verify(..) => SequenceStatus::Ignore,
             IambAction::VerifyRequest(..) => SequenceStatus::Ignore,
@@ -645,6 +789,7 @@ impl ApplicationAction for IambAction {
             IambAction::Room(..) => false,
             IambAction::Keys(..) => false,
             IambAction::Send(..) => false,
+            IambAction::Tarot(..) => false,
             IambAction::OpenLink(..) => false,
             IambAction::ToggleScrollbackFocus => false,
             IambAction::Verify(..) => false,
@@ -1571,6 +1716,41 @@ pub struct ChatStore {
 
     /// Notifications that should be dismissed when the user opens the room.
     pub open_notifications: HashMap<OwnedRoomId, Vec<NotificationHandle>>,
+
+    /// The most recent report shown in the `:tarothistory chart` window.
+    pub tarot_trends: Option<TrendReport>,
+
+    /// The reading shown in the `:tarothistory tree` window.
+    pub tarot_tree: Option<TreeView>,
+
+    /// The wheel shown in the `:tarothistory wheel` window.
+    pub tarot_wheel: Option<WheelView>,
+
+    /// Spreads posted face down in each room, waiting for `:tarot reveal`.
+    pub tarot_facedown: HashMap<OwnedRoomId, FaceDown>,
+
+    /// The spread whose cards are being chosen in the `:tarot <spread> pick` window.
+    pub tarot_pick: Option<PickSession>,
+
+    /// Flashcards for the `:tarot learn` window, loaded when it is first opened.
+    pub tarot_learn: Option<LearnSession>,
+
+    /// The user's notes on cards, shown with the deck's meanings.
+    ///
+    /// They are replaced with [ChatStore::set_tarot_annotations], so that searches see them.
+    pub tarot_annotations: Annotations,
+
+    /// Where readings are saved, and the reading history is read from.
+    pub tarot_store: Arc<dyn ReadingStore>,
+
+    /// The language card names and meanings are shown in.
+    pub tarot_language: Arc<Translation>,
+
+    /// The index `:tarot search` runs against, built when first needed after the notes change.
+    tarot_index: Option<SearchIndex>,
+
+    /// The cards found by the latest `:tarot search`.
+    pub tarot_search: Option<SearchResults>,
 }
 
 impl ChatStore {
@@ -1596,9 +1776,36 @@ impl ChatStore {
             ring_bell: false,
             focused: true,
             open_notifications: Default::default(),
+            tarot_trends: None,
+            tarot_tree: None,
+            tarot_wheel: None,
+            tarot_facedown: Default::default(),
+            tarot_pick: None,
+            tarot_learn: None,
+            tarot_annotations: Default::default(),
+            tarot_store: Arc::new(HttpStore::default()),
+            tarot_language: Default::default(),
+            tarot_index: None,
+            tarot_search: None,
         }
     }
 
+    /// Replace the user's notes on cards, which searches look through from now on.
+    pub fn set_tarot_annotations(&mut self, notes: Annotations) {
+        self.tarot_annotations = notes;
+        self.tarot_index = None;
+    }
+
+    /// Search the deck's meanings and the user's notes.
+    pub fn search_cards(&mut self, query: &str) -> Vec<SearchHit> {
+        let notes = &self.tarot_annotations;
+        let index = self
+            .tarot_index
+            .get_or_insert_with(|| SearchIndex::new(&tarot_cards::get_all_cards(), notes));
+
+        index.search(query)
+    }
+
     /// Get a joined room.
     pub fn get_joined_room(&self, room_id: &RoomId) -> Option<MatrixRoom> {
         let room = self.worker.client.get_room(room_id)?;
@@ -1668,6 +1875,24 @@ pub enum IambId {
 
     /// The `:unreads` window.
     UnreadList,
+
+    /// The `:tarothistory chart` window.
+    TarotTrends,
+
+    /// The `:tarothistory tree` window.
+    TarotTree,
+
+    /// The `:tarothistory wheel` window.
+    TarotWheel,
+
+    /// The `:tarot <spread> pick` window.
+    TarotPick,
+
+    /// The `:tarot learn` window.
+    TarotLearn,
+
+    /// The `:tarot search` window.
+    TarotSearch,
 }
 
 impl Display for IambId {
@@ -1689,6 +1914,12 @@ impl Display for IambId {
             IambId::Welcome => f.write_str("iamb://welcome"),
             IambId::ChatList => f.write_str("iamb://chats"),
             IambId::UnreadList => f.write_str("iamb://unreads"),
+            IambId::TarotTrends => f.write_str("iamb://tarottrends"),
+            IambId::TarotTree => f.write_str("iamb://tarottree"),
+            IambId::TarotWheel => f.write_str("iamb://tarotwheel"),
+            IambId::TarotPick => f.write_str("iamb://tarotpick"),
+            IambId::TarotLearn => f.write_str("iamb://tarotlearn"),
+            IambId::TarotSearch => f.write_str("iamb://tarotsearch"),
         }
     }
 }
@@ -1827,6 +2058,48 @@ impl Visitor<'_> for IambIdVisitor {
 
                 Ok(IambId::UnreadList)
             },
+            Some("tarottrends") => {
+                if url.path() != "" {
+                    return Err(E::custom("iamb://tarottrends takes no path"));
+                }
+
+                Ok(IambId::TarotTrends)
+            },
+            Some("tarottree") => {
+                if url.path() != "" {
+                    return Err(E::custom("iamb://tarottree takes no path"));
+                }
+
+                Ok(IambId::TarotTree)
+            },
+            Some("tarotwheel") => {
+                if url.path() != "" {
+                    return Err(E::custom("iamb://tarotwheel takes no path"));
+                }
+
+                Ok(IambId::TarotWheel)
+            },
+            Some("tarotpick") => {
+                if url.path() != "" {
+                    return Err(E::custom("iamb://tarotpick takes no path"));
+                }
+
+                Ok(IambId::TarotPick)
+            },
+            Some("tarotlearn") => {
+                if url.path() != "" {
+                    return Err(E::custom("iamb://tarotlearn takes no path"));
+                }
+
+                Ok(IambId::TarotLearn)
+            },
+            Some("tarotsearch") => {
+                if url.path() != "" {
+                    return Err(E::custom("iamb://tarotsearch takes no path"));
+                }
+
+                Ok(IambId::TarotSearch)
+            },
             Some(s) => Err(E::custom(format!("{s:?} is not a valid window"))),
             None => Err(E::custom("Invalid iamb window URL")),
         }
@@ -1897,6 +2170,24 @@ pub enum IambBufferId {
 
     /// The `:unreads` window.
     UnreadList,
+
+    /// The notes below the charts in the `:tarothistory chart` window.
+    TarotTrends,
+
+    /// The legend beside the tree in the `:tarothistory tree` window.
+    TarotTree,
+
+    /// The legend beside the wheel in the `:tarothistory wheel` window.
+    TarotWheel,
+
+    /// The face-down fan in the `:tarot <spread> pick` window, one character per card.
+    TarotPick,
+
+    /// The answer being typed in the `:tarot learn` window.
+    TarotLearn,
+
+    /// The `:tarot search` window, one line per card found.
+    TarotSearch,
 }
 
 impl IambBufferId {
@@ -1913,6 +2204,12 @@ impl IambBufferId {
             IambBufferId::Welcome => IambId::Welcome,
             IambBufferId::ChatList => IambId::ChatList,
             IambBufferId::UnreadList => IambId::UnreadList,
+            IambBufferId::TarotTrends => IambId::TarotTrends,
+            IambBufferId::TarotTree => IambId::TarotTree,
+            IambBufferId::TarotWheel => IambId::TarotWheel,
+            IambBufferId::TarotPick => IambId::TarotPick,
+            IambBufferId::TarotLearn => IambId::TarotLearn,
+            IambBufferId::TarotSearch => IambId::TarotSearch,
         };
 
         Some(id)
@@ -1957,6 +2254,12 @@ impl Completer<IambInfo> for IambCompleter {
             IambBufferId::Welcome => vec![],
             IambBufferId::ChatList => vec![],
             IambBufferId::UnreadList => vec![],
+            IambBufferId::TarotTrends => vec![],
+            IambBufferId::TarotTree => vec![],
+            IambBufferId::TarotWheel => vec![],
+            IambBufferId::TarotPick => vec![],
+            IambBufferId::TarotLearn => vec![],
+            IambBufferId::TarotSearch => vec![],
         }
     }
 }
@@ -2085,6 +2388,7 @@ fn complete_cmdarg(
         "members" | "rooms" | "spaces" | "welcome" => vec![],
         "download" | "keys" | "open" | "upload" | "tarot" => complete_path(text, cursor),
         "react" | "unreact" => complete_emoji(text, cursor, store),
+        "tarothistory" => complete_tarothistory(desc, text, cursor),
 
         "invite" => complete_users(text, cursor, store),
         "join" | "split" | "vsplit" | "tabedit" => complete_matrix_names(text, cursor, store),
@@ -2097,6 +2401,18 @@ fn complete_cmdarg(
     }
 }
 
+/// Tab completion for the file argument of `:tarothistory export` and `:tarothistory import`.
+fn complete_tarothistory(
+    desc: CommandDescription,
+    text: &EditRope,
+    cursor: &mut Cursor,
+) -> Vec<String> {
+    match desc.arg.text.split_whitespace().next() {
+        Some("export" | "import") => complete_path(text, cursor),
+        _ => vec![],
+    }
+}
+
 /// Tab completion for commands.
 fn complete_cmd(cmd: &str, text: &EditRope, cursor: &mut Cursor, store: &ChatStore) -> Vec<String> {
     match CommandDescription::from_str(cmd) {
@@ -2292,6 +2608,24 @@ pub mod tests {
         )],);
     }
 
+    #[tokio::test]
+    async fn test_search_cards() {
+        let mut store = mock_store().await;
+        let store = &mut store.application;
+        let fool = tarot_cards::find_card("fool").unwrap();
+
+        let hits = store.search_cards("moving abroad");
+        assert!(hits.iter().all(|hit| hit.card.card != "The Fool"));
+
+        // New notes are searched as soon as they are in place
+        let mut notes = Annotations::default();
+        notes.set(fool, "Always turns up before a move abroad", 1);
+        store.set_tarot_annotations(notes);
+
+        let hits = store.search_cards("moving abroad");
+        assert_eq!(hits[0].card.card, "The Fool");
+    }
+
     #[tokio::test]
     async fn test_complete_msgbar() {
         let store = mock_store().await;
@@ -2365,5 +2699,15 @@ pub mod tests {
         let mut cursor = Cursor::new(0, 15);
         let res = complete_cmdbar(&text, &mut cursor, &store);
         assert_eq!(res, users);
+
+        let text = EditRope::from("tarothistory export /");
+        let mut cursor = Cursor::new(0, 21);
+        let res = complete_cmdbar(&text, &mut cursor, &store);
+        assert!(!res.is_empty());
+
+        let text = EditRope::from("tarothistory summary /");
+        let mut cursor = Cursor::new(0, 22);
+        let res = complete_cmdbar(&text, &mut cursor, &store);
+        assert!(res.is_empty());
     }
 }
@@ -7,6 +7,7 @@ use std::{convert::TryFrom, str::FromStr as _};
 use matrix_sdk::ruma::{events::tag::TagName, OwnedRoomId, OwnedUserId};
 
 use modalkit::{
+    actions::Action,
     commands::{CommandError, CommandResult, CommandStep},
     env::vim::command::{CommandContext, CommandDescription, OptionType},
     prelude::OpenTarget,
@@ -16,23 +17,29 @@ use crate::base::{
     CreateRoomFlags,
     CreateRoomType,
     DownloadFlags,
+    HistoryQuery,
     HomeserverAction,
     IambAction,
     IambId,
     KeysAction,
     MemberUpdateAction,
     MessageAction,
+    ProgramAction,
     ProgramCommand,
     ProgramCommands,
     RoomAction,
     RoomField,
     SendAction,
     SpaceAction,
+    TarotAction,
+    TarotFlags,
     VerifyAction,
 };
+use crate::tarot_store::ReadingStore;
 
 type ProgContext = CommandContext;
 type ProgResult = CommandResult<ProgramCommand>;
+type HistoryResult = Result<ProgramAction, CommandError>;
 
 /// Convert strings the user types into a tag name.
 fn tag_name(name: String) -> Result<TagName, CommandError> {
@@ -644,25 +651,97 @@ fn iamb_upload(desc: CommandDescription, ctx: &mut ProgContext) -> ProgResult {
 }
 
 fn iamb_tarot(desc: CommandDescription, ctx: &mut ProgContext) -> ProgResult {
-    use crate::tarot_cards;
+    use crate::tarot_spreads::{self, Spread};
     
     let mut args = desc.arg.strings()?;
 
+    // Everything after "--" is the querent's question
+    let question = match args.iter().position(|arg| arg == "--") {
+        Some(idx) => {
+            let question = args.split_off(idx + 1).join(" ");
+            args.pop();
+            Some(question).filter(|q| !q.trim().is_empty())
+        },
+        None => None,
+    };
+
     if args.is_empty() {
-        let msg = "Usage: :tarot <card-name-or-number> [info] [deepinfo]\nExamples:\n  :tarot fool\n  :tarot fool info\n  :tarot six of swords deepinfo\n  :tarot 3 info\n  :tarot 5 info deepinfo";
+        let msg = "Usage: :tarot <card-name-or-number|celtic|daily|today|calendar> [info] [deepinfo] [tree] [wheel] [facedown] [pick] [-- question]\n       :tarot reveal [position]\n       :tarot learn\n       :tarot annotate <card-name>\n       :tarot search <words>\nExamples:\n  :tarot fool\n  :tarot fool info\n  :tarot six of swords deepinfo\n  :tarot 3 info\n  :tarot 5 info deepinfo\n  :tarot celtic tree wheel\n  :tarot celtic facedown\n  :tarot 3 pick\n  :tarot reveal\n  :tarot learn\n  :tarot annotate six of swords\n  :tarot search journey by water\n  :tarot daily\n  :tarot today\n  :tarot calendar 2026\n  :tarot celtic -- \"Should I take the job?\"";
         return Result::Err(CommandError::Error(msg.into()));
     }
 
+    if args[0] == "reveal" {
+        let position = match args.get(1..) {
+            Some([]) | None => None,
+            Some([n]) => {
+                Some(
+                    n.parse::<usize>().ok().filter(|n| *n > 0).ok_or_else(|| {
+                        CommandError::Error("Usage: :tarot reveal [position]".into())
+                    })?,
+                )
+            },
+            Some(_) => return Err(CommandError::Error("Usage: :tarot reveal [position]".into())),
+        };
+        let tact = TarotAction::Reveal(position);
+        let step = CommandStep::Continue(IambAction::from(tact).into(), ctx.context.clone());
+        return Ok(step);
+    }
+
+    if args[0] == "learn" {
+        if args.len() > 1 {
+            return Err(CommandError::Error("Usage: :tarot learn".into()));
+        }
+
+        let step =
+            CommandStep::Continue(IambAction::from(TarotAction::Learn).into(), ctx.context.clone());
+        return Ok(step);
+    }
+
+    if args[0] == "annotate" {
+        let name = args[1..].join(" ");
+        if name.trim().is_empty() {
+            return Err(CommandError::Error("Usage: :tarot annotate <card-name>".into()));
+        }
+
+        let tact = TarotAction::Annotate(name);
+        let step = CommandStep::Continue(IambAction::from(tact).into(), ctx.context.clone());
+        return Ok(step);
+    }
+
+    if args[0] == "search" {
+        let query = args[1..].join(" ");
+        if query.trim().is_empty() {
+            return Err(CommandError::Error("Usage: :tarot search <words>".into()));
+        }
+
+        let step = CommandStep::Continue(
+            IambAction::from(TarotAction::Search(query)).into(),
+            ctx.context.clone(),
+        );
+        return Ok(step);
+    }
+
     // Check for info/deepinfo flags at the end
-    let mut show_info = false;
-    let mut show_deepinfo = false;
+    let mut flags = TarotFlags::NONE;
     
     while let Some(last) = args.last() {
         if last == "info" {
-            show_info = true;
+            flags |= TarotFlags::INFO;
             args.pop();
         } else if last == "deepinfo" {
-            show_deepinfo = true;
+            flags |= TarotFlags::DEEPINFO;
+            args.pop();
+        } else if last == "tree" {
+            flags |= TarotFlags::TREE;
+            args.pop();
+        } else if last == "wheel" {
+            flags |= TarotFlags::WHEEL;
+            args.pop();
+        } else if last == "facedown" {
+            flags |= TarotFlags::FACEDOWN;
+            args.pop();
+        } else if last == "pick" {
+            flags |= TarotFlags::PICK;
             args.pop();
         } else {
             break;
@@ -677,8 +756,10 @@ fn iamb_tarot(desc: CommandDescription, ctx: &mut ProgContext) -> ProgResult {
     // Check if first argument is a number (for N-card spreads)
     if args.len() == 1 {
         if let Ok(num_cards) = args[0].parse::<usize>() {
-            if num_cards >= 1 && num_cards <= 10 {
-                return handle_n_card_spread(num_cards, show_info, show_deepinfo, ctx);
+            if (1..=10).contains(&num_cards) {
+                let tact = TarotAction::Spread(Spread::numbered(num_cards), question, flags);
+                let step = CommandStep::Continue(IambAction::from(tact).into(), ctx.context.clone());
+                return Ok(step);
             } else {
                 let msg = "Card count must be between 1 and 10";
                 return Err(CommandError::Error(msg.into()));
@@ -688,238 +769,372 @@ fn iamb_tarot(desc: CommandDescription, ctx: &mut ProgContext) -> ProgResult {
 
     // Join all arguments into a single string (for multi-word card names)
     let card_arg = args.join(" ");
-    
-    // Check for legacy spread types
-    if card_arg == "threecard" || card_arg == "three-card" {
-        return handle_n_card_spread(3, show_info, show_deepinfo, ctx);
-    }
-    
-    // Check if it's a full path (contains / or starts with ~)
-    let is_path = card_arg.contains('/') || card_arg.starts_with('~');
-    let file_path = if is_path {
-        card_arg.clone()
-    } else {
-        // Look up card in database
-        match tarot_cards::find_card(&card_arg) {
-            Some(card) => {
-                let path = card.image_path();
-                path.to_string_lossy().to_string()
-            },
-            None => {
-                let msg = format!("Card not found: '{}'\nTry: fool, magus, six of swords, science, etc.", card_arg);
-                return Err(CommandError::Error(msg));
-            }
-        }
-    };
 
-    // Upload the card image (with text if info requested)
-    let sact = if show_info || show_deepinfo {
-        // Look up card for info (only if not a direct path)
-        let card_lookup = if is_path {
-            None
-        } else {
-            tarot_cards::find_card(&card_arg)
-        };
-        
-        if let Some(card) = card_lookup {
-            let info_text = format_card_info(card, show_info, show_deepinfo);
-            SendAction::UploadWithText(file_path, info_text)
-        } else {
-            SendAction::Upload(file_path)
-        }
-    } else {
-        SendAction::Upload(file_path)
-    };
-    
-    let iact = IambAction::from(sact);
-    let step = CommandStep::Continue(iact.into(), ctx.context.clone());
+    if card_arg == "today" {
+        let tact = TarotAction::Today(flags);
+        let step = CommandStep::Continue(IambAction::from(tact).into(), ctx.context.clone());
+        return Ok(step);
+    }
 
-    return Ok(step);
-}
+    if card_arg == "daily" {
+        let tact = TarotAction::Daily(flags);
+        let step = CommandStep::Continue(IambAction::from(tact).into(), ctx.context.clone());
+        return Ok(step);
+    }
 
-fn format_card_info(card: &crate::tarot_cards::TarotCard, show_info: bool, show_deepinfo: bool) -> String {
-    let mut text = format!("**{}**", card.card);
-    
-    if let Some(title) = &card.title {
-        if !title.is_empty() {
-            text.push_str(&format!(" ({})", title));
-        }
+    if args[0] == "calendar" {
+        return tarot_calendar(&args[1..], ctx);
     }
-    text.push_str("\n\n");
     
-    if show_info {
-        if let Some(info) = &card.info {
-            text.push_str(info);
-            text.push_str("\n\n");
-        }
+    // Check for named spreads
+    if let Some(spread) = tarot_spreads::find_spread(&card_arg) {
+        let tact = TarotAction::Spread(spread, question, flags);
+        let step = CommandStep::Continue(IambAction::from(tact).into(), ctx.context.clone());
+        return Ok(step);
     }
-    
-    if show_deepinfo {
-        if let Some(deepinfo) = &card.deepinfo {
-            text.push_str(deepinfo);
-            text.push_str("\n\n");
-        }
+
+    if flags.contains(TarotFlags::FACEDOWN) {
+        let msg = "Only spreads can be drawn face down, e.g. :tarot celtic facedown";
+        return Err(CommandError::Error(msg.into()));
     }
-    
-    text
-}
 
-fn handle_n_card_spread(num_cards: usize, show_info: bool, show_deepinfo: bool, ctx: &mut ProgContext) -> ProgResult {
-    use crate::tarot_cards;
-    use crate::tarot_composite;
-    use crate::tarot_api;
-    use std::collections::HashSet;
-    
-    // Get all available cards
-    let all_cards = tarot_cards::get_all_cards();
-    
-    if all_cards.len() < num_cards {
-        let msg = format!("Not enough tarot cards in database. Need {}, have {}", num_cards, all_cards.len());
-        return Err(CommandError::Error(msg));
+    if flags.contains(TarotFlags::PICK) {
+        let msg = "Only spreads can be picked, e.g. :tarot celtic pick";
+        return Err(CommandError::Error(msg.into()));
     }
     
-    // Select N random cards
-    let mut rng = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    
-    let mut selected = HashSet::new();
-    while selected.len() < num_cards {
-        rng = rng.wrapping_mul(1103515245).wrapping_add(12345);
-        let idx = (rng as usize) % all_cards.len();
-        selected.insert(idx);
+    // Check if it's a full path (contains / or starts with ~)
+    if card_arg.contains('/') || card_arg.starts_with('~') {
+        // Upload the image as it is, captioned with the question if one was asked
+        let sact = match question {
+            Some(question) => {
+                SendAction::UploadWithCaption(card_arg, format!("Question: {}", question), None)
+            },
+            None => SendAction::Upload(card_arg),
+        };
+        let step = CommandStep::Continue(IambAction::from(sact).into(), ctx.context.clone());
+        return Ok(step);
     }
-    
-    let selected_indices: Vec<usize> = selected.into_iter().collect();
-    
-    // Get card paths and cards
-    let card_paths: Vec<String> = selected_indices
-        .iter()
-        .map(|&idx| all_cards[idx].image_path().to_string_lossy().to_string())
-        .collect();
-    
-    // Create composite image
-    let composite_path = match tarot_composite::save_composite_to_temp(&card_paths) {
-        Ok(path) => path,
-        Err(e) => {
-            let msg = format!("Failed to create composite image: {}", e);
-            return Err(CommandError::Error(msg));
-        }
-    };
-    
-    // Save reading to API database
-    // TODO: Get actual matrix_id and room_id from context when available
-    // For now, hardcoding to test the integration
-    let matrix_id = "@waqaas:endlessperfect.com".to_string();
-    let room_id = None; // TODO: Get current room ID from context
-    
-    let card_data: Vec<tarot_api::CardData> = selected_indices
-        .iter()
-        .enumerate()
-        .map(|(i, &idx)| tarot_api::CardData {
-            position: i as i32,
-            card_name: all_cards[idx].card.clone(),
-            card_label: Some(format!("Card {}", i + 1)),
-        })
-        .collect();
-    
-    let reading_request = tarot_api::ReadingCreate {
-        matrix_id,
-        room_id,
-        spread_type: num_cards.to_string(),
-        cards: card_data,
-        notes: None,
-        is_private: false,
-    };
-    
-    // Attempt to save the reading (non-blocking, failures are logged but don't stop the reading)
-    match tarot_api::save_reading(reading_request) {
-        Ok(response) => {
-            // Reading saved successfully with ID: response.reading_id
-            // Could add a success message if desired
+
+    // Cards are looked up when posted, since they can be named in the reader's language
+    let tact = TarotAction::Card(card_arg, question, flags);
+    let step = CommandStep::Continue(IambAction::from(tact).into(), ctx.context.clone());
+
+    return Ok(step);
+}
+
+/// Post the decan calendar for the year starting at the March equinox
+fn tarot_calendar(args: &[String], ctx: &mut ProgContext) -> ProgResult {
+    use crate::tarot_calendar;
+    use chrono::Datelike;
+
+    let year = match args {
+        [] => {
+            // Before the equinox, the current zodiacal year began last March
+            let today = chrono::Utc::now().date_naive();
+            if today.ordinal() < 80 {
+                today.year() - 1
+            } else {
+                today.year()
+            }
         },
-        Err(e) => {
-            // Log error but don't fail the reading
-            eprintln!("Failed to save reading to history: {}", e);
-        }
-    }
-    
-    // Create info text if requested
-    let sact = if show_info || show_deepinfo {
-        let mut info_text = String::new();
-        for (i, &idx) in selected_indices.iter().enumerate() {
-            info_text.push_str(&format!("**Card {}:**\n", i + 1));
-            info_text.push_str(&format_card_info(all_cards[idx], show_info, show_deepinfo));
-            info_text.push_str("\n");
-        }
-        SendAction::UploadWithText(composite_path, info_text)
-    } else {
-        SendAction::Upload(composite_path)
+        [year] => {
+            year.parse::<i32>()
+                .ok()
+                .filter(|y| (1900..=2100).contains(y))
+                .ok_or_else(|| CommandError::Error("Usage: :tarot calendar [year]".into()))?
+        },
+        _ => return Err(CommandError::Error("Usage: :tarot calendar [year]".into())),
     };
-    
+
+    let text = tarot_calendar::table_text(&tarot_calendar::decan_table(year));
+    let sact = SendAction::SendText(text);
     let iact = IambAction::from(sact);
     let step = CommandStep::Continue(iact.into(), ctx.context.clone());
-
     return Ok(step);
 }
 
 fn iamb_tarot_history(desc: CommandDescription, ctx: &mut ProgContext) -> ProgResult {
-    use crate::tarot_api;
-    
     let args = desc.arg.strings()?;
-    
-    // TODO: Get user ID from context properly
-    // For now, use a placeholder - this will be fixed when integrating with the store
-    let matrix_id = "@waqaas:endlessperfect.com".to_string();
-    
+
+    // The windows are filled in by their own actions, and everything else looks up the
+    // history under the logged-in user once the arguments are known to be good.
+    let act = match args.first().map(String::as_str) {
+        Some("chart") => {
+            let query =
+                crate::tarot_trends::TrendQuery::parse(&args[1..]).map_err(CommandError::Error)?;
+            TarotAction::Trends(query)
+        },
+        Some("tree") => {
+            let reading_num = match args.get(1) {
+                Some(num) => {
+                    num.parse::<usize>().ok().filter(|n| *n > 0).ok_or_else(|| {
+                        CommandError::Error("Usage: :tarothistory tree [number]".into())
+                    })?
+                },
+                None => 1,
+            };
+            TarotAction::Tree(reading_num)
+        },
+        Some("wheel") => TarotAction::Wheel(wheel_query(&args[1..])?),
+        _ => TarotAction::History(history_query(&args)?),
+    };
+    let iact = IambAction::from(act);
+    let step = CommandStep::Continue(iact.into(), ctx.context.clone());
+    return Ok(step);
+}
+
+/// Parse the arguments of `:tarothistory`
+fn history_query(args: &[String]) -> Result<HistoryQuery, CommandError> {
     if args.is_empty() {
         // Show all readings list
-        return show_history_list(&matrix_id, ctx);
+        return Ok(HistoryQuery::List);
     }
-    
-    match args[0].as_str() {
-        "suits" | "suit" => {
-            return show_attribute_graph(&matrix_id, "suit", ctx);
+
+    let query = match args[0].as_str() {
+        "suits" | "suit" => HistoryQuery::Attribute("suit", attribute_filter("suit", &args[1..])?),
+        "sephira" => HistoryQuery::Attribute("sephira", attribute_filter("sephira", &args[1..])?),
+        "planets" | "planet" => {
+            HistoryQuery::Attribute("planet", attribute_filter("planet", &args[1..])?)
         },
-        "sephira" => {
-            return show_attribute_graph(&matrix_id, "sephira", ctx);
+        "signs" | "sign" => HistoryQuery::Attribute("sign", attribute_filter("sign", &args[1..])?),
+        "elements" | "element" => {
+            HistoryQuery::Attribute("element", attribute_filter("element", &args[1..])?)
         },
-        "planets" | "planet" => {
-            return show_attribute_graph(&matrix_id, "planet", ctx);
+        "trends" | "trend" => {
+            let query =
+                crate::tarot_trends::TrendQuery::parse(&args[1..]).map_err(CommandError::Error)?;
+            HistoryQuery::Trends(query)
         },
-        "signs" | "sign" => {
-            return show_attribute_graph(&matrix_id, "sign", ctx);
+        "since" | "until" | "moon" | "sun" => {
+            let (filter, rest) =
+                crate::tarot_trends::parse_reading_filter(args).map_err(CommandError::Error)?;
+            if let Some(arg) = rest.first() {
+                let msg = format!("Invalid argument: '{}'\nUsage: :tarothistory [since YYYY-MM-DD] [until YYYY-MM-DD] [moon <phase>] [sun <sign>]", arg);
+                return Err(CommandError::Error(msg));
+            }
+            HistoryQuery::Filtered(filter)
         },
-        "elements" | "element" => {
-            return show_attribute_graph(&matrix_id, "element", ctx);
+        "summary" => HistoryQuery::Summary,
+        "search" => {
+            if args.len() < 2 {
+                let msg = "Usage: :tarothistory search <words>";
+                return Err(CommandError::Error(msg.into()));
+            }
+            HistoryQuery::Search(args[1..].to_vec())
         },
-        "summary" => {
-            return show_analytics_summary(&matrix_id, ctx);
+        "topics" | "topic" => HistoryQuery::Topics(args.get(1).cloned()),
+        "export" => {
+            let (path, format) = history_file_args(args, "export")?;
+            HistoryQuery::Export(path.into(), format)
+        },
+        "import" => {
+            let (path, format) = history_file_args(args, "import")?;
+            HistoryQuery::Import(path.into(), format)
         },
         num_str => {
             // Try to parse as reading number
             if let Ok(reading_num) = num_str.parse::<usize>() {
                 let show_info = args.len() > 1 && args[1] == "info";
-                return show_reading_details(&matrix_id, reading_num, show_info, ctx);
+                HistoryQuery::Details(reading_num, show_info)
             } else {
-                let msg = format!("Invalid argument: '{}'\nUsage: :tarothistory [number|suits|sephira|planets|signs|elements|summary]", num_str);
+                let msg = format!("Invalid argument: '{}'\nUsage: :tarothistory [number|suits|sephira|planets|signs|elements|summary|trends|chart|tree|wheel|search|topics|export|import]", num_str);
                 return Err(CommandError::Error(msg));
             }
-        }
+        },
+    };
+
+    Ok(query)
+}
+
+/// Parse the reading filter an attribute graph is limited to
+fn attribute_filter(
+    attribute_type: &str,
+    args: &[String],
+) -> Result<crate::tarot_trends::ReadingFilter, CommandError> {
+    let (filter, rest) =
+        crate::tarot_trends::parse_reading_filter(args).map_err(CommandError::Error)?;
+    if let Some(arg) = rest.first() {
+        let msg = format!("Invalid argument: '{}'\nUsage: :tarothistory {} [since YYYY-MM-DD] [until YYYY-MM-DD] [moon <phase>] [sun <sign>]", arg, attribute_type);
+        return Err(CommandError::Error(msg));
     }
+
+    Ok(filter)
 }
 
-fn show_history_list(matrix_id: &str, _ctx: &mut ProgContext) -> ProgResult {
-    use crate::tarot_api;
-    
-    let history = match tarot_api::get_history(matrix_id) {
-        Ok(h) => h,
-        Err(e) => {
-            let msg = format!("Failed to fetch history: {}", e);
-            return Err(CommandError::Error(msg));
+/// Look something up in a user's reading history, and return the action that shows it
+///
+/// This calls the store, which may block, so run it off the async runtime.
+pub fn tarot_history(
+    store: &dyn ReadingStore,
+    matrix_id: &str,
+    query: HistoryQuery,
+) -> HistoryResult {
+    match query {
+        HistoryQuery::List => show_history_list(store, matrix_id),
+        HistoryQuery::Details(reading_num, show_info) => {
+            show_reading_details(store, matrix_id, reading_num, show_info)
+        },
+        HistoryQuery::Attribute(attribute_type, filter) => {
+            show_attribute_graph(store, matrix_id, attribute_type, &filter)
+        },
+        HistoryQuery::Filtered(filter) => show_history_filtered(store, matrix_id, &filter),
+        HistoryQuery::Trends(query) => show_trends(store, matrix_id, &query),
+        HistoryQuery::Summary => show_analytics_summary(store, matrix_id),
+        HistoryQuery::Search(words) => show_history_search(store, matrix_id, &words),
+        HistoryQuery::Topics(topic) => show_history_topics(store, matrix_id, topic.as_deref()),
+        HistoryQuery::Export(path, format) => export_history(store, matrix_id, &path, format),
+        HistoryQuery::Import(path, format) => import_history(store, matrix_id, &path, format),
+    }
+}
+
+/// Post the text of a `:tarothistory` lookup
+fn posted(text: String) -> HistoryResult {
+    Ok(IambAction::from(SendAction::SendText(text)).into())
+}
+
+/// Format one entry of the reading list, numbered as in `:tarothistory`
+fn format_history_entry(num: usize, reading: &crate::tarot_api::ReadingHistoryItem) -> String {
+    let card_names: Vec<String> = reading.cards.iter().map(|c| c.card_name.clone()).collect();
+    let cards_str = card_names.join(", ");
+
+    let mut entry = format!(
+        "{}. {} - {}-card spread\n",
+        num,
+        reading.reading_date.get(..10).unwrap_or(&reading.reading_date), // Just the date part
+        reading.card_count
+    );
+    if let Some(question) = &reading.question {
+        entry.push_str(&format!("   \"{}\"\n", question));
+    }
+    entry.push_str(&format!("   {}\n", cards_str));
+    if let Some((moon, sun)) = crate::tarot_sky::reading_sky(reading) {
+        entry.push_str(&format!("   {} · Sun in {}\n", moon, sun));
+    }
+    if let Some(quintessence) = &reading.quintessence {
+        entry.push_str(&format!("   Quintessence: {}\n", quintessence));
+    }
+    entry.push('\n');
+    entry
+}
+
+/// Parse the arguments of `:tarothistory wheel`: a reading number, or a reading filter
+fn wheel_query(args: &[String]) -> Result<crate::tarot_wheel::WheelQuery, CommandError> {
+    use crate::tarot_wheel::WheelQuery;
+
+    let usage = "Usage: :tarothistory wheel [number | since YYYY-MM-DD | until YYYY-MM-DD]";
+
+    if let [num] = args {
+        if let Ok(reading_num) = num.parse::<usize>() {
+            if reading_num == 0 {
+                return Err(CommandError::Error(usage.into()));
+            }
+            return Ok(WheelQuery::Reading(reading_num));
         }
+    }
+
+    let (filter, rest) =
+        crate::tarot_trends::parse_reading_filter(args).map_err(CommandError::Error)?;
+    match rest.first() {
+        Some(arg) => Err(CommandError::Error(format!("Invalid argument: '{}'\n{}", arg, usage))),
+        None => Ok(WheelQuery::History(filter)),
+    }
+}
+
+/// Parse the `<path> [json|csv|markdown]` arguments of export and import
+fn history_file_args<'a>(
+    args: &'a [String],
+    cmd: &str,
+) -> Result<(&'a str, crate::tarot_export::ExportFormat), CommandError> {
+    use crate::tarot_export::ExportFormat;
+
+    let usage =
+        || CommandError::Error(format!("Usage: :tarothistory {} <path> [json|csv|markdown]", cmd));
+
+    let path = match args {
+        [_, path] | [_, path, _] => path.as_str(),
+        _ => return Err(usage()),
     };
+
+    // Without an explicit format, go by the file extension and fall back to JSON
+    let format = match args.get(2) {
+        Some(format) => format.parse().map_err(CommandError::Error)?,
+        None => ExportFormat::from_path(std::path::Path::new(path)).unwrap_or(ExportFormat::Json),
+    };
+
+    Ok((path, format))
+}
+
+fn export_history(
+    store: &dyn ReadingStore,
+    matrix_id: &str,
+    path: &str,
+    format: crate::tarot_export::ExportFormat,
+) -> HistoryResult {
+    use crate::tarot_export::{self, ExportedReading};
+
+    let history = fetch_history(store, matrix_id)?;
+    let readings: Vec<ExportedReading> =
+        history.readings.iter().map(ExportedReading::from).collect();
+
+    let content = tarot_export::export_readings(&readings, format).map_err(CommandError::Error)?;
+    std::fs::write(path, content).map_err(|e| {
+        let msg = format!("Failed to write {}: {}", path, e);
+        CommandError::Error(msg)
+    })?;
+
+    let msg = format!("Exported {} readings to {}", readings.len(), path);
+    return Ok(Action::ShowInfoMessage(msg.into()));
+}
+
+fn import_history(
+    store: &dyn ReadingStore,
+    matrix_id: &str,
+    path: &str,
+    format: crate::tarot_export::ExportFormat,
+) -> HistoryResult {
+    use crate::tarot_export::{self, ExportedReading};
+
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        let msg = format!("Failed to read {}: {}", path, e);
+        CommandError::Error(msg)
+    })?;
+    let imported = tarot_export::parse_readings(&content, format).map_err(|e| {
+        let msg = format!("Failed to import {}: {}", path, e);
+        CommandError::Error(msg)
+    })?;
+    let total = imported.len();
+
+    let history = fetch_history(store, matrix_id)?;
+    let existing: Vec<ExportedReading> =
+        history.readings.iter().map(ExportedReading::from).collect();
+    let readings = tarot_export::new_readings(imported, &existing);
+    let added = readings.len();
+
+    for reading in readings {
+        store.save(reading.into_create(matrix_id)).map_err(|e| {
+            let msg = format!("Failed to import reading: {}", e);
+            CommandError::Error(msg)
+        })?;
+    }
+
+    let msg =
+        format!("Imported {} readings from {} ({} duplicates skipped)", added, path, total - added);
+    return Ok(Action::ShowInfoMessage(msg.into()));
+}
+
+fn fetch_history(
+    store: &dyn ReadingStore,
+    matrix_id: &str,
+) -> Result<crate::tarot_api::HistoryResponse, CommandError> {
+    store.history(matrix_id).map_err(|e| {
+        let msg = format!("Failed to fetch history: {}", e);
+        CommandError::Error(msg)
+    })
+}
+
+fn show_history_list(store: &dyn ReadingStore, matrix_id: &str) -> HistoryResult {
+    let history = fetch_history(store, matrix_id)?;
     
     if history.total_readings == 0 {
         let msg = "No tarot readings found.\nUse :tarot to perform a reading!";
@@ -927,39 +1142,168 @@ fn show_history_list(matrix_id: &str, _ctx: &mut ProgContext) -> ProgResult {
     }
     
     let mut output = format!("**Tarot Reading History ({} total readings)**\n\n", history.total_readings);
-    
+
     for (i, reading) in history.readings.iter().enumerate() {
-        let card_names: Vec<String> = reading.cards.iter().map(|c| c.card_name.clone()).collect();
-        let cards_str = card_names.join(", ");
-        
-        output.push_str(&format!("{}. {} - {}-card spread\n", 
-            i + 1, 
-            &reading.reading_date[..10], // Just the date part
-            reading.card_count
-        ));
-        output.push_str(&format!("   {}\n\n", cards_str));
+        output.push_str(&format_history_entry(i + 1, reading));
     }
     
     output.push_str("Use :tarothistory <number> to see details\n");
     output.push_str("Use :tarothistory suits/sephira/etc for analytics");
-    
-    let sact = SendAction::SendText(output);
-    let iact = IambAction::from(sact);
-    let step = CommandStep::Continue(iact.into(), _ctx.context.clone());
-    return Ok(step);
+
+    return posted(output);
 }
 
-fn show_reading_details(matrix_id: &str, reading_num: usize, show_info: bool, _ctx: &mut ProgContext) -> ProgResult {
+fn show_history_search(
+    store: &dyn ReadingStore,
+    matrix_id: &str,
+    words: &[String],
+) -> HistoryResult {
     use crate::tarot_api;
-    
-    // First get the list to find the reading_id
-    let history = match tarot_api::get_history(matrix_id) {
-        Ok(h) => h,
-        Err(e) => {
-            let msg = format!("Failed to fetch history: {}", e);
+
+    let history = fetch_history(store, matrix_id)?;
+
+    let matches: Vec<(usize, &tarot_api::ReadingHistoryItem)> = history
+        .readings
+        .iter()
+        .enumerate()
+        .filter(|(_, reading)| tarot_api::question_matches(reading, words))
+        .collect();
+
+    if matches.is_empty() {
+        let msg = format!("No readings with a question matching '{}'", words.join(" "));
+        return Err(CommandError::Error(msg));
+    }
+
+    let mut output =
+        format!("**Readings matching '{}' ({} found)**\n\n", words.join(" "), matches.len());
+
+    for (i, reading) in matches {
+        output.push_str(&format_history_entry(i + 1, reading));
+    }
+
+    output.push_str("Use :tarothistory <number> to see details");
+
+    return posted(output);
+}
+
+fn show_history_filtered(
+    store: &dyn ReadingStore,
+    matrix_id: &str,
+    filter: &crate::tarot_trends::ReadingFilter,
+) -> HistoryResult {
+    let history = fetch_history(store, matrix_id)?;
+
+    // Keep the numbering of the full list, so `:tarothistory <number>` still works
+    let matches: Vec<(usize, &crate::tarot_api::ReadingHistoryItem)> = history
+        .readings
+        .iter()
+        .enumerate()
+        .filter(|(_, reading)| filter.includes(reading))
+        .collect();
+
+    if matches.is_empty() {
+        let msg = format!("No readings {}", filter.describe());
+        return Err(CommandError::Error(msg));
+    }
+
+    let mut output = format!("**Readings {} ({} found)**\n\n", filter.describe(), matches.len());
+
+    for (i, reading) in matches {
+        output.push_str(&format_history_entry(i + 1, reading));
+    }
+
+    output.push_str("Use :tarothistory <number> to see details");
+
+    return posted(output);
+}
+
+fn show_history_topics(
+    store: &dyn ReadingStore,
+    matrix_id: &str,
+    topic: Option<&str>,
+) -> HistoryResult {
+    use crate::tarot_api;
+
+    let history = fetch_history(store, matrix_id)?;
+
+    if let Some(topic) = topic {
+        let topic = topic.to_lowercase();
+        if !tarot_api::question_topics().contains(&topic.as_str()) {
+            let msg = format!(
+                "Unknown topic: '{}'\nTopics: {}",
+                topic,
+                tarot_api::question_topics().join(", ")
+            );
             return Err(CommandError::Error(msg));
         }
-    };
+
+        let mut output = format!("**Readings about {}**\n\n", topic);
+        let mut found = false;
+
+        for (i, reading) in history.readings.iter().enumerate() {
+            if let Some(question) = &reading.question {
+                if tarot_api::question_topic(question) == topic {
+                    output.push_str(&format_history_entry(i + 1, reading));
+                    found = true;
+                }
+            }
+        }
+
+        if !found {
+            let msg = format!("No readings with a question about {}", topic);
+            return Err(CommandError::Error(msg));
+        }
+
+        output.push_str("Use :tarothistory <number> to see details");
+
+        return posted(output);
+    }
+
+    // Group questions by topic, keeping the topic order stable
+    let mut groups: Vec<(&str, Vec<&str>)> = tarot_api::question_topics()
+        .into_iter()
+        .map(|topic| (topic, Vec::new()))
+        .collect();
+
+    for reading in &history.readings {
+        if let Some(question) = &reading.question {
+            let topic = tarot_api::question_topic(question);
+            if let Some((_, questions)) = groups.iter_mut().find(|(t, _)| *t == topic) {
+                questions.push(question);
+            }
+        }
+    }
+
+    groups.retain(|(_, questions)| !questions.is_empty());
+
+    if groups.is_empty() {
+        let msg = "No readings with a question yet.\nUse :tarot <spread> -- <question> to ask one!";
+        return Err(CommandError::Error(msg.into()));
+    }
+
+    let mut output = "**Reading Questions by Topic**\n\n".to_string();
+
+    for (topic, questions) in groups {
+        output.push_str(&format!("**{}** ({} readings)\n", topic, questions.len()));
+        for question in questions {
+            output.push_str(&format!("  \"{}\"\n", question));
+        }
+        output.push('\n');
+    }
+
+    output.push_str("Use :tarothistory topic <name> to list those readings");
+
+    return posted(output);
+}
+
+fn show_reading_details(
+    store: &dyn ReadingStore,
+    matrix_id: &str,
+    reading_num: usize,
+    show_info: bool,
+) -> HistoryResult {
+    // First get the list to find the reading_id
+    let history = fetch_history(store, matrix_id)?;
     
     if reading_num == 0 || reading_num > history.readings.len() {
         let msg = format!("Invalid reading number. Valid range: 1-{}", history.readings.len());
@@ -967,21 +1311,32 @@ fn show_reading_details(matrix_id: &str, reading_num: usize, show_info: bool, _c
     }
     
     let reading_id = history.readings[reading_num - 1].reading_id;
-    
-    let details = match tarot_api::get_reading_details(reading_id) {
+
+    let details = match store.details(reading_id) {
         Ok(d) => d,
         Err(e) => {
             let msg = format!("Failed to fetch reading details: {}", e);
             return Err(CommandError::Error(msg));
         }
     };
-    
-    let mut output = format!("**Reading #{} - {}**\n", reading_num, &details.reading_date[..10]);
-    output.push_str(&format!("Spread: {}\n\n", details.spread_type));
+
+    let date = details.reading_date.get(..10).unwrap_or(&details.reading_date);
+    let mut output = format!("**Reading #{} - {}**\n", reading_num, date);
+    if let Some(question) = &details.question {
+        output.push_str(&format!("Question: {}\n", question));
+    }
+    output.push_str(&format!("Spread: {}\n", details.spread_type));
+    if let Some((moon, sun)) = crate::tarot_sky::reading_sky(&history.readings[reading_num - 1]) {
+        output.push_str(&format!("Sky: {} · Sun in {}\n", moon, sun));
+    }
+    output.push('\n');
     
     for card in &details.cards {
         let label = card.label.as_ref().map(|l| format!(" ({})", l)).unwrap_or_default();
         output.push_str(&format!("**Card {}{}:** {}\n", card.position + 1, label, card.card_name));
+        if let Some(dignity) = &card.dignity {
+            output.push_str(&format!("Dignity: {}\n", dignity));
+        }
         
         if show_info {
             if let Some(info) = &card.info {
@@ -991,23 +1346,64 @@ fn show_reading_details(matrix_id: &str, reading_num: usize, show_info: bool, _c
                 output.push_str(&format!("{}\n", deepinfo));
             }
         }
-        output.push_str("\n");
+        output.push('\n');
+    }
+
+    if let Some(numerology) = &details.numerology {
+        output.push_str(numerology);
+        output.push('\n');
     }
     
     if let Some(notes) = &details.notes {
         output.push_str(&format!("Notes: {}\n", notes));
     }
-    
-    let sact = SendAction::SendText(output);
-    let iact = IambAction::from(sact);
-    let step = CommandStep::Continue(iact.into(), _ctx.context.clone());
-    return Ok(step);
+
+    return posted(output);
 }
 
-fn show_attribute_graph(matrix_id: &str, attribute_type: &str, _ctx: &mut ProgContext) -> ProgResult {
+fn show_attribute_graph(
+    store: &dyn ReadingStore,
+    matrix_id: &str,
+    attribute_type: &str,
+    filter: &crate::tarot_trends::ReadingFilter,
+) -> HistoryResult {
     use crate::tarot_api;
-    
-    let freq = match tarot_api::get_attribute_frequency(matrix_id, attribute_type) {
+    use crate::tarot_stats;
+    use crate::tarot_trends;
+
+    if !filter.is_empty() {
+        // The server only counts all-time totals, so filtered counts are computed here
+        let history = fetch_history(store, matrix_id)?;
+        let (frequencies, percentages) =
+            tarot_trends::attribute_frequencies(&history.readings, attribute_type, filter);
+        let total: i32 = frequencies.values().sum();
+
+        if total == 0 {
+            let msg = format!("No {} data {}", attribute_type, filter.describe());
+            return Err(CommandError::Error(msg));
+        }
+
+        let mut output = format!(
+            "**{} Distribution, {} ({} total)**\n\n",
+            attribute_type.to_uppercase(),
+            filter.describe(),
+            total
+        );
+        output.push_str(&tarot_api::generate_bar_graph(&frequencies, &percentages, 40));
+        output.push_str("\n\n");
+        output.push_str(
+            &tarot_stats::compare(
+                attribute_type,
+                &frequencies,
+                &tarot_stats::deck_counts(attribute_type),
+            )
+            .to_text(),
+        );
+
+        return posted(output);
+    }
+
+    let freq = match store.attribute_frequency(matrix_id, attribute_type) {
         Ok(f) => f,
         Err(e) => {
             let msg = format!("Failed to fetch {} frequency: {}", attribute_type, e);
@@ -1017,7 +1413,7 @@ fn show_attribute_graph(matrix_id: &str, attribute_type: &str, _ctx: &mut ProgCo
     
     if freq.total_count == 0 {
         let msg = format!("No {} data available yet.\nPerform some readings first!", attribute_type);
-        return Err(CommandError::Error(msg.into()));
+        return Err(CommandError::Error(msg));
     }
     
     let mut output = format!("**{} Distribution ({} total)**\n\n", 
@@ -1027,25 +1423,41 @@ fn show_attribute_graph(matrix_id: &str, attribute_type: &str, _ctx: &mut ProgCo
     
     let graph = tarot_api::generate_bar_graph(&freq.frequencies, &freq.percentages, 40);
     output.push_str(&graph);
-    
-    let sact = SendAction::SendText(output);
-    let iact = IambAction::from(sact);
-    let step = CommandStep::Continue(iact.into(), _ctx.context.clone());
-    return Ok(step);
+    output.push_str("\n\n");
+
+    let stats = tarot_stats::compare(
+        attribute_type,
+        &freq.frequencies,
+        &tarot_stats::deck_counts(attribute_type),
+    );
+    output.push_str(&stats.to_text());
+
+    return posted(output);
 }
 
-fn show_analytics_summary(matrix_id: &str, _ctx: &mut ProgContext) -> ProgResult {
-    use crate::tarot_api;
-    
-    let summary = match tarot_api::get_analytics_summary(matrix_id) {
+fn show_trends(
+    store: &dyn ReadingStore,
+    matrix_id: &str,
+    query: &crate::tarot_trends::TrendQuery,
+) -> HistoryResult {
+    let history = fetch_history(store, matrix_id)?;
+    let report = crate::tarot_trends::trend_report(&history.readings, query);
+
+    return posted(report.to_text());
+}
+
+fn show_analytics_summary(store: &dyn ReadingStore, matrix_id: &str) -> HistoryResult {
+    use crate::tarot_stats;
+
+    let summary = match store.summary(matrix_id) {
         Ok(s) => s,
         Err(e) => {
             let msg = format!("Failed to fetch analytics summary: {}", e);
             return Err(CommandError::Error(msg));
         }
     };
-    
-    let mut output = format!("**Tarot Analytics Summary**\n\n");
+
+    let mut output = "**Tarot Analytics Summary**\n\n".to_string();
     output.push_str(&format!("Total Readings: {}\n", summary.total_readings));
     output.push_str(&format!("Total Cards Drawn: {}\n\n", summary.total_cards_drawn));
     
@@ -1053,7 +1465,7 @@ fn show_analytics_summary(matrix_id: &str, _ctx: &mut ProgContext) -> ProgResult
     for spread in &summary.spread_types {
         output.push_str(&format!("  {} - {} readings\n", spread.spread_type, spread.count));
     }
-    output.push_str("\n");
+    output.push('\n');
     
     for (attr_type, top_items) in &summary.top_attributes {
         if !top_items.is_empty() {
@@ -1061,14 +1473,21 @@ fn show_analytics_summary(matrix_id: &str, _ctx: &mut ProgContext) -> ProgResult
             for item in top_items {
                 output.push_str(&format!("  {} - {}\n", item.value, item.count));
             }
-            output.push_str("\n");
+            output.push('\n');
         }
     }
     
-    let sact = SendAction::SendText(output);
-    let iact = IambAction::from(sact);
-    let step = CommandStep::Continue(iact.into(), _ctx.context.clone());
-    return Ok(step);
+    // Compare the draws with the deck; the summary still posts if history can't be fetched
+    if let Ok(history) = store.history(matrix_id) {
+        output.push_str("**Compared with the deck:**\n");
+        for attr_type in tarot_stats::ATTRIBUTE_TYPES.iter().chain(["card"].iter()) {
+            let stats = tarot_stats::compare_with_deck(&history.readings, attr_type);
+            output.push_str(&format!("  {} - {}\n", attr_type, stats.verdict()));
+        }
+        output.push_str("Small samples often look lopsided by chance; use :tarothistory suits (etc.) for details.\n");
+    }
+
+    return posted(output);
 }
 
 fn iamb_download(desc: CommandDescription, ctx: &mut ProgContext) -> ProgResult {
@@ -1258,6 +1677,83 @@ mod tests {
     use modalkit::actions::WindowAction;
     use modalkit::editing::context::EditContext;
 
+    /// The text a `:tarothistory` lookup posts
+    fn posted_text(res: HistoryResult) -> String {
+        match res {
+            Ok(Action::Application(IambAction::Send(SendAction::SendText(text)))) => text,
+            Ok(_) => panic!("expected text to be posted"),
+            Err(e) => panic!("command failed: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_tarothistory_api() {
+        use crate::tarot_api::{HttpStore, REQUEST_TIMEOUT};
+        use crate::tests::tarot_server::{Fault, StandIn, FIXTURE_USER};
+
+        let server = StandIn::start();
+        let store = HttpStore::new(server.base_url.clone(), REQUEST_TIMEOUT);
+
+        let query = HistoryQuery::Attribute("suit", Default::default());
+        let text = posted_text(tarot_history(&store, FIXTURE_USER, query));
+        assert!(text.starts_with("**SUIT Distribution (4 total)**"));
+        assert!(text.contains("75.0% (3)"));
+
+        let text =
+            posted_text(tarot_history(&store, FIXTURE_USER, HistoryQuery::Details(2, false)));
+        assert!(text.starts_with("**Reading #2 - 2026-03-01**"));
+        assert!(text.contains("**Card 2 (Card 2):** The Star"));
+        assert!(text.contains("Quintessence: The Star"));
+
+        let text = posted_text(tarot_history(&store, FIXTURE_USER, HistoryQuery::Summary));
+        assert!(text.contains("Total Readings: 2"));
+        assert!(text.contains("  daily - 1 readings"));
+
+        server.fail(Fault::Status(500));
+        let msg = "Failed to fetch history: API error: 500 Internal Server Error";
+        let res = tarot_history(&store, FIXTURE_USER, HistoryQuery::List);
+        assert_eq!(res.err(), Some(CommandError::Error(msg.into())));
+    }
+
+    #[test]
+    fn test_tarothistory_memory_store() {
+        use crate::tarot_store::tests::{reading, USER};
+        use crate::tarot_store::MemoryStore;
+
+        let store = MemoryStore::default();
+
+        let msg = "No tarot readings found.\nUse :tarot to perform a reading!";
+        let res = tarot_history(&store, USER, HistoryQuery::List);
+        assert_eq!(res.err(), Some(CommandError::Error(msg.into())));
+
+        store
+            .save(reading(USER, "2026-03-01", &["Six of Swords", "The Star"]))
+            .unwrap();
+        store.save(reading(USER, "2026-04-01", &["Two of Swords"])).unwrap();
+
+        let text = posted_text(tarot_history(&store, USER, HistoryQuery::List));
+        assert!(text.starts_with("**Tarot Reading History (2 total readings)**"));
+        assert!(text.contains("1. 2026-04-01 - 1-card spread"));
+
+        let text = posted_text(tarot_history(&store, USER, HistoryQuery::Details(2, true)));
+        assert!(text.contains("**Card 1 (Card 1):** Six of Swords"));
+        assert!(text.contains("Journey by water"));
+
+        let query = HistoryQuery::Attribute("suit", Default::default());
+        let text = posted_text(tarot_history(&store, USER, query));
+        assert!(text.starts_with("**SUIT Distribution"));
+        assert!(text.contains("Swords"));
+
+        let text = posted_text(tarot_history(&store, USER, HistoryQuery::Summary));
+        assert!(text.contains("Total Readings: 2"));
+        assert!(text.contains("Total Cards Drawn: 3"));
+
+        // Readings are only ever looked up under the user they were saved for
+        let msg = "No tarot readings found.\nUse :tarot to perform a reading!";
+        let res = tarot_history(&store, "@someone.else:example.com", HistoryQuery::List);
+        assert_eq!(res.err(), Some(CommandError::Error(msg.into())));
+    }
+
     #[test]
     fn test_cmd_verify() {
         let mut cmds = setup_commands();
@@ -1844,4 +2340,245 @@ mod tests {
         let res = cmds.input_cmd("keys import foo bar baz", ctx.clone());
         assert_eq!(res, Err(CommandError::InvalidArgument));
     }
+
+    #[test]
+    fn test_cmd_tarot_spread() {
+        use crate::tarot_spreads::Spread;
+
+        let mut cmds = setup_commands();
+        let ctx = EditContext::default();
+
+        let res = cmds.input_cmd("tarot 3", ctx.clone()).unwrap();
+        let act = TarotAction::Spread(Spread::numbered(3), None, TarotFlags::NONE);
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarot 5 info deepinfo", ctx.clone()).unwrap();
+        let flags = TarotFlags::INFO | TarotFlags::DEEPINFO;
+        let act = TarotAction::Spread(Spread::numbered(5), None, flags);
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarot celtic tree", ctx.clone()).unwrap();
+        let act = TarotAction::Spread(Spread::celtic_cross(), None, TarotFlags::TREE);
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds
+            .input_cmd("tarot celtic -- \"Should I take the job?\"", ctx.clone())
+            .unwrap();
+        let question = Some("Should I take the job?".to_string());
+        let act = TarotAction::Spread(Spread::celtic_cross(), question, TarotFlags::NONE);
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        // Unquoted questions are joined back together.
+        let res = cmds.input_cmd("tarot celtic info -- Will it rain?", ctx.clone()).unwrap();
+        let question = Some("Will it rain?".to_string());
+        let act = TarotAction::Spread(Spread::celtic_cross(), question, TarotFlags::INFO);
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        // An empty question is treated as no question.
+        let res = cmds.input_cmd("tarot threecard --", ctx.clone()).unwrap();
+        let act = TarotAction::Spread(Spread::numbered(3), None, TarotFlags::NONE);
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        // Invalid invocations.
+        let res = cmds.input_cmd("tarot 11", ctx.clone());
+        let msg = "Card count must be between 1 and 10";
+        assert_eq!(res, Err(CommandError::Error(msg.into())));
+
+        let res = cmds.input_cmd("tarot -- Will it rain?", ctx.clone());
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_cmd_tarot_facedown() {
+        use crate::tarot_spreads::Spread;
+
+        let mut cmds = setup_commands();
+        let ctx = EditContext::default();
+
+        let res = cmds.input_cmd("tarot celtic facedown info", ctx.clone()).unwrap();
+        let flags = TarotFlags::FACEDOWN | TarotFlags::INFO;
+        let act = TarotAction::Spread(Spread::celtic_cross(), None, flags);
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarot reveal", ctx.clone()).unwrap();
+        let act = TarotAction::Reveal(None);
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarot reveal 3", ctx.clone()).unwrap();
+        let act = TarotAction::Reveal(Some(3));
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarot reveal 0", ctx.clone());
+        let msg = "Usage: :tarot reveal [position]";
+        assert_eq!(res, Err(CommandError::Error(msg.into())));
+
+        let res = cmds.input_cmd("tarot learn", ctx.clone()).unwrap();
+        assert_eq!(res, vec![(IambAction::from(TarotAction::Learn).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarot annotate six of swords", ctx.clone()).unwrap();
+        let act = TarotAction::Annotate("six of swords".into());
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarot annotate", ctx.clone());
+        assert_eq!(res, Err(CommandError::Error("Usage: :tarot annotate <card-name>".into())));
+
+        let res = cmds.input_cmd("tarot learn swords", ctx.clone());
+        assert_eq!(res, Err(CommandError::Error("Usage: :tarot learn".into())));
+
+        let res = cmds.input_cmd("tarot search journey by water", ctx.clone()).unwrap();
+        let act = TarotAction::Search("journey by water".into());
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarot search", ctx.clone());
+        assert_eq!(res, Err(CommandError::Error("Usage: :tarot search <words>".into())));
+
+        let res = cmds.input_cmd("tarot 3 pick", ctx.clone()).unwrap();
+        let act = TarotAction::Spread(Spread::numbered(3), None, TarotFlags::PICK);
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarot fool pick", ctx.clone());
+        let msg = "Only spreads can be picked, e.g. :tarot celtic pick";
+        assert_eq!(res, Err(CommandError::Error(msg.into())));
+
+        let res = cmds.input_cmd("tarot fool facedown", ctx.clone());
+        let msg = "Only spreads can be drawn face down, e.g. :tarot celtic facedown";
+        assert_eq!(res, Err(CommandError::Error(msg.into())));
+    }
+
+    #[test]
+    fn test_cmd_tarot_card_question() {
+        let mut cmds = setup_commands();
+        let ctx = EditContext::default();
+
+        let res = cmds.input_cmd("tarot fool", ctx.clone()).unwrap();
+        let act = TarotAction::Card("fool".into(), None, TarotFlags::NONE);
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarot fool info -- \"What now?\"", ctx.clone()).unwrap();
+        let act = TarotAction::Card("fool".into(), Some("What now?".into()), TarotFlags::INFO);
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarot ~/fool.png -- \"What now?\"", ctx.clone()).unwrap();
+        let act =
+            SendAction::UploadWithCaption("~/fool.png".into(), "Question: What now?".into(), None);
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarot la estrella", ctx.clone()).unwrap();
+        let act = TarotAction::Card("la estrella".into(), None, TarotFlags::NONE);
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+    }
+
+    #[test]
+    fn test_cmd_tarot_today() {
+        let mut cmds = setup_commands();
+        let ctx = EditContext::default();
+
+        let res = cmds.input_cmd("tarot today", ctx.clone()).unwrap();
+        let act = IambAction::from(TarotAction::Today(TarotFlags::NONE));
+        assert_eq!(res, vec![(act.into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarot daily info", ctx.clone()).unwrap();
+        let act = IambAction::from(TarotAction::Daily(TarotFlags::INFO));
+        assert_eq!(res, vec![(act.into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarot calendar 2026", ctx.clone()).unwrap();
+        let text = crate::tarot_calendar::table_text(&crate::tarot_calendar::decan_table(2026));
+        let act = SendAction::SendText(text);
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarot calendar someday", ctx.clone());
+        assert_eq!(res, Err(CommandError::Error("Usage: :tarot calendar [year]".into())));
+    }
+
+    #[test]
+    fn test_cmd_tarothistory_export_args() {
+        let mut cmds = setup_commands();
+        let ctx = EditContext::default();
+
+        let res = cmds.input_cmd("tarothistory export", ctx.clone());
+        let msg = "Usage: :tarothistory export <path> [json|csv|markdown]";
+        assert_eq!(res, Err(CommandError::Error(msg.into())));
+
+        let res = cmds.input_cmd("tarothistory import a.json json extra", ctx.clone());
+        let msg = "Usage: :tarothistory import <path> [json|csv|markdown]";
+        assert_eq!(res, Err(CommandError::Error(msg.into())));
+
+        let res = cmds.input_cmd("tarothistory export a.json xml", ctx.clone());
+        let msg = "Unknown format: 'xml' (expected json, csv or markdown)";
+        assert_eq!(res, Err(CommandError::Error(msg.into())));
+    }
+
+    #[test]
+    fn test_cmd_tarothistory_trends() {
+        use crate::tarot_trends::TrendQuery;
+
+        let mut cmds = setup_commands();
+        let ctx = EditContext::default();
+
+        let res = cmds
+            .input_cmd("tarothistory chart elements weekly since 2026-01-01", ctx.clone())
+            .unwrap();
+        let args: Vec<String> = ["elements", "weekly", "since", "2026-01-01"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let act = TarotAction::Trends(TrendQuery::parse(&args).unwrap());
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarothistory suits since 01/01/2026", ctx.clone());
+        let msg = "Invalid date: '01/01/2026' (expected YYYY-MM-DD)";
+        assert_eq!(res, Err(CommandError::Error(msg.into())));
+
+        let res = cmds.input_cmd("tarothistory suits lately", ctx.clone());
+        let msg = "Invalid argument: 'lately'\nUsage: :tarothistory suit [since YYYY-MM-DD] [until YYYY-MM-DD] [moon <phase>] [sun <sign>]";
+        assert_eq!(res, Err(CommandError::Error(msg.into())));
+
+        let res = cmds.input_cmd("tarothistory trends colours", ctx.clone());
+        assert_eq!(res, Err(CommandError::Error("Unknown trend: 'colours'".into())));
+    }
+
+    #[test]
+    fn test_cmd_tarothistory_tree() {
+        let mut cmds = setup_commands();
+        let ctx = EditContext::default();
+
+        let res = cmds.input_cmd("tarothistory tree", ctx.clone()).unwrap();
+        let act = TarotAction::Tree(1);
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarothistory tree 4", ctx.clone()).unwrap();
+        let act = TarotAction::Tree(4);
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarothistory tree 0", ctx.clone());
+        let msg = "Usage: :tarothistory tree [number]";
+        assert_eq!(res, Err(CommandError::Error(msg.into())));
+    }
+
+    #[test]
+    fn test_cmd_tarothistory_wheel() {
+        use crate::tarot_trends::ReadingFilter;
+        use crate::tarot_wheel::WheelQuery;
+
+        let mut cmds = setup_commands();
+        let ctx = EditContext::default();
+
+        let res = cmds.input_cmd("tarothistory wheel 2", ctx.clone()).unwrap();
+        let act = TarotAction::Wheel(WheelQuery::Reading(2));
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarothistory wheel", ctx.clone()).unwrap();
+        let act = TarotAction::Wheel(WheelQuery::History(ReadingFilter::default()));
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarothistory wheel since 2026-01-01", ctx.clone()).unwrap();
+        let since = chrono::NaiveDate::from_ymd_opt(2026, 1, 1);
+        let act =
+            TarotAction::Wheel(WheelQuery::History(ReadingFilter { since, ..Default::default() }));
+        assert_eq!(res, vec![(IambAction::from(act).into(), ctx.clone())]);
+
+        let res = cmds.input_cmd("tarothistory wheel lately", ctx.clone());
+        let msg = "Invalid argument: 'lately'\nUsage: :tarothistory wheel [number | since YYYY-MM-DD | until YYYY-MM-DD]";
+        assert_eq!(res, Err(CommandError::Error(msg.into())));
+    }
 }
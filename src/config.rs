@@ -10,7 +10,8 @@ use std::io::{BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::process;
 
-use clap::Parser;
+use chrono::NaiveTime;
+use clap::{Parser, Subcommand};
 use matrix_sdk::authentication::matrix::MatrixSession;
 use matrix_sdk::ruma::{OwnedDeviceId, OwnedRoomAliasId, OwnedRoomId, OwnedUserId, UserId};
 use ratatui::style::{Color, Modifier as StyleModifier, Style};
@@ -31,6 +32,7 @@ use super::base::{
     SortFieldUser,
     SortOrder,
 };
+use super::tarot_cli::TarotCommand;
 
 type Macros = HashMap<VimModes, HashMap<Keys, Keys>>;
 
@@ -135,6 +137,19 @@ pub struct Iamb {
 
     #[clap(short = 'C', long, value_parser)]
     pub config_directory: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    pub command: Option<IambCommand>,
+}
+
+/// Commands that run without starting the terminal UI
+#[derive(Subcommand)]
+pub enum IambCommand {
+    /// Draw spreads, look up cards and read the reading history from scripts
+    Tarot {
+        #[clap(subcommand)]
+        command: TarotCommand,
+    },
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -265,6 +280,38 @@ impl<'de> Deserialize<'de> for LogLevel {
     }
 }
 
+/// A time of day for a reminder, written as "HH:MM" in local time
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ReminderTime(pub NaiveTime);
+pub struct ReminderTimeVisitor;
+
+impl Visitor<'_> for ReminderTimeVisitor {
+    type Value = ReminderTime;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a time of day (e.g. \"08:30\")")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: SerdeError,
+    {
+        NaiveTime::parse_from_str(value, "%H:%M")
+            .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S"))
+            .map(ReminderTime)
+            .map_err(|_| E::custom("could not parse into a time of day"))
+    }
+}
+
+impl<'de> Deserialize<'de> for ReminderTime {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(ReminderTimeVisitor)
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct UserColor(pub Color);
 pub struct UserColorVisitor;
@@ -487,6 +534,111 @@ pub struct Notifications {
     pub sound_hint: Option<String>,
 }
 
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+pub struct Tarot {
+    #[serde(default)]
+    pub prompt_question: bool,
+    #[serde(default)]
+    pub decan_notify: bool,
+    #[serde(default)]
+    pub daily_reminder: Option<ReminderTime>,
+    #[serde(default)]
+    pub composite: TarotComposite,
+    #[serde(default)]
+    pub sync_annotations: bool,
+    #[serde(default)]
+    pub history: HistoryBackend,
+    #[serde(default)]
+    pub history_room: Option<OwnedRoomId>,
+    #[serde(default)]
+    pub history_passphrase: Option<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+/// Where readings are saved and the reading history is read from
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryBackend {
+    /// The tarot API server
+    #[default]
+    Http,
+    /// A journal in the profile's data directory
+    Sqlite,
+    /// Kept only until iamb exits
+    Memory,
+    /// Encrypted in the Matrix account's data
+    Matrix,
+}
+
+/// Image format used for the composites posted with spreads
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CompositeFormat {
+    #[default]
+    Jpeg,
+    Webp,
+    Png,
+}
+
+impl CompositeFormat {
+    /// The format usually saved with a file extension
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_lowercase().as_str() {
+            "jpg" | "jpeg" => Some(CompositeFormat::Jpeg),
+            "webp" => Some(CompositeFormat::Webp),
+            "png" => Some(CompositeFormat::Png),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CompositeFormat::Jpeg => "jpg",
+            CompositeFormat::Webp => "webp",
+            CompositeFormat::Png => "png",
+        }
+    }
+
+    pub fn mime(&self) -> mime::Mime {
+        match self {
+            CompositeFormat::Jpeg => mime::IMAGE_JPEG,
+            CompositeFormat::Webp => "image/webp".parse().expect("valid mime type"),
+            CompositeFormat::Png => mime::IMAGE_PNG,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct TarotComposite {
+    /// Width in pixels that each card is scaled to before it is laid out
+    #[serde(default = "default_card_width")]
+    pub card_width: u32,
+    #[serde(default)]
+    pub format: CompositeFormat,
+    /// Largest composite to upload, in bytes, or 0 for no limit
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: usize,
+}
+
+impl Default for TarotComposite {
+    fn default() -> Self {
+        TarotComposite {
+            card_width: default_card_width(),
+            format: CompositeFormat::default(),
+            max_bytes: default_max_bytes(),
+        }
+    }
+}
+
+fn default_card_width() -> u32 {
+    240
+}
+
+fn default_max_bytes() -> usize {
+    1_000_000
+}
+
 #[derive(Clone)]
 pub struct ImagePreviewValues {
     pub size: ImagePreviewSize,
@@ -581,6 +733,7 @@ pub struct TunableValues {
     pub user_gutter_width: usize,
     pub external_edit_file_suffix: String,
     pub tabstop: usize,
+    pub tarot: Tarot,
 }
 
 #[derive(Clone, Default, Deserialize)]
@@ -609,6 +762,7 @@ pub struct Tunables {
     pub user_gutter_width: Option<usize>,
     pub external_edit_file_suffix: Option<String>,
     pub tabstop: Option<usize>,
+    pub tarot: Option<Tarot>,
 }
 
 impl Tunables {
@@ -643,6 +797,7 @@ impl Tunables {
                 .external_edit_file_suffix
                 .or(other.external_edit_file_suffix),
             tabstop: self.tabstop.or(other.tabstop),
+            tarot: self.tarot.or(other.tarot),
         }
     }
 
@@ -673,6 +828,7 @@ impl Tunables {
                 .external_edit_file_suffix
                 .unwrap_or_else(|| ".md".to_string()),
             tabstop: self.tabstop.unwrap_or(4),
+            tarot: self.tarot.unwrap_or_default(),
         }
     }
 }
@@ -835,6 +991,9 @@ pub struct ApplicationSettings {
     pub session_json_old: PathBuf,
     pub sled_dir: PathBuf,
     pub sqlite_dir: PathBuf,
+    pub tarot_learn_json: PathBuf,
+    pub tarot_annotations_json: PathBuf,
+    pub tarot_history_db: PathBuf,
     pub profile_name: String,
     pub profile: ProfileConfig,
     pub tunables: TunableValues,
@@ -960,6 +1119,15 @@ impl ApplicationSettings {
         let mut session_json = profile_data_dir.clone();
         session_json.push("session.json");
 
+        let mut tarot_learn_json = profile_data_dir.clone();
+        tarot_learn_json.push("tarot_learn.json");
+
+        let mut tarot_annotations_json = profile_data_dir.clone();
+        tarot_annotations_json.push("tarot_annotations.json");
+
+        let mut tarot_history_db = profile_data_dir.clone();
+        tarot_history_db.push("tarot_history.sqlite3");
+
         let mut session_json_old = profile_dir;
         session_json_old.push("session.json");
 
@@ -977,6 +1145,9 @@ impl ApplicationSettings {
             session_json,
             session_json_old,
             sqlite_dir,
+            tarot_learn_json,
+            tarot_annotations_json,
+            tarot_history_db,
             profile_name,
             profile,
             tunables,
@@ -1317,6 +1488,40 @@ mod tests {
         assert!(serde_json::from_str::<NotifyVia>(r#""""#).is_err());
     }
 
+    #[test]
+    fn test_parse_reminder_time() {
+        let time = |h, m| ReminderTime(NaiveTime::from_hms_opt(h, m, 0).unwrap());
+
+        assert_eq!(time(8, 30), serde_json::from_str(r#""08:30""#).unwrap());
+        assert_eq!(time(21, 5), serde_json::from_str(r#""21:05:00""#).unwrap());
+        assert!(serde_json::from_str::<ReminderTime>(r#""25:00""#).is_err());
+        assert!(serde_json::from_str::<ReminderTime>(r#""morning""#).is_err());
+    }
+
+    #[test]
+    fn test_parse_tarot_composite() {
+        let tarot: Tarot = serde_json::from_str(r#"{"composite": {"format": "webp"}}"#).unwrap();
+        assert_eq!(tarot.composite.format, CompositeFormat::Webp);
+        assert_eq!(tarot.composite.card_width, 240);
+        assert_eq!(tarot.composite.max_bytes, 1_000_000);
+
+        let tarot: Tarot = serde_json::from_str("{}").unwrap();
+        assert_eq!(tarot.composite, TarotComposite::default());
+        assert!(!tarot.sync_annotations);
+        assert_eq!(tarot.history, HistoryBackend::Http);
+        assert_eq!(tarot.history_room, None);
+        assert_eq!(tarot.language, None);
+
+        let tarot: Tarot = serde_json::from_str(
+            r#"{"history": "matrix", "history_room": "!journal:example.com", "history_passphrase": "hunter2"}"#,
+        )
+        .unwrap();
+        assert_eq!(tarot.history, HistoryBackend::Matrix);
+        assert_eq!(tarot.history_room.unwrap().as_str(), "!journal:example.com");
+        assert_eq!(tarot.history_passphrase.as_deref(), Some("hunter2"));
+        assert!(serde_json::from_str::<CompositeFormat>(r#""gif""#).is_err());
+    }
+
     #[test]
     fn test_load_example_config_toml() {
         let path = PathBuf::from("config.example.toml");
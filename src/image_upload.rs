@@ -0,0 +1,264 @@
+//! Images held in memory for upload, with the metadata clients use to lay them out
+//!
+//! Clients size an image's placeholder from its width and height, paint its
+//! [BlurHash](https://blurha.sh) while it downloads, and show the thumbnail in the timeline
+//! instead of fetching a large original.
+use std::f64::consts::PI;
+use std::path::Path;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use mime::Mime;
+
+/// Images bigger than this either way are sent with a thumbnail
+const THUMBNAIL_SIZE: (u32, u32) = (800, 600);
+
+const THUMBNAIL_QUALITY: u8 = 80;
+
+/// BlurHash components across and down
+const BLURHASH_COMPONENTS: (u32, u32) = (4, 3);
+
+/// Images are shrunk to this width before their BlurHash is worked out
+const BLURHASH_SAMPLE_WIDTH: u32 = 64;
+
+const BASE83: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+/// A smaller copy of an image, encoded as a JPEG
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Thumbnail {
+    pub bytes: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// An encoded image and what clients need to know to show it
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ImageUpload {
+    pub name: String,
+    pub mime: Mime,
+    pub bytes: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub blurhash: String,
+    pub thumbnail: Option<Thumbnail>,
+}
+
+impl ImageUpload {
+    /// Describe an encoded image, given the pixels it was encoded from
+    pub fn new(name: impl Into<String>, mime: Mime, bytes: Vec<u8>, img: &DynamicImage) -> Self {
+        ImageUpload {
+            name: name.into(),
+            mime,
+            bytes,
+            width: img.width(),
+            height: img.height(),
+            blurhash: blurhash(img),
+            thumbnail: thumbnail(img),
+        }
+    }
+
+    /// Describe an encoded image, decoding it to find its size and BlurHash
+    pub fn from_bytes(name: impl Into<String>, mime: Mime, bytes: Vec<u8>) -> Result<Self, String> {
+        let name = name.into();
+        let img = image::load_from_memory(&bytes)
+            .map_err(|e| format!("Failed to load image {}: {}", name, e))?;
+
+        Ok(ImageUpload::new(name, mime, bytes, &img))
+    }
+
+    /// Read an image file into memory
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let path = Path::new(path);
+        let bytes = std::fs::read(path)
+            .map_err(|e| format!("Failed to read image {}: {}", path.display(), e))?;
+
+        let mime = mime_guess::from_path(path).first_or(mime::APPLICATION_OCTET_STREAM);
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "image".to_string());
+
+        ImageUpload::from_bytes(name, mime, bytes)
+    }
+}
+
+/// Make a thumbnail of an image too big to show in the timeline as it is
+fn thumbnail(img: &DynamicImage) -> Option<Thumbnail> {
+    let (width, height) = THUMBNAIL_SIZE;
+
+    if img.width() <= width && img.height() <= height {
+        return None;
+    }
+
+    let small = img.resize(width, height, FilterType::Triangle);
+    let mut bytes: Vec<u8> = Vec::new();
+    JpegEncoder::new_with_quality(&mut bytes, THUMBNAIL_QUALITY)
+        .encode_image(&small.to_rgb8())
+        .ok()?;
+
+    Some(Thumbnail {
+        bytes,
+        width: small.width(),
+        height: small.height(),
+    })
+}
+
+fn srgb_to_linear(value: u8) -> f64 {
+    let v = f64::from(value) / 255.0;
+
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f64) -> u32 {
+    let v = value.clamp(0.0, 1.0);
+
+    if v <= 0.003_130_8 {
+        (v * 12.92 * 255.0 + 0.5) as u32
+    } else {
+        ((1.055 * v.powf(1.0 / 2.4) - 0.055) * 255.0 + 0.5) as u32
+    }
+}
+
+fn push_base83(hash: &mut String, value: u32, digits: u32) {
+    for i in (0..digits).rev() {
+        let digit = (value / 83u32.pow(i)) % 83;
+        hash.push(char::from(BASE83[digit as usize]));
+    }
+}
+
+/// Encode the BlurHash of an image
+pub fn blurhash(img: &DynamicImage) -> String {
+    let sample = if img.width() > BLURHASH_SAMPLE_WIDTH {
+        img.resize(BLURHASH_SAMPLE_WIDTH, u32::MAX, FilterType::Triangle)
+    } else {
+        img.clone()
+    };
+    let rgb = sample.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let linear: Vec<[f64; 3]> = rgb
+        .pixels()
+        .map(|p| {
+            [
+                srgb_to_linear(p[0]),
+                srgb_to_linear(p[1]),
+                srgb_to_linear(p[2]),
+            ]
+        })
+        .collect();
+
+    // The image's cosine transform, over as many components as are encoded
+    let (nx, ny) = BLURHASH_COMPONENTS;
+    let mut factors: Vec<[f64; 3]> = Vec::with_capacity((nx * ny) as usize);
+
+    for j in 0..ny {
+        for i in 0..nx {
+            let norm = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+            let mut sum = [0.0; 3];
+
+            for y in 0..height {
+                for x in 0..width {
+                    let basis = (PI * f64::from(i * x) / f64::from(width)).cos() *
+                        (PI * f64::from(j * y) / f64::from(height)).cos();
+                    let pixel = linear[(y * width + x) as usize];
+
+                    for c in 0..3 {
+                        sum[c] += basis * pixel[c];
+                    }
+                }
+            }
+
+            let scale = norm / f64::from(width * height);
+            factors.push(sum.map(|v| v * scale));
+        }
+    }
+
+    let (dc, ac) = factors.split_first().expect("at least one component");
+    let max_ac = ac.iter().flatten().fold(0.0f64, |max, v| max.max(v.abs()));
+    let quantised_max = (max_ac * 166.0 - 0.5).floor().clamp(0.0, 82.0) as u32;
+    let max_value = f64::from(quantised_max + 1) / 166.0;
+
+    let mut hash = String::new();
+    push_base83(&mut hash, (nx - 1) + (ny - 1) * 9, 1);
+    push_base83(&mut hash, quantised_max, 1);
+    push_base83(
+        &mut hash,
+        (linear_to_srgb(dc[0]) << 16) + (linear_to_srgb(dc[1]) << 8) + linear_to_srgb(dc[2]),
+        4,
+    );
+
+    for component in ac {
+        let quantise = |v: f64| {
+            let v = v / max_value;
+            (v.signum() * v.abs().sqrt() * 9.0 + 9.5).floor().clamp(0.0, 18.0) as u32
+        };
+
+        push_base83(
+            &mut hash,
+            quantise(component[0]) * 19 * 19 + quantise(component[1]) * 19 + quantise(component[2]),
+            2,
+        );
+    }
+
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_blurhash() {
+        // The basis isn't centred on pixels, so even a flat image keeps a little of the
+        // vertical components, as with the reference encoder; its colour is exact though
+        let white = DynamicImage::ImageRgb8(RgbImage::from_pixel(100, 80, Rgb([255, 255, 255])));
+        assert_eq!(&blurhash(&white)[2..6], "TSUA");
+
+        let black = DynamicImage::ImageRgb8(RgbImage::from_pixel(10, 10, Rgb([0, 0, 0])));
+        assert_eq!(blurhash(&black), format!("L00000{}", "fQ".repeat(11)));
+
+        // Left half black and right half white, which puts detail in the horizontal components
+        let split = RgbImage::from_fn(64, 48, |x, _| {
+            if x < 32 {
+                Rgb([0, 0, 0])
+            } else {
+                Rgb([255; 3])
+            }
+        });
+        let hash = blurhash(&DynamicImage::ImageRgb8(split));
+        assert_eq!(hash.len(), 28);
+        assert!(hash.starts_with('L'));
+        assert_ne!(&hash[6..8], "fQ");
+    }
+
+    #[test]
+    fn test_thumbnail() {
+        let small = DynamicImage::new_rgb8(360, 528);
+        assert!(thumbnail(&small).is_none());
+
+        let composite = DynamicImage::new_rgb8(1280, 754);
+        let thumb = thumbnail(&composite).unwrap();
+        assert_eq!((thumb.width, thumb.height), (800, 471));
+        assert!(image::load_from_memory(&thumb.bytes).is_ok());
+    }
+
+    #[test]
+    fn test_image_upload() {
+        let card = crate::tarot_cards::card_back_path();
+        let upload = ImageUpload::from_file(&card.to_string_lossy()).unwrap();
+
+        assert_eq!(upload.name, "card_back.jpg");
+        assert_eq!(upload.mime, mime::IMAGE_JPEG);
+        assert_eq!((upload.width, upload.height), (360, 528));
+        assert_eq!(upload.blurhash.len(), 28);
+        assert!(upload.thumbnail.is_none());
+
+        assert!(ImageUpload::from_file("/nonexistent/card.jpg").is_err());
+    }
+}
@@ -27,6 +27,7 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use clap::Parser;
+use edit::{edit_with_builder as external_edit, Builder};
 use matrix_sdk::crypto::encrypt_room_key_export;
 use matrix_sdk::ruma::api::client::error::ErrorKind;
 use matrix_sdk::ruma::OwnedUserId;
@@ -71,14 +72,30 @@ use ratatui::{
 mod base;
 mod commands;
 mod config;
+mod image_upload;
 mod keybindings;
 mod message;
 mod notifications;
 mod preview;
 mod sled_export;
+mod tarot_annotations;
 mod tarot_api;
+mod tarot_calendar;
 mod tarot_cards;
+mod tarot_cli;
 mod tarot_composite;
+mod tarot_dignities;
+mod tarot_export;
+mod tarot_learn;
+mod tarot_numerology;
+mod tarot_search;
+mod tarot_sky;
+mod tarot_spreads;
+mod tarot_stats;
+mod tarot_store;
+mod tarot_tree;
+mod tarot_trends;
+mod tarot_wheel;
 mod util;
 mod windows;
 mod worker;
@@ -101,8 +118,17 @@ use crate::{
         ProgramAction,
         ProgramContext,
         ProgramStore,
+        SendAction,
+        TarotAction,
+        TarotFlags,
     },
-    config::{ApplicationSettings, Iamb},
+    config::{ApplicationSettings, HistoryBackend, Iamb, IambCommand},
+    tarot_annotations::Annotations,
+    tarot_api::ReadingHistoryItem,
+    tarot_cards::{TarotCard, Translation},
+    tarot_composite::CompositeOptions,
+    tarot_search::SearchResults,
+    tarot_store::MatrixStore,
     windows::IambWindow,
     worker::{create_room, ClientWorker, LoginStyle, Requester},
 };
@@ -608,6 +634,13 @@ impl Application {
                 self.screen.current_window_mut()?.send_command(act, ctx, store).await?
             },
 
+            IambAction::Tarot(act) => {
+                let acts = self.tarot_command(act, ctx, store).await?;
+                self.action_prepend(acts);
+
+                None
+            },
+
             IambAction::OpenLink(url) => {
                 tokio::task::spawn_blocking(move || {
                     return open::that(url);
@@ -669,6 +702,323 @@ impl Application {
         }
     }
 
+    /// The room shown in the current window, which readings are saved with
+    fn current_room_id(&self) -> Option<String> {
+        match self.screen.current_window().map(|w| w.id()) {
+            Some(IambId::Room(room_id, _)) => Some(room_id.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Who readings drawn now are for, posted to the room shown in the current window
+    fn querent(&self, store: &ProgramStore) -> tarot_spreads::Querent {
+        tarot_spreads::Querent {
+            matrix_id: store.application.settings.profile.user_id.to_string(),
+            room_id: self.current_room_id(),
+            notes: store.application.tarot_annotations.clone(),
+            store: store.application.tarot_store.clone(),
+            language: store.application.tarot_language.clone(),
+        }
+    }
+
+    async fn tarot_command(
+        &mut self,
+        action: TarotAction,
+        ctx: ProgramContext,
+        store: &mut ProgramStore,
+    ) -> IambResult<Vec<(Action<IambInfo>, ProgramContext)>> {
+        match action {
+            TarotAction::Spread(spread, question, flags) => {
+                let question = if question.is_none() &&
+                    store.application.settings.tunables.tarot.prompt_question
+                {
+                    // Ask for the question in the external editor.
+                    self.dirty = true;
+
+                    let question = external_edit(String::new(), Builder::new().suffix(".txt"))?;
+                    let question = question.trim();
+
+                    (!question.is_empty()).then(|| question.to_string())
+                } else {
+                    question
+                };
+
+                if flags.contains(TarotFlags::PICK) {
+                    let room_id = match self.screen.current_window_mut()?.id() {
+                        IambId::Room(room_id, _) => room_id,
+                        _ => {
+                            return Err(UIError::Failure(
+                                "Pick cards from the room to post them in".into(),
+                            ))
+                        },
+                    };
+
+                    let flags = flags - TarotFlags::PICK;
+                    let session = tarot_spreads::PickSession::new(spread, question, flags, room_id);
+                    store.application.tarot_pick = Some(session);
+
+                    let target = OpenTarget::Application(IambId::TarotPick);
+                    let action = WindowAction::Switch(target);
+
+                    return Ok(vec![(action.into(), ctx)]);
+                }
+
+                let querent = self.querent(store);
+                let composite = CompositeOptions::from_settings(&store.application.settings);
+                let sacts = tokio::task::spawn_blocking(move || {
+                    tarot_spreads::perform_reading(&querent, &spread, question, flags, &composite)
+                })
+                .await
+                .map_err(|e| UIError::Failure(e.to_string()))?
+                .map_err(UIError::Failure)?;
+
+                let acts = sacts
+                    .into_iter()
+                    .map(|sact| (IambAction::from(sact).into(), ctx.clone()))
+                    .collect();
+
+                Ok(acts)
+            },
+            TarotAction::Card(name, question, flags) => {
+                let lang = &store.application.tarot_language;
+                let card = find_card(lang, &name)?;
+                let notes = &store.application.tarot_annotations;
+                let sact = tarot_spreads::post_card(card, question, flags, notes, lang);
+
+                Ok(vec![(IambAction::from(sact).into(), ctx)])
+            },
+            TarotAction::Today(flags) => {
+                let today = tarot_calendar::Today::at(chrono::Utc::now().naive_utc())
+                    .ok_or_else(|| UIError::Failure("Couldn't work out today's decan".into()))?;
+                let notes = &store.application.tarot_annotations;
+                let lang = &store.application.tarot_language;
+                let sact = tarot_spreads::post_today(&today, flags, notes, lang);
+
+                Ok(vec![(IambAction::from(sact).into(), ctx)])
+            },
+            TarotAction::History(query) => {
+                let matrix_id = store.application.settings.profile.user_id.to_string();
+                let readings = store.application.tarot_store.clone();
+                let act = tokio::task::spawn_blocking(move || {
+                    commands::tarot_history(&*readings, &matrix_id, query)
+                })
+                .await
+                .map_err(|e| UIError::Failure(e.to_string()))??;
+
+                Ok(vec![(act, ctx)])
+            },
+            TarotAction::Daily(flags) => {
+                let readings = reading_history(store).await?;
+
+                let today = chrono::Local::now().date_naive();
+                let sacts = match tarot_spreads::daily_reading(&readings, today) {
+                    Some(reading) => {
+                        let notes = &store.application.tarot_annotations;
+                        let lang = &store.application.tarot_language;
+                        vec![tarot_spreads::repeat_daily(reading, flags, notes, lang)
+                            .map_err(UIError::Failure)?]
+                    },
+                    None => {
+                        let querent = self.querent(store);
+                        let composite =
+                            CompositeOptions::from_settings(&store.application.settings);
+
+                        tokio::task::spawn_blocking(move || {
+                            let spread = tarot_spreads::Spread::daily();
+                            tarot_spreads::perform_reading(
+                                &querent, &spread, None, flags, &composite,
+                            )
+                        })
+                        .await
+                        .map_err(|e| UIError::Failure(e.to_string()))?
+                        .map_err(UIError::Failure)?
+                    },
+                };
+
+                let acts = sacts
+                    .into_iter()
+                    .map(|sact| (IambAction::from(sact).into(), ctx.clone()))
+                    .collect();
+
+                Ok(acts)
+            },
+            TarotAction::Learn => {
+                if store.application.tarot_learn.is_none() {
+                    let path = store.application.settings.tarot_learn_json.clone();
+                    let session =
+                        tarot_learn::LearnSession::load(path).map_err(UIError::Failure)?;
+                    store.application.tarot_learn = Some(session);
+                }
+
+                let target = OpenTarget::Application(IambId::TarotLearn);
+                let action = WindowAction::Switch(target);
+
+                Ok(vec![(action.into(), ctx)])
+            },
+            TarotAction::Search(query) => {
+                let hits = store.application.search_cards(&query);
+                store.application.tarot_search = Some(SearchResults { query, hits });
+
+                let target = OpenTarget::Application(IambId::TarotSearch);
+                let action = WindowAction::Switch(target);
+
+                Ok(vec![(action.into(), ctx)])
+            },
+            TarotAction::Annotate(name) => {
+                let card = find_card(&store.application.tarot_language, &name)?;
+                let settings = &store.application.settings;
+                let path = settings.tarot_annotations_json.clone();
+                let sync = settings.tunables.tarot.sync_annotations;
+                let client = store.application.worker.client.clone();
+
+                // Start from the newest copy, in case the note was edited on another device.
+                let mut notes = store.application.tarot_annotations.clone();
+                if sync {
+                    if let Some(synced) =
+                        tarot_annotations::pull(&client).await.map_err(UIError::Failure)?
+                    {
+                        notes.merge(&synced);
+                    }
+                }
+
+                self.dirty = true;
+
+                let old = notes.get(card).unwrap_or_default().to_string();
+                let text = external_edit(old.clone(), Builder::new().suffix(".md"))?;
+
+                if text.trim() != old {
+                    let now = chrono::Utc::now().timestamp_millis() as u64;
+                    notes.set(card, &text, now);
+                }
+
+                notes.save(&path).map_err(UIError::Failure)?;
+                store.application.set_tarot_annotations(notes.clone());
+
+                if sync {
+                    tarot_annotations::push(&client, &notes).await.map_err(UIError::Failure)?;
+                }
+
+                Ok(vec![])
+            },
+            TarotAction::Picked => {
+                let Some(session) = store.application.tarot_pick.take() else {
+                    return Err(UIError::Failure("No spread is being picked".into()));
+                };
+
+                // The cards are posted to the room they were picked for
+                let querent = tarot_spreads::Querent {
+                    room_id: Some(session.room_id.to_string()),
+                    ..self.querent(store)
+                };
+                let composite = CompositeOptions::from_settings(&store.application.settings);
+                let sacts = tokio::task::spawn_blocking(move || {
+                    tarot_spreads::post_reading(
+                        &querent,
+                        &session.spread,
+                        session.cards(),
+                        session.question.clone(),
+                        session.flags,
+                        &composite,
+                    )
+                })
+                .await
+                .map_err(|e| UIError::Failure(e.to_string()))?
+                .map_err(UIError::Failure)?;
+
+                let acts = sacts
+                    .into_iter()
+                    .map(|sact| (IambAction::from(sact).into(), ctx.clone()))
+                    .collect();
+
+                Ok(acts)
+            },
+            TarotAction::Reveal(position) => {
+                let room_id = match self.screen.current_window_mut()?.id() {
+                    IambId::Room(room_id, _) => room_id,
+                    _ => {
+                        return Err(UIError::Failure(
+                            "Reveal cards from the room they were drawn in".into(),
+                        ))
+                    },
+                };
+
+                let Some(facedown) = store.application.tarot_facedown.get(&room_id) else {
+                    let msg =
+                        "No face-down spread in this room. Draw one with :tarot <spread> facedown";
+                    return Err(UIError::Failure(msg.into()));
+                };
+                if facedown.event_id.is_none() {
+                    return Err(UIError::Failure(
+                        "The face-down spread hasn't been posted yet".into(),
+                    ));
+                }
+
+                // Turn the card over on a copy, which replaces the spread once the edit is sent
+                let mut facedown = facedown.clone();
+                facedown.reveal(position).map_err(UIError::Failure)?;
+
+                let composite = CompositeOptions::from_settings(&store.application.settings);
+                let cards = facedown.composite_paths();
+                let image = tokio::task::spawn_blocking(move || {
+                    tarot_composite::create_composite_spread(&cards, &composite)
+                })
+                .await
+                .map_err(|e| UIError::Failure(e.to_string()))?
+                .map_err(|e| UIError::Failure(format!("Failed to create composite image: {e}")))?;
+                let sacts = vec![SendAction::RevealFaceDown(
+                    Box::new(image),
+                    Box::new(facedown),
+                )];
+
+                let acts = sacts
+                    .into_iter()
+                    .map(|sact| (IambAction::from(sact).into(), ctx.clone()))
+                    .collect();
+
+                Ok(acts)
+            },
+            TarotAction::Trends(query) => {
+                let readings = reading_history(store).await?;
+                let report = tarot_trends::trend_report(&readings, &query);
+                store.application.tarot_trends = Some(report);
+
+                let target = OpenTarget::Application(IambId::TarotTrends);
+                let action = WindowAction::Switch(target);
+
+                Ok(vec![(action.into(), ctx)])
+            },
+            TarotAction::Tree(reading_num) => {
+                let readings = reading_history(store).await?;
+                let (title, cards) = lookup_reading(&readings, reading_num)?;
+                let highlights = tarot_tree::tree_highlights(&cards);
+                store.application.tarot_tree = Some(tarot_tree::TreeView { title, highlights });
+
+                let target = OpenTarget::Application(IambId::TarotTree);
+                let action = WindowAction::Switch(target);
+
+                Ok(vec![(action.into(), ctx)])
+            },
+            TarotAction::Wheel(query) => {
+                let readings = reading_history(store).await?;
+                let view = match query {
+                    tarot_wheel::WheelQuery::History(filter) => {
+                        tarot_wheel::history_wheel(&readings, &filter)
+                    },
+                    tarot_wheel::WheelQuery::Reading(reading_num) => {
+                        let (title, cards) = lookup_reading(&readings, reading_num)?;
+                        tarot_wheel::reading_wheel(title, &cards)
+                    },
+                };
+                store.application.tarot_wheel = Some(view);
+
+                let target = OpenTarget::Application(IambId::TarotWheel);
+                let action = WindowAction::Switch(target);
+
+                Ok(vec![(action.into(), ctx)])
+            },
+        }
+    }
+
     async fn keys_command(
         &mut self,
         action: KeysAction,
@@ -775,6 +1125,49 @@ impl Application {
     }
 }
 
+/// Fetch the logged-in user's reading history, most recent first, off the async runtime
+async fn reading_history(store: &ProgramStore) -> IambResult<Vec<ReadingHistoryItem>> {
+    let matrix_id = store.application.settings.profile.user_id.to_string();
+    let readings = store.application.tarot_store.clone();
+    let history = tokio::task::spawn_blocking(move || readings.history(&matrix_id))
+        .await
+        .map_err(|e| UIError::Failure(e.to_string()))?
+        .map_err(|e| UIError::Failure(format!("Failed to fetch history: {e}")))?;
+
+    Ok(history.readings)
+}
+
+/// Find a card by a name typed in English or the language cards are shown in
+fn find_card(lang: &Translation, name: &str) -> IambResult<&'static TarotCard> {
+    lang.find_card(name).ok_or_else(|| {
+        let msg =
+            format!("Card not found: '{name}'\nTry: fool, magus, six of swords, science, etc.");
+        UIError::Failure(msg)
+    })
+}
+
+/// Find reading `reading_num`, counting from 1, and return a title for it along with its cards
+fn lookup_reading(
+    readings: &[ReadingHistoryItem],
+    reading_num: usize,
+) -> IambResult<(String, Vec<&'static TarotCard>)> {
+    let Some(reading) = reading_num.checked_sub(1).and_then(|i| readings.get(i)) else {
+        let msg = format!("Invalid reading number. Valid range: 1-{}", readings.len());
+        return Err(UIError::Failure(msg));
+    };
+
+    let cards = reading
+        .cards
+        .iter()
+        .filter_map(|card| tarot_cards::find_card(&card.card_name))
+        .collect();
+
+    let date = reading.reading_date.get(..10).unwrap_or(&reading.reading_date);
+    let title = format!("Reading #{reading_num} - {date} ({})", reading.spread_type);
+
+    Ok((title, cards))
+}
+
 fn gen_passphrase() -> String {
     rand::thread_rng()
         .sample_iter(&Alphanumeric)
@@ -1013,6 +1406,37 @@ fn restore_tty(enable_enhanced_keys: bool, enable_mouse: bool) {
     let _ = crossterm::terminal::disable_raw_mode();
 }
 
+/// Load the user's notes on cards, with any edited since on their other devices.
+async fn load_annotations(
+    settings: &ApplicationSettings,
+    client: &matrix_sdk::Client,
+) -> Annotations {
+    let path = settings.tarot_annotations_json.as_path();
+    let mut notes = match Annotations::load(path) {
+        Ok(notes) => notes,
+        Err(e) => {
+            tracing::warn!(err = %e, "Failed to load tarot annotations");
+            return Annotations::default();
+        },
+    };
+
+    if settings.tunables.tarot.sync_annotations {
+        match tarot_annotations::pull(client).await {
+            Ok(Some(synced)) => {
+                if notes.merge(&synced) {
+                    if let Err(e) = notes.save(path) {
+                        tracing::warn!(err = %e, "Failed to save synced tarot annotations");
+                    }
+                }
+            },
+            Ok(None) => (),
+            Err(e) => tracing::warn!(err = %e, "Failed to read synced tarot annotations"),
+        }
+    }
+
+    notes
+}
+
 async fn run(settings: ApplicationSettings) -> IambResult<()> {
     // Get old keys the first time we run w/ the upgraded SDK.
     let import_keys = check_import_keys(&settings).await?;
@@ -1023,7 +1447,10 @@ async fn run(settings: ApplicationSettings) -> IambResult<()> {
 
     // Set up the async worker thread and global store.
     let worker = ClientWorker::spawn(client.clone(), settings.clone()).await;
-    let store = ChatStore::new(worker.clone(), settings.clone());
+    let mut store = ChatStore::new(worker.clone(), settings.clone());
+    store.tarot_store = tarot_store::open(&settings).unwrap_or_else(print_exit);
+    let language = Translation::configured(settings.tunables.tarot.language.as_deref());
+    store.tarot_language = Arc::new(language.unwrap_or_else(print_exit));
     let mut store = Store::new(store);
     store.completer = Box::new(IambCompleter);
 
@@ -1048,6 +1475,17 @@ async fn run(settings: ApplicationSettings) -> IambResult<()> {
         Ok(()) => (),
     }
 
+    if settings.tunables.tarot.history == HistoryBackend::Matrix {
+        // Leave the placeholder store in place if this fails, so the rest of iamb still works
+        match MatrixStore::open(client.clone(), &settings.tunables.tarot).await {
+            Ok(history) => store.lock().await.application.tarot_store = Arc::new(history),
+            Err(e) => tracing::warn!(err = %e, "Failed to open the tarot history in account data"),
+        }
+    }
+
+    let notes = load_annotations(&settings, &client).await;
+    store.lock().await.application.set_tarot_annotations(notes);
+
     // Set up the terminal for drawing, and cleanup properly on panics.
     let enable_enhanced_keys = match crossterm::terminal::supports_keyboard_enhancement() {
         Ok(supported) => supported,
@@ -1079,11 +1517,18 @@ async fn run(settings: ApplicationSettings) -> IambResult<()> {
 
 fn main() -> IambResult<()> {
     // Parse command-line flags.
-    let iamb = Iamb::parse();
+    let mut iamb = Iamb::parse();
+    let command = iamb.command.take();
 
     // Load configuration and set up the Matrix SDK.
     let settings = ApplicationSettings::load(iamb).unwrap_or_else(print_exit);
 
+    // Commands for scripts print their results and exit without starting the UI.
+    if let Some(IambCommand::Tarot { command }) = command {
+        tarot_cli::run(command, &settings).unwrap_or_else(print_exit);
+        process::exit(0);
+    }
+
     // Set umask on Unix platforms so that tokens, keys, etc. are only readable by the user.
     #[cfg(unix)]
     unsafe {
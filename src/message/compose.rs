@@ -8,13 +8,28 @@ use nom::{
     IResult,
 };
 
-use matrix_sdk::ruma::events::room::message::{
-    EmoteMessageEventContent,
-    MessageType,
-    RoomMessageEventContent,
-    TextMessageEventContent,
+use matrix_sdk::{
+    room::reply::{EnforceThread, Reply},
+    ruma::{
+        events::relation::{Replacement, Thread},
+        events::room::message::{
+            AddMentions,
+            EmoteMessageEventContent,
+            ForwardThread,
+            MessageType,
+            OriginalRoomMessageEvent,
+            Relation,
+            ReplyWithinThread,
+            RoomMessageEventContent,
+            RoomMessageEventContentWithoutRelation,
+            TextMessageEventContent,
+        },
+        OwnedEventId,
+    },
 };
 
+use crate::base::RoomInfo;
+
 #[derive(Clone, Debug, Default)]
 enum SlashCommand {
     /// Send an emote message.
@@ -162,7 +177,7 @@ fn text_to_html(input: &str) -> Option<String> {
     markdown_to_html(input, &options).into()
 }
 
-fn text_to_message_content(input: String) -> TextMessageEventContent {
+pub fn text_to_message_content(input: String) -> TextMessageEventContent {
     if let Some(html) = text_to_html(input.as_str()) {
         TextMessageEventContent::html(input, html)
     } else {
@@ -178,6 +193,102 @@ pub fn text_to_message(input: String) -> RoomMessageEventContent {
     RoomMessageEventContent::new(msg)
 }
 
+/// How a message being sent relates to the others in its room.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MessageRelation {
+    /// A message in the main timeline.
+    None,
+
+    /// An edit replacing an earlier message.
+    Edit(OwnedEventId),
+
+    /// A reply to a message outside of any thread.
+    Reply(Box<OriginalRoomMessageEvent>),
+
+    /// A message in a thread, following on from the given message or replying to it.
+    Thread(Box<OriginalRoomMessageEvent>, ReplyWithinThread),
+}
+
+impl MessageRelation {
+    /// Work out the relation from the message being edited, the open thread and the message
+    /// being replied to, in that order of precedence.
+    pub fn new(
+        editing: Option<&OwnedEventId>,
+        thread: Option<&OwnedEventId>,
+        reply_to: Option<&OriginalRoomMessageEvent>,
+        info: &RoomInfo,
+    ) -> Self {
+        if let Some(event_id) = editing {
+            MessageRelation::Edit(event_id.clone())
+        } else if let Some(thread_root) = thread {
+            if let Some(m) = reply_to {
+                MessageRelation::Thread(Box::new(m.clone()), ReplyWithinThread::Yes)
+            } else if let Some(m) = info.get_thread_last(thread_root) {
+                MessageRelation::Thread(Box::new(m.clone()), ReplyWithinThread::No)
+            } else {
+                // Internal state is wonky?
+                MessageRelation::None
+            }
+        } else if let Some(m) = reply_to {
+            MessageRelation::Reply(Box::new(m.clone()))
+        } else {
+            MessageRelation::None
+        }
+    }
+
+    /// Relate a message's content.
+    pub fn apply(&self, mut msg: RoomMessageEventContent) -> RoomMessageEventContent {
+        match self {
+            MessageRelation::None => msg,
+            MessageRelation::Edit(event_id) => {
+                msg.relates_to = Some(Relation::Replacement(Replacement::new(
+                    event_id.clone(),
+                    msg.msgtype.clone().into(),
+                )));
+                msg
+            },
+            MessageRelation::Reply(m) => msg.make_reply_to(m, ForwardThread::Yes, AddMentions::No),
+            MessageRelation::Thread(m, is_reply) => {
+                msg.make_for_thread(m, *is_reply, AddMentions::No)
+            },
+        }
+    }
+
+    /// The reply to attach to an uploaded file, which cannot be sent as an edit.
+    pub fn attachment_reply(&self) -> Result<Option<Reply>, String> {
+        let (event_id, enforce_thread) = match self {
+            MessageRelation::None => return Ok(None),
+            MessageRelation::Edit(_) => {
+                return Err("Cannot send an attachment as an edit of a message".into());
+            },
+            MessageRelation::Reply(m) => (m.event_id.clone(), EnforceThread::MaybeThreaded),
+            MessageRelation::Thread(m, is_reply) => {
+                (m.event_id.clone(), EnforceThread::Threaded(*is_reply))
+            },
+        };
+
+        Ok(Some(Reply { event_id, enforce_thread }))
+    }
+
+    /// The relation for text sent straight after an attachment, which continues the thread
+    /// after it when there is one.
+    pub fn follow_up(
+        &self,
+        attachment: OwnedEventId,
+    ) -> Option<Relation<RoomMessageEventContentWithoutRelation>> {
+        let MessageRelation::Thread(m, _) = self else {
+            return None;
+        };
+
+        let thread_root = match &m.content.relates_to {
+            Some(Relation::Thread(thread)) => thread.event_id.clone(),
+            _ => m.event_id.clone(),
+        };
+
+        Some(Relation::Thread(Thread::plain(thread_root, attachment)))
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -18,7 +18,9 @@ use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{
     base::{AsyncProgramStore, IambError, IambResult, ProgramStore},
-    config::{ApplicationSettings, NotifyVia},
+    config::{ApplicationSettings, NotifyVia, ReminderTime},
+    tarot_calendar::{Decan, Today},
+    tarot_spreads,
 };
 
 const IAMB_XDG_NAME: &str = match option_env!("IAMB_XDG_NAME") {
@@ -111,6 +113,109 @@ pub async fn register_notifications(
         .await;
 }
 
+/// Notify the user each time the Sun moves into a new decan, while iamb is running.
+pub async fn notify_decan_changes(settings: &ApplicationSettings, store: &AsyncProgramStore) {
+    if !settings.tunables.tarot.decan_notify {
+        return;
+    }
+    let notify_via = settings.tunables.notifications.via;
+    let sound_hint = settings.tunables.notifications.sound_hint.as_deref();
+
+    loop {
+        let now = chrono::Utc::now().naive_utc();
+        let Some(decan) = Decan::containing(now) else {
+            return;
+        };
+
+        // Wake up just after the Sun has moved on, so the new decan is the one found
+        let wait = (decan.end - now + chrono::Duration::minutes(1))
+            .to_std()
+            .unwrap_or_default();
+        tokio::time::sleep(wait).await;
+
+        let Some(today) = Today::at(chrono::Utc::now().naive_utc()) else {
+            return;
+        };
+        let summary = format!("{} begins: {}", today.decan.name(), today.decan.card.card);
+        let body = today.to_text().replace("**", "");
+
+        send_reminder(&notify_via, &summary, &body, store, sound_hint).await;
+    }
+}
+
+/// Remind the user to draw their card of the day at the configured time, unless they
+/// already have.
+pub async fn remind_daily_draw(settings: &ApplicationSettings, store: &AsyncProgramStore) {
+    let Some(ReminderTime(at)) = settings.tunables.tarot.daily_reminder else {
+        return;
+    };
+    let notify_via = settings.tunables.notifications.via;
+    let sound_hint = settings.tunables.notifications.sound_hint.as_deref();
+    let matrix_id = settings.profile.user_id.to_string();
+
+    loop {
+        let now = chrono::Local::now().naive_local();
+        let mut next = now.date().and_time(at);
+        if next <= now {
+            next += chrono::Duration::days(1);
+        }
+
+        let wait = (next - now).to_std().unwrap_or_default();
+        tokio::time::sleep(wait).await;
+
+        let matrix_id = matrix_id.clone();
+        let readings = store.lock().await.application.tarot_store.clone();
+        let history = tokio::task::spawn_blocking(move || readings.history(&matrix_id)).await;
+        let today = chrono::Local::now().date_naive();
+
+        // If the history can't be fetched, remind anyway rather than stay quiet
+        if let Ok(Ok(history)) = history {
+            if tarot_spreads::daily_reading(&history.readings, today).is_some() {
+                continue;
+            }
+        }
+
+        let body = "Use :tarot daily to pull today's card.";
+        send_reminder(&notify_via, "Time to draw your card of the day", body, store, sound_hint)
+            .await;
+    }
+}
+
+/// Send a notification that isn't about a room, such as a reminder from the tarot calendar.
+pub async fn send_reminder(
+    via: &NotifyVia,
+    summary: &str,
+    body: &str,
+    store: &AsyncProgramStore,
+    sound_hint: Option<&str>,
+) {
+    #[cfg(feature = "desktop")]
+    if via.desktop {
+        let mut desktop_notification = notify_rust::Notification::new();
+        desktop_notification
+            .summary(summary)
+            .body(body)
+            .appname(IAMB_XDG_NAME)
+            .icon(IAMB_XDG_NAME);
+
+        if let Some(sound_hint) = sound_hint {
+            desktop_notification.sound_name(sound_hint);
+        }
+
+        if let Err(err) = desktop_notification.show() {
+            tracing::error!("Failed to send notification: {err}");
+        }
+    }
+    #[cfg(not(feature = "desktop"))]
+    {
+        let _ = (summary, body, sound_hint);
+    }
+
+    if via.bell {
+        send_notification_bell(store).await;
+    }
+}
+
 async fn send_notification(
     via: &NotifyVia,
     summary: &str,
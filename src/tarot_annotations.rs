@@ -0,0 +1,179 @@
+//! Personal notes on cards, layered over the deck's meanings
+//!
+//! Notes are kept in `tarot_annotations.json` in the profile's data directory. With
+//! `tarot.sync_annotations` turned on they are also stored in the user's Matrix account data,
+//! so that they follow the account to other devices. Each note remembers when it was last
+//! edited, and the newer copy of a note wins when the two are merged.
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use matrix_sdk::ruma::events::GlobalAccountDataEventType;
+use matrix_sdk::ruma::serde::Raw;
+use matrix_sdk::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::tarot_cards::TarotCard;
+
+/// The account data event the notes are synced through
+pub const EVENT_TYPE: &str = "com.endlessperfect.tarot.annotations";
+
+/// One card's note
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Annotation {
+    /// The note's Markdown, empty once it has been deleted
+    pub text: String,
+    /// When the note was last edited, in milliseconds since the Unix epoch
+    pub updated: u64,
+}
+
+/// Every card's note, by the card's search key
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Annotations {
+    pub cards: BTreeMap<String, Annotation>,
+}
+
+impl Annotations {
+    /// Load the saved notes, or none if nothing has been saved yet
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => {
+                serde_json::from_str(&json)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Annotations::default()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+
+        std::fs::write(path, json).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+    }
+
+    /// The note on a card, if it has one
+    pub fn get(&self, card: &TarotCard) -> Option<&str> {
+        self.cards
+            .get(&card.search_key())
+            .map(|note| note.text.as_str())
+            .filter(|text| !text.is_empty())
+    }
+
+    /// Replace the note on a card, or delete it if the text is empty
+    ///
+    /// Deleted notes are kept empty rather than removed, so that merging doesn't bring back
+    /// an older copy from another device.
+    pub fn set(&mut self, card: &TarotCard, text: &str, now: u64) {
+        let note = Annotation { text: text.trim().to_string(), updated: now };
+
+        self.cards.insert(card.search_key(), note);
+    }
+
+    /// Take any notes from another copy that were edited more recently than ours
+    ///
+    /// Returns whether anything changed.
+    pub fn merge(&mut self, other: &Annotations) -> bool {
+        let mut changed = false;
+
+        for (key, theirs) in &other.cards {
+            match self.cards.get(key) {
+                Some(ours) if ours.updated >= theirs.updated => {},
+                _ => {
+                    self.cards.insert(key.clone(), theirs.clone());
+                    changed = true;
+                },
+            }
+        }
+
+        changed
+    }
+}
+
+/// Read the notes synced to the account, as of the last sync
+pub async fn pull(client: &Client) -> Result<Option<Annotations>, String> {
+    let event_type = GlobalAccountDataEventType::from(EVENT_TYPE);
+    let raw = client
+        .account()
+        .account_data_raw(event_type)
+        .await
+        .map_err(|e| format!("Failed to read synced annotations: {}", e))?;
+
+    raw.map(|raw| {
+        raw.deserialize_as::<Annotations>()
+            .map_err(|e| format!("Failed to read synced annotations: {}", e))
+    })
+    .transpose()
+}
+
+/// Store the notes in the account, for other devices to pick up
+pub async fn push(client: &Client, notes: &Annotations) -> Result<(), String> {
+    let event_type = GlobalAccountDataEventType::from(EVENT_TYPE);
+    let content = Raw::new(notes).map_err(|e| e.to_string())?.cast();
+
+    client
+        .account()
+        .set_account_data_raw(event_type, content)
+        .await
+        .map_err(|e| format!("Failed to sync annotations: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tarot_cards::find_card;
+    use temp_dir::TempDir;
+
+    #[test]
+    fn test_annotations() {
+        let fool = find_card("fool").unwrap();
+        let star = find_card("star").unwrap();
+        let mut notes = Annotations::default();
+
+        notes.set(fool, "  Leaping before looking \n", 10);
+        assert_eq!(notes.get(fool), Some("Leaping before looking"));
+        assert_eq!(notes.get(star), None);
+
+        // Deleting a note keeps when it was deleted
+        notes.set(fool, "", 20);
+        assert_eq!(notes.get(fool), None);
+        assert_eq!(notes.cards["fool"].updated, 20);
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tarot_annotations.json");
+        assert_eq!(Annotations::load(&path).unwrap(), Annotations::default());
+
+        notes.set(star, "Hope after the Tower", 30);
+        notes.save(&path).unwrap();
+        assert_eq!(Annotations::load(&path).unwrap(), notes);
+
+        std::fs::write(&path, "[]").unwrap();
+        assert!(Annotations::load(&path).is_err());
+    }
+
+    #[test]
+    fn test_merge_annotations() {
+        let fool = find_card("fool").unwrap();
+        let star = find_card("star").unwrap();
+        let magus = find_card("magus").unwrap();
+
+        let mut ours = Annotations::default();
+        ours.set(fool, "Ours, newer", 20);
+        ours.set(star, "Ours, older", 10);
+        ours.set(magus, "", 30);
+
+        let mut theirs = Annotations::default();
+        theirs.set(fool, "Theirs, older", 15);
+        theirs.set(star, "Theirs, newer", 25);
+        theirs.set(magus, "Deleted here since", 5);
+
+        assert!(ours.merge(&theirs));
+        assert_eq!(ours.get(fool), Some("Ours, newer"));
+        assert_eq!(ours.get(star), Some("Theirs, newer"));
+        assert_eq!(ours.get(magus), None);
+
+        // Merging the same notes again changes nothing
+        assert!(!ours.merge(&theirs));
+    }
+}
@@ -1,18 +1,28 @@
-//! API client for tarot reading history and analytics
+//! Reading history types, and the API client that stores them on the tarot server
 
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::tarot_store::ReadingStore;
 
 const API_BASE_URL: &str = "https://endlessperfect.com/tarot-api/api";
 
-#[derive(Debug, Deserialize)]
+/// How long to wait for the server before giving up on a request
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct CardInReading {
     pub position: i32,
     pub card_name: String,
     pub label: Option<String>,
+    #[serde(default)]
+    pub reversed: bool,
+    #[serde(default)]
+    pub dignity: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ReadingHistoryItem {
     pub reading_id: i32,
     pub spread_type: String,
@@ -20,9 +30,19 @@ pub struct ReadingHistoryItem {
     pub card_count: usize,
     pub cards: Vec<CardInReading>,
     pub notes: Option<String>,
+    #[serde(default)]
+    pub question: Option<String>,
+    #[serde(default)]
+    pub room_id: Option<String>,
+    #[serde(default)]
+    pub quintessence: Option<String>,
+    #[serde(default)]
+    pub moon_phase: Option<String>,
+    #[serde(default)]
+    pub sun_sign: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct HistoryResponse {
     pub total_readings: usize,
     pub readings: Vec<ReadingHistoryItem>,
@@ -35,21 +55,24 @@ pub struct CardDetail {
     pub label: Option<String>,
     pub info: Option<String>,
     pub deepinfo: Option<String>,
+    #[serde(default)]
+    pub dignity: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ReadingDetails {
-    pub reading_id: i32,
     pub spread_type: String,
     pub reading_date: String,
     pub notes: Option<String>,
+    #[serde(default)]
+    pub question: Option<String>,
+    #[serde(default)]
+    pub numerology: Option<String>,
     pub cards: Vec<CardDetail>,
-    pub attributes: HashMap<String, HashMap<String, i32>>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct AttributeFrequency {
-    pub attribute_type: String,
     pub total_count: i32,
     pub frequencies: HashMap<String, i32>,
     pub percentages: HashMap<String, f64>,
@@ -61,20 +84,41 @@ pub struct TopAttribute {
     pub count: i32,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CardData {
     pub position: i32,
     pub card_name: String,
     pub card_label: Option<String>,
+    pub is_reversed: bool,
+    /// Elemental dignity from the card's neighbours, e.g. "ill-dignified"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dignity: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ReadingCreate {
     pub matrix_id: String,
     pub room_id: Option<String>,
     pub spread_type: String,
     pub cards: Vec<CardData>,
     pub notes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub question: Option<String>,
+    /// Original date of an imported reading; the server uses the current time when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reading_date: Option<String>,
+    /// Name of the trump the spread's card numbers reduce to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quintessence: Option<String>,
+    /// Numerology summary, as appended to the posted reading
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub numerology: Option<String>,
+    /// Phase of the Moon when the reading was drawn, e.g. "Full Moon"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub moon_phase: Option<String>,
+    /// Sign the Sun was in when the reading was drawn
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sun_sign: Option<String>,
     pub is_private: bool,
 }
 
@@ -98,64 +142,198 @@ pub struct AnalyticsSummary {
     pub top_attributes: HashMap<String, Vec<TopAttribute>>,
 }
 
-/// Get reading history for a user
-pub fn get_history(matrix_id: &str) -> Result<HistoryResponse, String> {
-    let url = format!("{}/readings/user/{}/history", API_BASE_URL, matrix_id);
-    
-    let response = reqwest::blocking::get(&url)
-        .map_err(|e| format!("Failed to fetch history: {}", e))?;
-    
-    if !response.status().is_success() {
-        return Err(format!("API error: {}", response.status()));
+/// Keywords used to group readings by the topic of the querent's question
+const QUESTION_TOPICS: &[(&str, &[&str])] = &[
+    ("career", &[
+        "job",
+        "jobs",
+        "work",
+        "career",
+        "boss",
+        "promotion",
+        "business",
+        "interview",
+        "colleague",
+        "colleagues",
+        "office",
+        "project",
+    ]),
+    ("love", &[
+        "love",
+        "relationship",
+        "partner",
+        "marriage",
+        "marry",
+        "dating",
+        "date",
+        "boyfriend",
+        "girlfriend",
+        "husband",
+        "wife",
+        "romance",
+        "crush",
+    ]),
+    ("money", &[
+        "money",
+        "finance",
+        "finances",
+        "financial",
+        "debt",
+        "salary",
+        "invest",
+        "investment",
+        "buy",
+        "sell",
+        "rent",
+        "loan",
+        "afford",
+    ]),
+    ("health", &[
+        "health", "healthy", "illness", "sick", "doctor", "healing", "heal", "body", "diet",
+        "surgery",
+    ]),
+    ("family", &[
+        "family", "mother", "father", "mom", "dad", "parent", "parents", "child", "children",
+        "son", "daughter", "sister", "brother",
+    ]),
+    ("home", &[
+        "home",
+        "house",
+        "move",
+        "moving",
+        "apartment",
+        "city",
+        "travel",
+        "trip",
+    ]),
+    ("spiritual", &[
+        "spiritual",
+        "purpose",
+        "magick",
+        "meditation",
+        "soul",
+        "initiation",
+    ]),
+];
+
+/// Classify a question into one of the topics in `QUESTION_TOPICS`, or "general"
+pub fn question_topic(question: &str) -> &'static str {
+    let question = question.to_lowercase();
+    let words: Vec<&str> = question
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    QUESTION_TOPICS
+        .iter()
+        .find(|(_, keywords)| words.iter().any(|w| keywords.contains(w)))
+        .map(|(topic, _)| *topic)
+        .unwrap_or("general")
+}
+
+/// Names of all question topics, including the "general" fallback
+pub fn question_topics() -> Vec<&'static str> {
+    let mut topics: Vec<&'static str> = QUESTION_TOPICS.iter().map(|(topic, _)| *topic).collect();
+    topics.push("general");
+    topics
+}
+
+/// Whether a reading's question contains every one of the search words
+pub fn question_matches(reading: &ReadingHistoryItem, words: &[String]) -> bool {
+    match &reading.question {
+        Some(question) => {
+            let question = question.to_lowercase();
+            words.iter().all(|w| question.contains(&w.to_lowercase()))
+        },
+        None => false,
     }
-    
-    response.json::<HistoryResponse>()
-        .map_err(|e| format!("Failed to parse response: {}", e))
 }
 
-/// Get details for a specific reading
-pub fn get_reading_details(reading_id: i32) -> Result<ReadingDetails, String> {
-    let url = format!("{}/readings/{}/details", API_BASE_URL, reading_id);
-    
-    let response = reqwest::blocking::get(&url)
-        .map_err(|e| format!("Failed to fetch reading details: {}", e))?;
-    
-    if !response.status().is_success() {
-        return Err(format!("API error: {}", response.status()));
+/// The tarot API, which saves readings and works out their analytics on the server
+#[derive(Clone, Debug)]
+pub struct HttpStore {
+    base_url: String,
+    timeout: Duration,
+}
+
+impl HttpStore {
+    /// The API at `base_url`, giving up on requests that take longer than `timeout`
+    pub fn new(base_url: impl Into<String>, timeout: Duration) -> Self {
+        HttpStore { base_url: base_url.into(), timeout }
+    }
+
+    fn client(&self) -> Result<reqwest::blocking::Client, String> {
+        reqwest::blocking::Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(|e| e.to_string())
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str, what: &str) -> Result<T, String> {
+        let url = format!("{}{}", self.base_url, path);
+
+        let response = self
+            .client()?
+            .get(&url)
+            .send()
+            .map_err(|e| format!("Failed to fetch {}: {}", what, e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("API error: {}", response.status()));
+        }
+
+        response
+            .json::<T>()
+            .map_err(|e| format!("Failed to parse response: {}", e))
     }
-    
-    response.json::<ReadingDetails>()
-        .map_err(|e| format!("Failed to parse response: {}", e))
 }
 
-/// Get attribute frequency distribution
-pub fn get_attribute_frequency(matrix_id: &str, attribute_type: &str) -> Result<AttributeFrequency, String> {
-    let url = format!("{}/analytics/user/{}/attributes/{}", API_BASE_URL, matrix_id, attribute_type);
-    
-    let response = reqwest::blocking::get(&url)
-        .map_err(|e| format!("Failed to fetch attribute frequency: {}", e))?;
-    
-    if !response.status().is_success() {
-        return Err(format!("API error: {}", response.status()));
+impl Default for HttpStore {
+    fn default() -> Self {
+        HttpStore::new(API_BASE_URL, REQUEST_TIMEOUT)
     }
-    
-    response.json::<AttributeFrequency>()
-        .map_err(|e| format!("Failed to parse response: {}", e))
 }
 
-/// Get analytics summary
-pub fn get_analytics_summary(matrix_id: &str) -> Result<AnalyticsSummary, String> {
-    let url = format!("{}/analytics/user/{}/summary", API_BASE_URL, matrix_id);
-    
-    let response = reqwest::blocking::get(&url)
-        .map_err(|e| format!("Failed to fetch analytics summary: {}", e))?;
-    
-    if !response.status().is_success() {
-        return Err(format!("API error: {}", response.status()));
+impl ReadingStore for HttpStore {
+    fn save(&self, reading: ReadingCreate) -> Result<ReadingCreateResponse, String> {
+        let url = format!("{}/readings", self.base_url);
+
+        let response = self
+            .client()?
+            .post(&url)
+            .json(&reading)
+            .send()
+            .map_err(|e| format!("Failed to save reading: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("API error: {}", response.status()));
+        }
+
+        response
+            .json::<ReadingCreateResponse>()
+            .map_err(|e| format!("Failed to parse response: {}", e))
+    }
+
+    fn history(&self, matrix_id: &str) -> Result<HistoryResponse, String> {
+        self.get(&format!("/readings/user/{}/history", matrix_id), "history")
+    }
+
+    fn details(&self, reading_id: i32) -> Result<ReadingDetails, String> {
+        self.get(&format!("/readings/{}/details", reading_id), "reading details")
+    }
+
+    fn attribute_frequency(
+        &self,
+        matrix_id: &str,
+        attribute_type: &str,
+    ) -> Result<AttributeFrequency, String> {
+        let path = format!("/analytics/user/{}/attributes/{}", matrix_id, attribute_type);
+        self.get(&path, "attribute frequency")
+    }
+
+    fn summary(&self, matrix_id: &str) -> Result<AnalyticsSummary, String> {
+        self.get(&format!("/analytics/user/{}/summary", matrix_id), "analytics summary")
     }
-    
-    response.json::<AnalyticsSummary>()
-        .map_err(|e| format!("Failed to parse response: {}", e))
 }
 
 /// Generate ASCII bar graph from frequency data
@@ -187,20 +365,203 @@ pub fn generate_bar_graph(frequencies: &HashMap<String, i32>, percentages: &Hash
     lines.join("\n")
 }
 
-/// Save a new tarot reading
-pub fn save_reading(reading: ReadingCreate) -> Result<ReadingCreateResponse, String> {
-    let url = format!("{}/readings", API_BASE_URL);
-    
-    let client = reqwest::blocking::Client::new();
-    let response = client.post(&url)
-        .json(&reading)
-        .send()
-        .map_err(|e| format!("Failed to save reading: {}", e))?;
-    
-    if !response.status().is_success() {
-        return Err(format!("API error: {}", response.status()));
+/// Characters for sparkline levels, from lowest to highest
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Render a row of counts as a sparkline, scaled to `max_value`; zero counts show as `·`
+pub fn sparkline(counts: &[i32], max_value: i32) -> String {
+    counts
+        .iter()
+        .map(|&count| {
+            if count <= 0 || max_value <= 0 {
+                '·'
+            } else {
+                let level =
+                    (count as f64 / max_value as f64 * (SPARK_LEVELS.len() - 1) as f64).round();
+                SPARK_LEVELS[level as usize]
+            }
+        })
+        .collect()
+}
+
+/// Generate a text chart with one sparkline per series, over the given periods
+///
+/// All series share one scale, so their heights can be compared.
+pub fn generate_trend_graph(periods: &[String], series: &[(String, Vec<i32>)]) -> String {
+    if periods.is_empty() || series.is_empty() {
+        return "No data available".to_string();
+    }
+
+    let max_value = series
+        .iter()
+        .flat_map(|(_, counts)| counts.iter().copied())
+        .max()
+        .unwrap_or(0);
+    let mut lines = vec![format!(
+        "{:<15} {} → {}",
+        "",
+        periods[0],
+        periods[periods.len() - 1]
+    )];
+
+    for (label, counts) in series {
+        let total: i32 = counts.iter().sum();
+        lines.push(format!("{:<15} {} ({})", label, sparkline(counts, max_value), total));
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tarot_store::tests::reading;
+    use crate::tests::tarot_server::{Fault, StandIn, FIXTURE_USER};
+
+    #[test]
+    fn test_http_store() {
+        let server = StandIn::start();
+        let store = HttpStore::new(server.base_url.clone(), REQUEST_TIMEOUT);
+
+        let history = store.history(FIXTURE_USER).unwrap();
+        assert_eq!(history.total_readings, 2);
+        assert_eq!(history.readings[0].spread_type, "daily");
+        assert_eq!(history.readings[1].cards[0].card_name, "Six of Swords");
+        assert!(!history.readings[1].cards[0].reversed);
+        assert!(store.history("@nobody:example.com").unwrap().readings.is_empty());
+
+        let saved = store.save(reading(FIXTURE_USER, "2026-04-02", &["The Tower"])).unwrap();
+        assert_eq!(saved.reading_id, 3);
+
+        // Unset fields are left out of what's posted
+        let posted = &server.saved()[0];
+        assert_eq!(posted["cards"][0]["card_name"], "The Tower");
+        assert_eq!(posted["reading_date"], "2026-04-02T12:00:00");
+        assert!(posted.get("quintessence").is_none());
+
+        let history = store.history(FIXTURE_USER).unwrap();
+        assert_eq!(history.total_readings, 3);
+        assert_eq!(history.readings[0].reading_id, 3);
+        assert_eq!(history.readings[0].question.as_deref(), Some("Should I take the job?"));
+
+        let details = store.details(1).unwrap();
+        assert_eq!(details.cards[1].card_name, "The Star");
+        assert_eq!(details.cards[1].info.as_deref(), Some("Fixture meaning"));
+        assert_eq!(details.numerology.as_deref(), Some("Quintessence: The Star"));
+        assert_eq!(store.details(99).unwrap_err(), "API error: 404 Not Found");
+
+        let freq = store.attribute_frequency(FIXTURE_USER, "suit").unwrap();
+        assert_eq!(freq.total_count, 4);
+        assert_eq!(freq.frequencies["Swords"], 3);
+
+        let summary = store.summary(FIXTURE_USER).unwrap();
+        assert_eq!(summary.total_readings, 2);
+        assert_eq!(summary.spread_types[1].spread_type, "daily");
+        assert_eq!(summary.top_attributes["suit"][0].value, "Swords");
+    }
+
+    #[test]
+    fn test_http_store_server_error() {
+        let server = StandIn::start();
+        let store = HttpStore::new(server.base_url.clone(), REQUEST_TIMEOUT);
+        server.fail(Fault::Status(503));
+
+        let msg = "API error: 503 Service Unavailable";
+        assert_eq!(store.history(FIXTURE_USER).unwrap_err(), msg);
+        assert_eq!(store.details(1).unwrap_err(), msg);
+        assert_eq!(store.attribute_frequency(FIXTURE_USER, "suit").unwrap_err(), msg);
+        assert_eq!(store.summary(FIXTURE_USER).unwrap_err(), msg);
+        assert_eq!(
+            store
+                .save(reading(FIXTURE_USER, "2026-04-02", &["The Tower"]))
+                .unwrap_err(),
+            msg
+        );
+        assert!(server.saved().is_empty());
+    }
+
+    #[test]
+    fn test_http_store_malformed_json() {
+        let server = StandIn::start();
+        let store = HttpStore::new(server.base_url.clone(), REQUEST_TIMEOUT);
+        server.fail(Fault::MalformedJson);
+
+        let err = store.history(FIXTURE_USER).unwrap_err();
+        assert!(err.starts_with("Failed to parse response:"), "{}", err);
+
+        let err = store
+            .save(reading(FIXTURE_USER, "2026-04-02", &["The Tower"]))
+            .unwrap_err();
+        assert!(err.starts_with("Failed to parse response:"), "{}", err);
+    }
+
+    #[test]
+    fn test_http_store_timeout() {
+        let server = StandIn::start();
+        let store = HttpStore::new(server.base_url.clone(), Duration::from_millis(200));
+        server.fail(Fault::Stall(Duration::from_secs(3)));
+
+        let err = store.history(FIXTURE_USER).unwrap_err();
+        assert!(
+            err.starts_with("Failed to fetch history:") && err.contains("timed out"),
+            "{}",
+            err
+        );
+
+        let err = store
+            .save(reading(FIXTURE_USER, "2026-04-02", &["The Tower"]))
+            .unwrap_err();
+        assert!(err.starts_with("Failed to save reading:") && err.contains("timed out"), "{}", err);
+    }
+
+    #[test]
+    fn test_http_store_unreachable() {
+        // Nothing listens on the discard port
+        let store = HttpStore::new("http://127.0.0.1:9/api", REQUEST_TIMEOUT);
+
+        let err = store.summary(FIXTURE_USER).unwrap_err();
+        assert!(err.starts_with("Failed to fetch analytics summary:"), "{}", err);
+    }
+
+    #[test]
+    fn test_question_topic() {
+        assert_eq!(question_topic("Should I take the job?"), "career");
+        assert_eq!(question_topic("Does my partner love me?"), "love");
+        assert_eq!(question_topic("Can I afford the new house?"), "money");
+        assert_eq!(question_topic("What should I focus on today?"), "general");
+        assert_eq!(question_topic("What does JOB mean"), "career");
+
+        // "Will" starts many questions and must not pick a topic by itself
+        assert_eq!(question_topic("Will I get the job?"), "career");
+        assert_eq!(question_topic("Will it rain on Saturday?"), "general");
+    }
+
+    #[test]
+    fn test_question_topics() {
+        let topics = question_topics();
+        assert_eq!(topics.first(), Some(&"career"));
+        assert_eq!(topics.last(), Some(&"general"));
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[0, 1, 4, 8], 8), "·▂▅█");
+        assert_eq!(sparkline(&[0, 0], 0), "··");
+    }
+
+    #[test]
+    fn test_generate_trend_graph() {
+        let periods = vec!["2026-01".to_string(), "2026-02".to_string()];
+        let series = vec![
+            ("Cups".to_string(), vec![2, 0]),
+            ("Wands".to_string(), vec![1, 2]),
+        ];
+        let graph = generate_trend_graph(&periods, &series);
+        let lines: Vec<&str> = graph.lines().collect();
+
+        assert_eq!(lines[0].trim(), "2026-01 → 2026-02");
+        assert_eq!(lines[1], "Cups            █· (2)");
+        assert_eq!(lines[2], "Wands           ▅█ (3)");
+        assert_eq!(generate_trend_graph(&periods, &[]), "No data available");
     }
-    
-    response.json::<ReadingCreateResponse>()
-        .map_err(|e| format!("Failed to parse response: {}", e))
 }
@@ -0,0 +1,291 @@
+//! The decan calendar: the small card ruling the Sun's decan on any day of the year
+//!
+//! The Sun spends about ten days in each decan, so each of the 36 small cards rules a
+//! stretch of the calendar: the Two of Wands from the March equinox, the Three of Wands
+//! from around the 31st of March, and so on round the year. The zodiacal trump of the
+//! Sun's sign rules the whole month around it. Dates come from the Sun's longitude in
+//! `tarot_sky`, so they shift by a day or so from year to year as the real decans do.
+use chrono::{Duration, NaiveDateTime};
+
+use crate::tarot_cards::{self, TarotCard};
+use crate::tarot_sky::Sky;
+use crate::tarot_wheel::{self, SIGNS};
+
+/// The Sun's mean daily motion through the zodiac, in degrees
+const SUN_DEGREES_PER_DAY: f64 = 0.985_647;
+
+/// Number of upcoming decans listed after the current one by `:tarot today`
+const UPCOMING: usize = 2;
+
+/// A decan, and when the Sun passes through it
+#[derive(Clone, Debug)]
+pub struct Decan {
+    /// Numbered from 0 for the first decan of Aries
+    pub index: usize,
+    pub card: &'static TarotCard,
+    /// When the Sun enters and leaves the decan, in UTC
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+}
+
+impl Decan {
+    /// The decan the Sun is in at a time in UTC
+    pub fn containing(time: NaiveDateTime) -> Option<Self> {
+        let index = (Sky::at(time).sun_longitude / 10.0) as usize % 36;
+        Decan::around(index, time)
+    }
+
+    /// The decan numbered `index` that the Sun passes through nearest to `time`
+    fn around(index: usize, time: NaiveDateTime) -> Option<Self> {
+        let start = sun_reaches(index as f64 * 10.0, time);
+        let end = sun_reaches(index as f64 * 10.0 + 10.0, start + Duration::days(10));
+
+        Some(Decan {
+            index,
+            card: tarot_wheel::decan_card(index)?,
+            start,
+            end,
+        })
+    }
+
+    /// The decan after this one
+    pub fn next(&self) -> Option<Self> {
+        Decan::around((self.index + 1) % 36, self.end)
+    }
+
+    pub fn name(&self) -> String {
+        tarot_wheel::decan_name(self.index)
+    }
+
+    pub fn sign(&self) -> usize {
+        self.index / 3
+    }
+
+    /// The planet ruling the decan, as given by its card
+    pub fn planet(&self) -> Option<&'static str> {
+        self.card.planet_orb.as_deref()
+    }
+
+    /// Whole days left before the Sun leaves the decan
+    pub fn days_left(&self, now: NaiveDateTime) -> i64 {
+        (self.end - now).num_days().max(0)
+    }
+}
+
+/// The time nearest to `near` at which the Sun reaches a longitude
+fn sun_reaches(longitude: f64, near: NaiveDateTime) -> NaiveDateTime {
+    let mut time = near;
+
+    // The Sun's speed varies by only a few percent, so this settles within seconds
+    for _ in 0..5 {
+        let behind = (longitude - Sky::at(time).sun_longitude + 180.0).rem_euclid(360.0) - 180.0;
+        let seconds = behind / SUN_DEGREES_PER_DAY * 86_400.0;
+        time += Duration::seconds(seconds as i64);
+    }
+
+    time
+}
+
+/// The 36 decans of the year that starts at the March equinox of `year`
+pub fn decan_table(year: i32) -> Vec<Decan> {
+    let Some(equinox) =
+        chrono::NaiveDate::from_ymd_opt(year, 3, 20).and_then(|d| d.and_hms_opt(12, 0, 0))
+    else {
+        return vec![];
+    };
+
+    let mut table: Vec<Decan> = Vec::with_capacity(36);
+
+    while table.len() < 36 {
+        let decan = match table.last() {
+            Some(last) => last.next(),
+            None => Decan::around(0, equinox),
+        };
+
+        match decan {
+            Some(decan) => table.push(decan),
+            None => break,
+        }
+    }
+
+    table
+}
+
+/// List a year's decans with their cards and dates, a sign to a paragraph
+pub fn table_text(table: &[Decan]) -> String {
+    let Some(first) = table.first() else {
+        return String::new();
+    };
+
+    let mut out = format!("**Decan calendar from {}**\n", first.start.format("%Y-%m-%d"));
+
+    for decan in table {
+        if decan.index % 3 == 0 {
+            let sign = decan.sign();
+            let trump = sign_trump(sign).map(|c| format!(" ({})", c.card)).unwrap_or_default();
+            out.push_str(&format!(
+                "\n{} {}{}\n",
+                tarot_wheel::sign_glyph(sign),
+                SIGNS[sign],
+                trump
+            ));
+        }
+
+        out.push_str(&format!(
+            "  {} – {}  {}\n",
+            decan.start.format("%b %d"),
+            decan.end.format("%b %d"),
+            decan.card.card
+        ));
+    }
+
+    out
+}
+
+/// The zodiacal trump of a sign, from 0 for Aries
+pub fn sign_trump(sign: usize) -> Option<&'static TarotCard> {
+    let sign = SIGNS.get(sign)?;
+
+    tarot_cards::get_major_arcana()
+        .into_iter()
+        .find(|card| card.sign_1.as_deref() == Some(*sign))
+}
+
+/// The cards ruling a moment: its decan, the trump of the Sun's sign and the decans to come
+#[derive(Clone, Debug)]
+pub struct Today {
+    pub now: NaiveDateTime,
+    pub decan: Decan,
+    pub trump: Option<&'static TarotCard>,
+    pub upcoming: Vec<Decan>,
+}
+
+impl Today {
+    pub fn at(now: NaiveDateTime) -> Option<Self> {
+        let decan = Decan::containing(now)?;
+        let trump = sign_trump(decan.sign());
+
+        let mut upcoming: Vec<Decan> = Vec::with_capacity(UPCOMING);
+        while upcoming.len() < UPCOMING {
+            match upcoming.last().unwrap_or(&decan).next() {
+                Some(next) => upcoming.push(next),
+                None => break,
+            }
+        }
+
+        Some(Today { now, decan, trump, upcoming })
+    }
+
+    /// The text posted by `:tarot today`
+    pub fn to_text(&self) -> String {
+        let decan = &self.decan;
+        let sign = decan.sign();
+
+        let mut out = format!(
+            "**Today:** {} · Sun in {} {}\n\n",
+            self.now.format("%Y-%m-%d"),
+            tarot_wheel::sign_glyph(sign),
+            decan.name()
+        );
+
+        let ruler = match decan.planet() {
+            Some(planet) => format!(" ({} in {})", planet, SIGNS[sign]),
+            None => String::new(),
+        };
+        let days = match decan.days_left(self.now) {
+            0 => "ends today".to_string(),
+            1 => "1 day left".to_string(),
+            n => format!("{} days left", n),
+        };
+        out.push_str(&format!(
+            "**Decan:** {}{}, until {} ({})\n",
+            decan.card.card,
+            ruler,
+            decan.end.format("%Y-%m-%d"),
+            days
+        ));
+
+        if let Some(trump) = self.trump {
+            out.push_str(&format!("**Sign:** {} ({})\n", trump.card, SIGNS[sign]));
+        }
+
+        if !self.upcoming.is_empty() {
+            let upcoming: Vec<String> = self
+                .upcoming
+                .iter()
+                .map(|d| format!("{} from {}", d.card.card, d.start.format("%Y-%m-%d")))
+                .collect();
+            out.push_str(&format!("**Coming up:** {}\n", upcoming.join(", ")));
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_decan_table() {
+        let table = decan_table(2026);
+        assert_eq!(table.len(), 36);
+
+        // The Sun entered Aries at 14:46 UTC on 2026-03-20
+        let aries = &table[0];
+        assert_eq!(aries.card.card, "Two of Wands");
+        assert_eq!(aries.start.format("%Y-%m-%d %H").to_string(), "2026-03-20 14");
+
+        for (i, pair) in table.windows(2).enumerate() {
+            assert_eq!(pair[0].index, i);
+            assert_eq!(pair[0].end, pair[1].start);
+
+            let days = (pair[0].end - pair[0].start).num_hours() as f64 / 24.0;
+            assert!((9.0..11.0).contains(&days), "{} lasts {} days", pair[0].name(), days);
+        }
+
+        // Pisces III, the Ten of Cups, ends at the next equinox
+        assert_eq!(table[35].card.card, "Ten of Cups");
+        assert_eq!(table[35].end.format("%Y-%m-%d").to_string(), "2027-03-20");
+    }
+
+    #[test]
+    fn test_table_text() {
+        let text = table_text(&decan_table(2026));
+        assert!(text.starts_with("**Decan calendar from 2026-03-20**\n\n♈ Aries (The Emperor)\n"));
+        assert!(text.contains("  Mar 20 – Mar 30  Two of Wands\n"));
+        assert!(text.contains("\n♓ Pisces (The Moon)\n"));
+        assert_eq!(table_text(&[]), "");
+    }
+
+    #[test]
+    fn test_today() {
+        // The Sun enters Scorpio mid-morning (UTC) on 2026-10-23
+        let today = Today::at(time("2026-10-18T09:00:00")).unwrap();
+        assert_eq!(today.decan.name(), "Libra III");
+        assert_eq!(today.decan.planet(), Some("Jupiter"));
+        assert_eq!(today.decan.days_left(today.now), 5);
+        assert_eq!(today.trump.map(|c| c.card.as_str()), Some("Adjustment"));
+        assert_eq!(today.upcoming.len(), 2);
+        assert_eq!(today.upcoming[0].name(), "Scorpio I");
+
+        let text = today.to_text();
+        assert!(text.starts_with("**Today:** 2026-10-18 · Sun in ♎ Libra III\n\n"));
+        assert!(text.contains(
+            "**Decan:** Four of Swords (Jupiter in Libra), until 2026-10-23 (5 days left)\n"
+        ));
+        assert!(text.contains("**Sign:** Adjustment (Libra)\n"));
+        assert!(text.contains("**Coming up:** Five of Cups from 2026-10-23, "));
+    }
+
+    #[test]
+    fn test_sign_trump() {
+        assert_eq!(sign_trump(0).map(|c| c.card.as_str()), Some("The Emperor"));
+        assert_eq!(sign_trump(10).map(|c| c.card.as_str()), Some("The Star"));
+        assert!(sign_trump(12).is_none());
+    }
+}
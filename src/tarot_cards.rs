@@ -1,9 +1,90 @@
 //! Tarot card database and lookup functionality
-use std::collections::HashMap;
-use std::path::PathBuf;
+//!
+//! Cards are read from the English `cards.csv`. With `tarot.language` set, names and meanings
+//! are also read from `cards.<language>.csv` in the deck directory, and shown in place of the
+//! English wherever the translation has them.
 use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::tarot_dignities::CardDignity;
+
+/// Trumps in order of their number, from 0 (The Fool) to XXI (The Universe)
+const TRUMPS: [&str; 22] = [
+    "The Fool",
+    "The Magus",
+    "The Priestess",
+    "The Empress",
+    "The Emperor",
+    "The Hierophant",
+    "The Lovers",
+    "The Chariot",
+    "Adjustment",
+    "The Hermit",
+    "Fortune",
+    "Lust",
+    "The Hanged Man",
+    "Death",
+    "Art",
+    "The Devil",
+    "The Tower",
+    "The Star",
+    "The Moon",
+    "The Sun",
+    "The Aeon",
+    "The Universe",
+];
+
+/// Names of pip values, from Ace (1) to Ten
+const PIPS: [&str; 10] = [
+    "Ace", "Two", "Three", "Four", "Five", "Six", "Seven", "Eight", "Nine", "Ten",
+];
+
+/// Court cards, from lowest to highest
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Court {
+    Princess,
+    Prince,
+    Queen,
+    Knight,
+}
+
+/// A card's place in the deck: a numbered trump, a pip value or a court rank
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Rank {
+    Trump(u8),
+    Pip(u8),
+    Court(Court),
+}
+
+/// Format a trump number as a Roman numeral, with 0 for The Fool
+pub fn roman_numeral(mut n: u8) -> String {
+    const NUMERALS: [(u8, &str); 5] = [(20, "XX"), (10, "X"), (9, "IX"), (5, "V"), (4, "IV")];
+
+    if n == 0 {
+        return "0".to_string();
+    }
+
+    let mut out = String::new();
+    for (value, numeral) in NUMERALS {
+        while n >= value {
+            out.push_str(numeral);
+            n -= value;
+        }
+    }
+    out.push_str(&"I".repeat(n.into()));
+    out
+}
+
+/// Look up a trump by its number
+pub fn find_trump(number: u8) -> Option<&'static TarotCard> {
+    TRUMPS
+        .get(usize::from(number))
+        .and_then(|name| CARD_DATABASE.get(&normalize_card_name(name)))
+}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct TarotCard {
     pub card: String,
     pub image: String,
@@ -34,14 +115,58 @@ impl TarotCard {
         normalize_card_name(&self.card)
     }
 
-    /// Get display name (uses title if available, otherwise card name)
-    pub fn display_name(&self) -> String {
-        if let Some(title) = &self.title {
-            if !title.is_empty() {
-                return format!("{} ({})", self.card, title);
-            }
+    /// Whether this card belongs to the Major Arcana
+    pub fn is_major(&self) -> bool {
+        self.suit_1.is_none() && self.suit_2.is_none()
+    }
+
+    /// The card's trump number, pip value or court rank
+    pub fn rank(&self) -> Option<Rank> {
+        if let Some(n) = TRUMPS.iter().position(|t| *t == self.card) {
+            return Some(Rank::Trump(n as u8));
+        }
+
+        let first = self.card.split_whitespace().next()?;
+        if let Some(n) = PIPS.iter().position(|p| *p == first) {
+            return Some(Rank::Pip(n as u8 + 1));
         }
-        self.card.clone()
+
+        let court = match first {
+            "Princess" => Court::Princess,
+            "Prince" => Court::Prince,
+            "Queen" => Court::Queen,
+            "Knight" => Court::Knight,
+            _ => return None,
+        };
+
+        Some(Rank::Court(court))
+    }
+
+    /// The number a card counts as: a trump's number or a pip's value; courts have none
+    pub fn number(&self) -> Option<u8> {
+        match self.rank()? {
+            Rank::Trump(n) | Rank::Pip(n) => Some(n),
+            Rank::Court(_) => None,
+        }
+    }
+
+    /// Values this card counts towards for an attribute type, as the server tallies them
+    ///
+    /// Besides the server's `suit`, `element`, `planet`, `sign` and `sephira`, the
+    /// `arcana` type splits cards into the Major and Minor Arcana.
+    pub fn attribute_values(&self, attribute_type: &str) -> Vec<&str> {
+        let fields = match attribute_type {
+            "suit" => vec![&self.suit_1, &self.suit_2],
+            "element" => vec![&self.element_1, &self.element_2],
+            "planet" => vec![&self.planet_orb, &self.planet_house],
+            "sign" => vec![&self.sign_1, &self.sign_2, &self.sign_3],
+            "sephira" => vec![&self.sephira],
+            "arcana" if self.is_major() => return vec!["Major Arcana"],
+            "arcana" => return vec!["Minor Arcana"],
+            _ => vec![],
+        };
+
+        fields.into_iter().flatten().map(String::as_str).collect()
     }
 }
 
@@ -55,6 +180,16 @@ pub fn normalize_card_name(name: &str) -> String {
         .replace("-", "")
 }
 
+/// Normalize a card name with the word "of" left out, but not the letters in other words
+fn without_of(name: &str) -> String {
+    let words: Vec<&str> = name
+        .split_whitespace()
+        .filter(|word| !word.eq_ignore_ascii_case("of"))
+        .collect();
+
+    normalize_card_name(&words.join(" "))
+}
+
 /// Get the deck directory path
 pub fn get_deck_dir() -> PathBuf {
     // Try environment variable first
@@ -66,6 +201,11 @@ pub fn get_deck_dir() -> PathBuf {
     PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("deck")
 }
 
+/// Get the path of the image used for the back of every card
+pub fn card_back_path() -> PathBuf {
+    get_deck_dir().join("card_back.jpg")
+}
+
 /// Parse a CSV line handling quoted fields
 fn parse_csv_line(line: &str) -> Vec<String> {
     let mut fields = Vec::new();
@@ -148,6 +288,143 @@ fn load_cards_from_csv() -> HashMap<String, TarotCard> {
 /// Global card database
 static CARD_DATABASE: Lazy<HashMap<String, TarotCard>> = Lazy::new(load_cards_from_csv);
 
+/// A card's name and meanings in another language, as far as they have been translated
+#[derive(Clone, Debug, Default)]
+struct LocalCard {
+    name: Option<String>,
+    title: Option<String>,
+    /// Other names the card can be looked up by, such as spellings without accents
+    aliases: Vec<String>,
+    info: Option<String>,
+    deepinfo: Option<String>,
+}
+
+/// Card names and meanings in the reader's language, falling back to the deck's English
+#[derive(Debug, Default)]
+pub struct Translation {
+    /// Translated cards, by their English search key
+    cards: HashMap<String, LocalCard>,
+}
+
+fn non_empty(field: Option<&String>) -> Option<String> {
+    field.filter(|s| !s.is_empty()).cloned()
+}
+
+impl Translation {
+    /// Load `cards.<language>.csv` from a deck directory; English needs no file
+    pub fn load(deck_dir: &Path, language: &str) -> Result<Self, String> {
+        if language.eq_ignore_ascii_case("en") {
+            return Ok(Translation::default());
+        }
+
+        let path = deck_dir.join(format!("cards.{}.csv", language));
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Can't read card translations from {}: {}", path.display(), e))?;
+
+        Translation::parse(&content).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Load the language set as `tarot.language` from the deck directory, if one is set
+    pub fn configured(language: Option<&str>) -> Result<Self, String> {
+        match language {
+            Some(language) => Translation::load(&get_deck_dir(), language),
+            None => Ok(Translation::default()),
+        }
+    }
+
+    /// Parse a translation, with columns Card, Name, Title, Aliases, info and deepinfo
+    ///
+    /// Card is the English name of the card being translated, and Aliases are separated by
+    /// semicolons. Cards and fields left out are shown in English.
+    fn parse(content: &str) -> Result<Self, String> {
+        let mut cards = HashMap::new();
+
+        for (n, line) in content.lines().enumerate().skip(1) {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let fields = parse_csv_line(line);
+            let key = normalize_card_name(&fields[0]);
+            if !CARD_DATABASE.contains_key(&key) {
+                return Err(format!("line {}: no card named '{}' in the deck", n + 1, fields[0]));
+            }
+
+            let aliases = fields.get(3).map(String::as_str).unwrap_or_default();
+            let aliases = aliases
+                .split(';')
+                .map(str::trim)
+                .filter(|a| !a.is_empty())
+                .map(String::from)
+                .collect();
+
+            let card = LocalCard {
+                name: non_empty(fields.get(1)),
+                title: non_empty(fields.get(2)),
+                aliases,
+                info: non_empty(fields.get(4)),
+                deepinfo: non_empty(fields.get(5)),
+            };
+
+            cards.insert(key, card);
+        }
+
+        Ok(Translation { cards })
+    }
+
+    fn get(&self, card: &TarotCard) -> Option<&LocalCard> {
+        self.cards.get(&card.search_key())
+    }
+
+    /// The card's name in this language
+    pub fn name<'a>(&'a self, card: &'a TarotCard) -> &'a str {
+        self.get(card)
+            .and_then(|local| local.name.as_deref())
+            .unwrap_or(&card.card)
+    }
+
+    /// The card's title in this language, if it has one
+    pub fn title<'a>(&'a self, card: &'a TarotCard) -> Option<&'a str> {
+        self.get(card)
+            .and_then(|local| local.title.as_deref())
+            .or(card.title.as_deref())
+            .filter(|title| !title.is_empty())
+    }
+
+    pub fn info<'a>(&'a self, card: &'a TarotCard) -> Option<&'a str> {
+        self.get(card)
+            .and_then(|local| local.info.as_deref())
+            .or(card.info.as_deref())
+    }
+
+    pub fn deepinfo<'a>(&'a self, card: &'a TarotCard) -> Option<&'a str> {
+        self.get(card)
+            .and_then(|local| local.deepinfo.as_deref())
+            .or(card.deepinfo.as_deref())
+    }
+
+    /// Find a card by flexible name matching, in this language or in English
+    pub fn find_card(&self, query: &str) -> Option<&'static TarotCard> {
+        self.find(query).or_else(|| find_card(query))
+    }
+
+    /// Find a card by its name, title or one of its aliases in this language
+    fn find(&self, query: &str) -> Option<&'static TarotCard> {
+        let normalized_query = normalize_card_name(query);
+
+        let (key, _) = self.cards.iter().find(|(_, local)| {
+            local
+                .name
+                .iter()
+                .chain(&local.title)
+                .chain(&local.aliases)
+                .any(|name| normalize_card_name(name) == normalized_query)
+        })?;
+
+        CARD_DATABASE.get(key)
+    }
+}
+
 /// Find a card by flexible name matching (including by title)
 pub fn find_card(query: &str) -> Option<&'static TarotCard> {
     let normalized_query = normalize_card_name(query);
@@ -168,6 +445,15 @@ pub fn find_card(query: &str) -> Option<&'static TarotCard> {
         return Some(card);
     }
     
+    // Match ignoring "of", so "six swords" finds the Six of Swords
+    let query_without_of = without_of(query);
+    if let Some(card) = CARD_DATABASE
+        .values()
+        .find(|card| without_of(&card.card) == query_without_of)
+    {
+        return Some(card);
+    }
+    
     // Partial match - find first card that contains the query in name or title
     CARD_DATABASE.values().find(|card| {
         let key = card.search_key();
@@ -184,26 +470,62 @@ pub fn find_card(query: &str) -> Option<&'static TarotCard> {
     })
 }
 
+/// Format a card's name, title, elemental dignity and requested meanings as message text, in
+/// the reader's language
+///
+/// The user's own note on the card, if they have written one, follows the deck's meanings.
+pub fn format_card_info(
+    card: &TarotCard,
+    show_info: bool,
+    show_deepinfo: bool,
+    dignity: Option<&CardDignity>,
+    note: Option<&str>,
+    lang: &Translation,
+) -> String {
+    let mut text = format!("**{}**", lang.name(card));
+
+    if let Some(title) = lang.title(card) {
+        text.push_str(&format!(" ({})", title));
+    }
+    text.push_str("\n\n");
+
+    if let Some(dignity) = dignity {
+        text.push_str(&dignity.describe());
+        text.push_str("\n\n");
+    }
+
+    if show_info {
+        if let Some(info) = lang.info(card) {
+            text.push_str(info);
+            text.push_str("\n\n");
+        }
+    }
+
+    if show_deepinfo {
+        if let Some(deepinfo) = lang.deepinfo(card) {
+            text.push_str(deepinfo);
+            text.push_str("\n\n");
+        }
+    }
+
+    if let Some(note) = note.filter(|_| show_info || show_deepinfo) {
+        text.push_str("**My notes:** ");
+        text.push_str(note);
+        text.push_str("\n\n");
+    }
+
+    text
+}
+
 /// Get all cards
 pub fn get_all_cards() -> Vec<&'static TarotCard> {
     CARD_DATABASE.values().collect()
 }
 
-/// Get cards by suit
-pub fn get_cards_by_suit(suit: &str) -> Vec<&'static TarotCard> {
-    let normalized_suit = suit.to_lowercase();
-    CARD_DATABASE.values()
-        .filter(|card| {
-            card.suit_1.as_ref().map(|s| s.to_lowercase() == normalized_suit).unwrap_or(false) ||
-            card.suit_2.as_ref().map(|s| s.to_lowercase() == normalized_suit).unwrap_or(false)
-        })
-        .collect()
-}
-
 /// Get major arcana cards
 pub fn get_major_arcana() -> Vec<&'static TarotCard> {
     CARD_DATABASE.values()
-        .filter(|card| card.suit_1.is_none() && card.suit_2.is_none())
+        .filter(|card| card.is_major())
         .collect()
 }
 
@@ -231,6 +553,11 @@ mod tests {
         assert!(find_card("The Fool").is_some());
         assert!(find_card("six swords").is_some());
         assert!(find_card("6 swords").is_none()); // Number not in name
+
+        // Only the word "of" is left out, not the letters inside other words
+        assert_eq!(without_of("Six of Swords"), "sixswords");
+        assert_eq!(without_of("an offering of profession"), "anofferingprofession");
+        assert!(find_card("prince offering").is_none());
     }
 
     #[test]
@@ -239,4 +566,110 @@ mod tests {
         assert_eq!(get_major_arcana().len(), 22);
         assert_eq!(get_minor_arcana().len(), 56);
     }
+
+    #[test]
+    fn test_attribute_values() {
+        let card = find_card("six of swords").unwrap();
+        assert_eq!(card.attribute_values("suit"), vec!["Swords"]);
+        assert_eq!(card.attribute_values("element"), vec!["Air"]);
+        assert_eq!(card.attribute_values("planet"), vec!["Mercury", "Sol"]);
+        assert_eq!(card.attribute_values("arcana"), vec!["Minor Arcana"]);
+
+        let card = find_card("star").unwrap();
+        assert!(card.attribute_values("suit").is_empty());
+        assert_eq!(card.attribute_values("sign"), vec!["Aquarius"]);
+        assert_eq!(card.attribute_values("arcana"), vec!["Major Arcana"]);
+        assert!(card.attribute_values("colour").is_empty());
+    }
+
+    #[test]
+    fn test_card_ranks() {
+        assert_eq!(find_card("fool").unwrap().rank(), Some(Rank::Trump(0)));
+        assert_eq!(find_card("universe").unwrap().rank(), Some(Rank::Trump(21)));
+        assert_eq!(find_card("adjustment").unwrap().number(), Some(8));
+        assert_eq!(find_card("ace of cups").unwrap().rank(), Some(Rank::Pip(1)));
+        assert_eq!(find_card("ten of disks").unwrap().number(), Some(10));
+        assert_eq!(find_card("queen of swords").unwrap().rank(), Some(Rank::Court(Court::Queen)));
+        assert_eq!(find_card("queen of swords").unwrap().number(), None);
+
+        // Every card in the deck has a rank
+        assert!(get_all_cards().iter().all(|card| card.rank().is_some()));
+        assert_eq!(find_trump(4).unwrap().card, "The Emperor");
+        assert!(find_trump(22).is_none());
+    }
+
+    #[test]
+    fn test_roman_numeral() {
+        assert_eq!(roman_numeral(0), "0");
+        assert_eq!(roman_numeral(4), "IV");
+        assert_eq!(roman_numeral(9), "IX");
+        assert_eq!(roman_numeral(14), "XIV");
+        assert_eq!(roman_numeral(19), "XIX");
+        assert_eq!(roman_numeral(21), "XXI");
+    }
+
+    #[test]
+    fn test_format_card_info_note() {
+        let star = find_card("star").unwrap();
+        let note = Some("Came up the week I moved");
+
+        let english = Translation::default();
+
+        let text = format_card_info(star, true, false, None, note, &english);
+        assert!(text.starts_with("**The Star**\n\nHope."));
+        assert!(text.ends_with("Disappointment.\n\n**My notes:** Came up the week I moved\n\n"));
+
+        // The note goes with the deck's meanings, so it isn't shown without them
+        assert_eq!(format_card_info(star, false, false, None, note, &english), "**The Star**\n\n");
+    }
+
+    #[test]
+    fn test_translation() {
+        let content = "Card,Name,Title,Aliases,info,deepinfo\n\
+            Six of Swords,Seis de Espadas,Ciencia,6 de espadas; seis espadas,\"Ciencia. Viaje por agua.\",\n\
+            The Star,La Estrella,,Estrella,,\n";
+        let spanish = Translation::parse(content).unwrap();
+
+        let found = |query| spanish.find(query).map(|card| card.card.as_str());
+        assert_eq!(found("seis de espadas"), Some("Six of Swords"));
+        assert_eq!(found("Ciencia"), Some("Six of Swords"));
+        assert_eq!(found("6 de espadas"), Some("Six of Swords"));
+        assert_eq!(found("estrella"), Some("The Star"));
+        assert!(spanish.find("six of swords").is_none());
+        assert_eq!(spanish.find_card("six of swords").unwrap().card, "Six of Swords");
+
+        let six = find_card("six of swords").unwrap();
+        let text = format_card_info(six, true, true, None, None, &spanish);
+        assert!(text.starts_with("**Seis de Espadas** (Ciencia)\n\nCiencia. Viaje por agua.\n\n"));
+
+        // Whatever hasn't been translated is shown in English
+        assert!(text.contains("Tiphareth in the suit of Air"));
+        let fool = find_card("fool").unwrap();
+        assert_eq!(format_card_info(fool, false, false, None, None, &spanish), "**The Fool**\n\n");
+
+        let err = Translation::parse("Card,Name\nSix of Quills,Seis de Plumas\n").unwrap_err();
+        assert_eq!(err, "line 2: no card named 'Six of Quills' in the deck");
+    }
+
+    #[test]
+    fn test_deck_translations() {
+        for language in ["es", "de"].iter() {
+            let translation = Translation::load(&get_deck_dir(), language).unwrap();
+
+            // Every card has a name, and can be found by it
+            for card in get_all_cards() {
+                assert!(translation.get(card).and_then(|local| local.name.as_ref()).is_some());
+
+                let found = translation.find(translation.name(card)).map(|found| &found.card);
+                assert_eq!(found, Some(&card.card), "in {}", language);
+            }
+        }
+
+        let german = Translation::load(&get_deck_dir(), "de").unwrap();
+        assert_eq!(german.find("koenigin der kelche").unwrap().card, "Queen of Cups");
+        assert_eq!(german.title(find_card("two of cups").unwrap()), Some("Liebe"));
+
+        assert!(Translation::load(&get_deck_dir(), "en").unwrap().cards.is_empty());
+        assert!(Translation::load(&get_deck_dir(), "xx").is_err());
+    }
 }
@@ -0,0 +1,380 @@
+//! Tarot commands run from the shell, without starting the terminal UI
+//!
+//! `iamb tarot ...` prints its results and exits, so that the deck can be used from scripts
+//! and cron jobs. Each command takes `--json` to print machine-readable output instead of
+//! text meant for people.
+use std::path::{Path, PathBuf};
+
+use clap::Subcommand;
+use serde::Serialize;
+
+use crate::config::{ApplicationSettings, CompositeFormat, HistoryBackend};
+use crate::tarot_annotations::Annotations;
+use crate::tarot_cards::{self, TarotCard, Translation};
+use crate::tarot_composite::{self, CompositeOptions};
+use crate::tarot_dignities;
+use crate::tarot_spreads::{self, Spread};
+use crate::tarot_store;
+
+#[derive(Clone, Debug, Eq, PartialEq, Subcommand)]
+pub enum TarotCommand {
+    /// Draw a spread at random, without recording it in the reading history
+    Draw {
+        /// A spread name (celtic, threecard, daily) or a number of cards from 1 to 10
+        #[clap(short, long, default_value = "3")]
+        spread: String,
+
+        /// The question the spread is drawn for
+        #[clap(short, long)]
+        question: Option<String>,
+
+        #[clap(long)]
+        json: bool,
+    },
+
+    /// Show a card's meanings, and your notes on it
+    Card {
+        /// The card's name or trump number, e.g. "six of swords" or 17
+        name: Vec<String>,
+
+        #[clap(long)]
+        json: bool,
+    },
+
+    /// Lay out cards side by side in one image
+    Composite {
+        /// The cards to lay out, separated by commas
+        #[clap(short, long, value_delimiter = ',', required = true)]
+        cards: Vec<String>,
+
+        /// Where to save the image; its extension (jpg, webp or png) picks the format
+        #[clap(short, long)]
+        output: PathBuf,
+
+        #[clap(long)]
+        json: bool,
+    },
+
+    /// List the profile's past readings, newest first
+    History {
+        /// Show only this many readings
+        #[clap(short = 'n', long)]
+        limit: Option<usize>,
+
+        #[clap(long)]
+        json: bool,
+    },
+}
+
+/// A drawn card, in the output of `iamb tarot draw`
+#[derive(Debug, Serialize)]
+struct DrawnCard<'a> {
+    position: usize,
+    label: &'a str,
+    card: &'a str,
+    title: Option<&'a str>,
+    dignity: &'static str,
+    image: PathBuf,
+}
+
+#[derive(Debug, Serialize)]
+struct Draw<'a> {
+    spread: &'a str,
+    question: Option<&'a str>,
+    cards: Vec<DrawnCard<'a>>,
+}
+
+/// A card and its meanings, in the output of `iamb tarot card`
+#[derive(Debug, Serialize)]
+struct CardOutput<'a> {
+    #[serde(flatten)]
+    card: &'a TarotCard,
+    image_path: PathBuf,
+    note: Option<&'a str>,
+}
+
+/// The image written by `iamb tarot composite`
+#[derive(Debug, Serialize)]
+struct CompositeOutput<'a> {
+    path: &'a Path,
+    mime: String,
+    bytes: usize,
+    width: u32,
+    height: u32,
+    blurhash: &'a str,
+}
+
+/// Run a tarot command, printing its results to stdout
+pub fn run(cmd: TarotCommand, settings: &ApplicationSettings) -> Result<(), String> {
+    let lang = Translation::configured(settings.tunables.tarot.language.as_deref())?;
+
+    match cmd {
+        TarotCommand::Draw { spread, question, json } => {
+            draw(&spread, question.as_deref(), &lang, json)
+        },
+        TarotCommand::Card { name, json } => card(&name.join(" "), settings, &lang, json),
+        TarotCommand::Composite { cards, output, json } => {
+            composite(&cards, &output, settings, &lang, json)
+        },
+        TarotCommand::History { limit, json } => history(limit, settings, json),
+    }
+}
+
+/// Look up a spread the way `:tarot` does: by name, or by its number of cards
+fn parse_spread(name: &str) -> Result<Spread, String> {
+    if name.eq_ignore_ascii_case(tarot_spreads::DAILY) {
+        return Ok(Spread::daily());
+    }
+
+    if let Ok(count) = name.parse::<usize>() {
+        if !(1..=10).contains(&count) {
+            return Err("Card count must be between 1 and 10".into());
+        }
+
+        return Ok(Spread::numbered(count));
+    }
+
+    tarot_spreads::find_spread(name).ok_or_else(|| format!("Unknown spread: '{}'", name))
+}
+
+/// Look up a card by its name in English or the reader's language, or a trump by its number
+fn find_card(lang: &Translation, name: &str) -> Result<&'static TarotCard, String> {
+    let card = match name.parse::<u8>() {
+        Ok(number) => tarot_cards::find_trump(number),
+        Err(_) => lang.find_card(name),
+    };
+
+    card.ok_or_else(|| format!("Card not found: '{}'", name))
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", json);
+
+    Ok(())
+}
+
+fn draw(
+    spread: &str,
+    question: Option<&str>,
+    lang: &Translation,
+    json: bool,
+) -> Result<(), String> {
+    let spread = parse_spread(spread)?;
+    let cards = tarot_spreads::draw_cards(spread.card_count())?;
+    let dignities = tarot_dignities::spread_dignities(&cards);
+
+    let drawn = cards
+        .iter()
+        .zip(&spread.positions)
+        .zip(&dignities)
+        .enumerate()
+        .map(|(i, ((card, label), dignity))| {
+            DrawnCard {
+                position: i + 1,
+                label,
+                card: &card.card,
+                title: card.title.as_deref(),
+                dignity: dignity.as_str(),
+                image: card.image_path(),
+            }
+        })
+        .collect();
+    let draw = Draw { spread: &spread.name, question, cards: drawn };
+
+    if json {
+        return print_json(&draw);
+    }
+
+    if let Some(question) = draw.question {
+        println!("Question: {}", question);
+    }
+
+    // JSON keeps the deck's English names, but people read them in their own language
+    for (card, drawn) in cards.iter().zip(&draw.cards) {
+        match lang.title(card) {
+            Some(title) => {
+                println!("{}. {}: {} ({})", drawn.position, drawn.label, lang.name(card), title)
+            },
+            None => println!("{}. {}: {}", drawn.position, drawn.label, lang.name(card)),
+        }
+    }
+
+    Ok(())
+}
+
+fn card(
+    name: &str,
+    settings: &ApplicationSettings,
+    lang: &Translation,
+    json: bool,
+) -> Result<(), String> {
+    let card = find_card(lang, name)?;
+
+    let notes = Annotations::load(&settings.tarot_annotations_json)?;
+
+    if json {
+        let output = CardOutput {
+            card,
+            image_path: card.image_path(),
+            note: notes.get(card),
+        };
+
+        return print_json(&output);
+    }
+
+    print!("{}", tarot_cards::format_card_info(card, true, true, None, notes.get(card), lang));
+
+    Ok(())
+}
+
+fn composite(
+    names: &[String],
+    output: &Path,
+    settings: &ApplicationSettings,
+    lang: &Translation,
+    json: bool,
+) -> Result<(), String> {
+    let paths = names
+        .iter()
+        .map(|name| Ok(find_card(lang, name.trim())?.image_path().to_string_lossy().to_string()))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let extension = output.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+    let format = CompositeFormat::from_extension(extension).ok_or_else(|| {
+        format!("Can't tell the image format of {}; use .jpg, .webp or .png", output.display())
+    })?;
+    let opts = CompositeOptions {
+        format,
+        ..CompositeOptions::from_settings(settings)
+    };
+
+    let image = tarot_composite::create_composite_spread(&paths, &opts)?;
+    std::fs::write(output, &image.bytes)
+        .map_err(|e| format!("Failed to save {}: {}", output.display(), e))?;
+
+    if json {
+        let output = CompositeOutput {
+            path: output,
+            mime: image.mime.to_string(),
+            bytes: image.bytes.len(),
+            width: image.width,
+            height: image.height,
+            blurhash: &image.blurhash,
+        };
+
+        return print_json(&output);
+    }
+
+    println!("{}", output.display());
+
+    Ok(())
+}
+
+fn history(limit: Option<usize>, settings: &ApplicationSettings, json: bool) -> Result<(), String> {
+    // Account data can only be read once logged in, which commands run from the shell never do
+    if settings.tunables.tarot.history == HistoryBackend::Matrix {
+        let msg = "The reading history in Matrix account data can't be read from the command \
+                   line; use :tarot history inside iamb instead";
+        return Err(msg.into());
+    }
+
+    let matrix_id = settings.profile.user_id.to_string();
+    let mut history = tarot_store::open(settings)?.history(&matrix_id)?;
+
+    if let Some(limit) = limit {
+        history.readings.truncate(limit);
+    }
+
+    if json {
+        return print_json(&history);
+    }
+
+    for reading in &history.readings {
+        let cards: Vec<&str> = reading.cards.iter().map(|card| card.card_name.as_str()).collect();
+        println!("{}  {}: {}", reading.reading_date, reading.spread_type, cards.join(", "));
+
+        if let Some(question) = &reading.question {
+            println!("    {}", question);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Iamb, IambCommand};
+    use crate::tests::mock_settings;
+    use clap::Parser;
+
+    fn parse(args: &[&str]) -> TarotCommand {
+        let iamb = Iamb::try_parse_from(args).unwrap();
+
+        match iamb.command {
+            Some(IambCommand::Tarot { command }) => command,
+            None => panic!("no command parsed"),
+        }
+    }
+
+    #[test]
+    fn test_parse_tarot_command() {
+        let cmd = parse(&["iamb", "tarot", "draw", "--spread", "celtic", "--json"]);
+        let spread = "celtic".to_string();
+        assert_eq!(cmd, TarotCommand::Draw { spread, question: None, json: true });
+
+        let cmd = parse(&["iamb", "-P", "work", "tarot", "card", "six", "of", "swords"]);
+        let name = vec!["six".into(), "of".into(), "swords".into()];
+        assert_eq!(cmd, TarotCommand::Card { name, json: false });
+
+        let cmd = parse(&[
+            "iamb",
+            "tarot",
+            "composite",
+            "--cards",
+            "fool,six of swords",
+            "-o",
+            "out.png",
+        ]);
+        let cards = vec!["fool".into(), "six of swords".into()];
+        let output = PathBuf::from("out.png");
+        assert_eq!(cmd, TarotCommand::Composite { cards, output, json: false });
+
+        let cmd = parse(&["iamb", "tarot", "history", "-n", "5", "--json"]);
+        assert_eq!(cmd, TarotCommand::History { limit: Some(5), json: true });
+
+        assert!(Iamb::try_parse_from(["iamb", "tarot", "composite", "-o", "out.png"]).is_err());
+        assert!(Iamb::try_parse_from(["iamb"]).unwrap().command.is_none());
+    }
+
+    #[test]
+    fn test_find_card() {
+        let english = Translation::default();
+        assert_eq!(find_card(&english, "six of swords").unwrap().card, "Six of Swords");
+        assert_eq!(find_card(&english, "17").unwrap().card, "The Star");
+        assert_eq!(find_card(&english, "22").unwrap_err(), "Card not found: '22'");
+
+        let german = Translation::load(&tarot_cards::get_deck_dir(), "de").unwrap();
+        assert_eq!(find_card(&german, "der stern").unwrap().card, "The Star");
+        assert_eq!(find_card(&german, "six of swords").unwrap().card, "Six of Swords");
+    }
+
+    #[test]
+    fn test_parse_spread() {
+        assert_eq!(parse_spread("celtic").unwrap(), Spread::celtic_cross());
+        assert_eq!(parse_spread("Daily").unwrap(), Spread::daily());
+        assert_eq!(parse_spread("5").unwrap(), Spread::numbered(5));
+        assert_eq!(parse_spread("11").unwrap_err(), "Card count must be between 1 and 10");
+        assert_eq!(parse_spread("pentagram").unwrap_err(), "Unknown spread: 'pentagram'");
+    }
+
+    #[test]
+    fn test_history_in_account_data() {
+        let mut settings = mock_settings();
+        settings.tunables.tarot.history = HistoryBackend::Matrix;
+
+        let err = history(None, &settings, false).unwrap_err();
+        assert!(err.contains("can't be read from the command line"), "{}", err);
+    }
+}
@@ -1,67 +1,179 @@
 //! Composite image generation for tarot spreads
-use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba, RgbaImage};
-use std::path::Path;
+//!
+//! Cards are scaled down once and kept in a thumbnail cache under the cache directory, a
+//! folder per deck and card width, so that drawing a spread only decodes small images. They
+//! are decoded in parallel, laid out in rows, and encoded in the configured format, lowering
+//! the quality and then the size until the composite fits in the upload budget. The result
+//! stays in memory until it's uploaded.
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
 
-/// Create a composite image from multiple card images arranged in rows
-/// Max 5 cards per row, then wraps to next row
-pub fn create_composite_spread(card_paths: &[String]) -> Result<Vec<u8>, String> {
-    if card_paths.is_empty() {
-        return Err("No cards provided".to_string());
+use image::buffer::ConvertBuffer;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ExtendedColorType, ImageBuffer, ImageFormat, RgbImage, Rgba, RgbaImage};
+use rayon::prelude::*;
+
+use crate::config::{ApplicationSettings, CompositeFormat, TarotComposite};
+use crate::image_upload::ImageUpload;
+
+/// Qualities tried in turn when encoding a JPEG composite
+const JPEG_QUALITIES: [u8; 4] = [85, 70, 55, 40];
+
+/// How much a composite is scaled down each time it misses the byte budget
+const SHRINK: f64 = 0.75;
+
+/// Quality of the cached thumbnails, which are decoded far faster as JPEGs than as PNGs
+const THUMBNAIL_QUALITY: u8 = 92;
+
+/// Composites aren't shrunk any narrower than this to fit the budget
+const MIN_WIDTH: u32 = 320;
+
+/// How composites are built, and where scaled cards are cached
+#[derive(Clone, Debug)]
+pub struct CompositeOptions {
+    /// Width that cards are scaled down to, or 0 to keep them at full size
+    pub card_width: u32,
+    pub format: CompositeFormat,
+    /// Largest composite in bytes, or 0 for no limit
+    pub max_bytes: usize,
+    /// Where scaled cards are kept between draws, if anywhere
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl CompositeOptions {
+    pub fn new(composite: &TarotComposite, cache_dir: Option<PathBuf>) -> Self {
+        CompositeOptions {
+            card_width: composite.card_width,
+            format: composite.format,
+            max_bytes: composite.max_bytes,
+            cache_dir,
+        }
     }
-    
-    if card_paths.len() > 10 {
-        return Err("Maximum 10 cards supported".to_string());
+
+    /// The options configured under `[settings.tarot.composite]`, caching in the cache directory
+    pub fn from_settings(settings: &ApplicationSettings) -> Self {
+        let cache_dir = settings.dirs.cache.join("tarot");
+
+        CompositeOptions::new(&settings.tunables.tarot.composite, Some(cache_dir))
     }
-    
-    // Load all card images
-    let mut images: Vec<DynamicImage> = Vec::new();
-    for path in card_paths {
-        let img = image::open(Path::new(path))
-            .map_err(|e| format!("Failed to load image {}: {}", path, e))?;
-        images.push(img);
+}
+
+/// Where the scaled copy of a card is cached, under a folder for its deck and the width
+fn thumbnail_path(cache_dir: &Path, card: &Path, width: u32) -> Option<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    card.parent()?.hash(&mut hasher);
+
+    let deck = format!("{:016x}-{}", hasher.finish(), width);
+    let name = format!("{}.jpg", card.file_stem()?.to_string_lossy());
+
+    Some(cache_dir.join(deck).join(name))
+}
+
+/// Whether a cached thumbnail is at least as new as the card it was made from
+fn is_fresh(thumbnail: &Path, card: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+
+    match (modified(thumbnail), modified(card)) {
+        (Some(thumbnail), Some(card)) => thumbnail >= card,
+        _ => false,
+    }
+}
+
+/// Scale a card down to `width`, keeping its proportions
+fn scale_card(img: DynamicImage, width: u32) -> DynamicImage {
+    if width == 0 || img.width() <= width {
+        return img;
+    }
+
+    img.resize(width, u32::MAX, FilterType::Triangle)
+}
+
+/// Save a thumbnail, writing it elsewhere first so that it never appears half-written
+fn save_thumbnail(img: &DynamicImage, path: &Path) -> Result<(), String> {
+    let dir = path.parent().ok_or("Thumbnail has no directory")?;
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+
+    let mut bytes: Vec<u8> = Vec::new();
+    JpegEncoder::new_with_quality(&mut bytes, THUMBNAIL_QUALITY)
+        .encode_image(&img.to_rgb8())
+        .map_err(|e| e.to_string())?;
+
+    let tmp = path.with_extension(format!("{:08x}.tmp", rand::random::<u32>()));
+    fs::write(&tmp, bytes).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+/// Load a card scaled to the configured width, from the thumbnail cache if it's up to date
+fn load_card(path: &str, opts: &CompositeOptions) -> Result<DynamicImage, String> {
+    let path = Path::new(path);
+    let thumbnail = opts
+        .cache_dir
+        .as_deref()
+        .and_then(|dir| thumbnail_path(dir, path, opts.card_width));
+
+    if let Some(thumbnail) = &thumbnail {
+        if is_fresh(thumbnail, path) {
+            if let Ok(img) = image::open(thumbnail) {
+                return Ok(img);
+            }
+        }
     }
-    
-    if images.is_empty() {
-        return Err("No images loaded".to_string());
+
+    let img =
+        image::open(path).map_err(|e| format!("Failed to load image {}: {}", path.display(), e))?;
+    let img = scale_card(img, opts.card_width);
+
+    if let Some(thumbnail) = &thumbnail {
+        // A card that can't be cached is just scaled again on the next draw
+        if let Err(e) = save_thumbnail(&img, thumbnail) {
+            tracing::warn!("Failed to cache card thumbnail {}: {}", thumbnail.display(), e);
+        }
     }
-    
+
+    Ok(img)
+}
+
+/// Lay cards out in centred rows of up to five, on a dark background
+fn layout(images: &[&DynamicImage]) -> RgbaImage {
     // Get dimensions from first card (assume all cards are same size)
     let card_width = images[0].width();
     let card_height = images[0].height();
-    
+
     // Calculate layout
     let cards_per_row = 5;
     let num_cards = images.len();
-    let num_rows = (num_cards + cards_per_row - 1) / cards_per_row; // Ceiling division
-    
+    let num_rows = num_cards.div_ceil(cards_per_row);
+
     let spacing = 10u32; // Pixels between cards
     let padding = 20u32; // Padding around edges
-    
+
     // Calculate composite dimensions
     let cards_in_first_row = std::cmp::min(num_cards, cards_per_row);
-    let cards_in_second_row = if num_cards > cards_per_row {
-        num_cards - cards_per_row
-    } else {
-        0
-    };
-    
+    let cards_in_second_row = num_cards.saturating_sub(cards_per_row);
+
     let first_row_width = cards_in_first_row as u32 * card_width + (cards_in_first_row as u32 - 1) * spacing;
     let second_row_width = if cards_in_second_row > 0 {
         cards_in_second_row as u32 * card_width + (cards_in_second_row as u32 - 1) * spacing
     } else {
         0
     };
-    
+
     let composite_width = std::cmp::max(first_row_width, second_row_width) + 2 * padding;
     let composite_height = num_rows as u32 * card_height + (num_rows as u32 - 1) * spacing + 2 * padding;
-    
+
     // Create composite image with dark background
     let mut composite: RgbaImage = ImageBuffer::from_pixel(
         composite_width,
         composite_height,
         Rgba([20, 20, 30, 255]) // Dark blue-gray background
     );
-    
+
     // Place cards
     let mut card_idx = 0;
     for row in 0..num_rows {
@@ -70,57 +182,208 @@ pub fn create_composite_spread(card_paths: &[String]) -> Result<Vec<u8>, String>
         } else {
             num_cards - cards_per_row
         };
-        
+
         let row_width = cards_in_this_row as u32 * card_width + (cards_in_this_row as u32 - 1) * spacing;
         let row_start_x = (composite_width - row_width) / 2; // Center the row
-        
+
         for col in 0..cards_in_this_row {
-            if card_idx >= images.len() {
-                break;
-            }
-            
             let x = row_start_x + col as u32 * (card_width + spacing);
             let y = padding + row as u32 * (card_height + spacing);
-            
-            // Overlay card image
-            let card = &images[card_idx];
-            image::imageops::overlay(&mut composite, card, x as i64, y as i64);
-            
+
+            // Cards are opaque, so they're copied in rather than blended
+            image::imageops::replace(&mut composite, &images[card_idx].to_rgba8(), x as i64, y as i64);
+
             card_idx += 1;
         }
     }
-    
-    // Encode to PNG bytes
-    let mut png_bytes: Vec<u8> = Vec::new();
-    composite.write_to(
-        &mut std::io::Cursor::new(&mut png_bytes),
-        image::ImageFormat::Png
-    ).map_err(|e| format!("Failed to encode PNG: {}", e))?;
-    
-    Ok(png_bytes)
+
+    composite
+}
+
+fn encode(img: &RgbaImage, format: CompositeFormat, quality: u8) -> Result<Vec<u8>, String> {
+    let mut bytes: Vec<u8> = Vec::new();
+
+    let res = match format {
+        CompositeFormat::Jpeg => {
+            // JPEG has no alpha channel
+            let rgb: RgbImage = img.convert();
+            JpegEncoder::new_with_quality(&mut bytes, quality).encode_image(&rgb)
+        },
+        CompositeFormat::Webp => {
+            let (width, height) = img.dimensions();
+            WebPEncoder::new_lossless(&mut bytes).encode(
+                img.as_raw(),
+                width,
+                height,
+                ExtendedColorType::Rgba8,
+            )
+        },
+        CompositeFormat::Png => img.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png),
+    };
+
+    res.map_err(|e| format!("Failed to encode composite: {}", e))?;
+
+    Ok(bytes)
+}
+
+/// Encode a composite, lowering the quality and then the size until it fits in the budget
+///
+/// Returns the encoded bytes and the image at the size it was encoded.
+pub fn encode_composite(
+    composite: RgbaImage,
+    opts: &CompositeOptions,
+) -> Result<(Vec<u8>, RgbaImage), String> {
+    // Lossless formats have only the one setting to try
+    let qualities: &[u8] = match opts.format {
+        CompositeFormat::Jpeg => &JPEG_QUALITIES,
+        CompositeFormat::Webp | CompositeFormat::Png => &[100],
+    };
+    let mut img = composite;
+
+    loop {
+        for &quality in qualities {
+            let bytes = encode(&img, opts.format, quality)?;
+
+            if opts.max_bytes == 0 || bytes.len() <= opts.max_bytes {
+                return Ok((bytes, img));
+            }
+        }
+
+        let width = (f64::from(img.width()) * SHRINK) as u32;
+        let height = (f64::from(img.height()) * SHRINK) as u32;
+
+        if width < MIN_WIDTH {
+            return Err(format!("The composite doesn't fit in {} bytes", opts.max_bytes));
+        }
+
+        img = image::imageops::resize(&img, width, height, FilterType::Triangle);
+    }
+}
+
+/// Create a composite image from multiple card images arranged in rows
+/// Max 5 cards per row, then wraps to next row
+pub fn create_composite_spread(
+    card_paths: &[String],
+    opts: &CompositeOptions,
+) -> Result<ImageUpload, String> {
+    if card_paths.is_empty() {
+        return Err("No cards provided".to_string());
+    }
+
+    if card_paths.len() > 10 {
+        return Err("Maximum 10 cards supported".to_string());
+    }
+
+    // Decode each distinct card once, in parallel; face-down spreads repeat the card back
+    let mut unique: Vec<&String> = card_paths.iter().collect();
+    unique.sort();
+    unique.dedup();
+
+    let loaded: HashMap<&String, DynamicImage> = unique
+        .into_par_iter()
+        .map(|path| Ok((path, load_card(path, opts)?)))
+        .collect::<Result<_, String>>()?;
+    let images: Vec<&DynamicImage> = card_paths.iter().map(|path| &loaded[path]).collect();
+
+    let (bytes, img) = encode_composite(layout(&images), opts)?;
+    let name = format!("tarot_spread.{}", opts.format.extension());
+
+    Ok(ImageUpload::new(name, opts.format.mime(), bytes, &DynamicImage::ImageRgba8(img)))
 }
 
-/// Save composite image to a temporary file and return the path
-pub fn save_composite_to_temp(card_paths: &[String]) -> Result<String, String> {
-    let png_bytes = create_composite_spread(card_paths)?;
-    
-    // Create temp file
-    let temp_dir = std::env::temp_dir();
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let temp_path = temp_dir.join(format!("tarot_spread_{}.png", timestamp));
-    
-    std::fs::write(&temp_path, png_bytes)
-        .map_err(|e| format!("Failed to write temp file: {}", e))?;
-    
-    Ok(temp_path.to_string_lossy().to_string())
+/// Colour every pixel within `width / 2` of the segment from `a` to `b`
+pub fn draw_line(img: &mut RgbaImage, a: (f64, f64), b: (f64, f64), width: f64, color: Rgba<u8>) {
+    let half = width / 2.0;
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len2 = dx * dx + dy * dy;
+
+    let x0 = (a.0.min(b.0) - half).floor().max(0.0) as u32;
+    let x1 = (a.0.max(b.0) + half).ceil().min(f64::from(img.width() - 1)) as u32;
+    let y0 = (a.1.min(b.1) - half).floor().max(0.0) as u32;
+    let y1 = (a.1.max(b.1) + half).ceil().min(f64::from(img.height() - 1)) as u32;
+
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            let (px, py) = (f64::from(x), f64::from(y));
+            let t = if len2 == 0.0 {
+                0.0
+            } else {
+                (((px - a.0) * dx + (py - a.1) * dy) / len2).clamp(0.0, 1.0)
+            };
+            let (cx, cy) = (a.0 + t * dx, a.1 + t * dy);
+
+            if (px - cx).powi(2) + (py - cy).powi(2) <= half * half {
+                img.put_pixel(x, y, color);
+            }
+        }
+    }
+}
+
+/// Fill a disc of radius `r` around `c`
+pub fn draw_disc(img: &mut RgbaImage, c: (f64, f64), r: f64, color: Rgba<u8>) {
+    let x0 = (c.0 - r).floor().max(0.0) as u32;
+    let x1 = (c.0 + r).ceil().min(f64::from(img.width() - 1)) as u32;
+    let y0 = (c.1 - r).floor().max(0.0) as u32;
+    let y1 = (c.1 + r).ceil().min(f64::from(img.height() - 1)) as u32;
+
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            if (f64::from(x) - c.0).powi(2) + (f64::from(y) - c.1).powi(2) <= r * r {
+                img.put_pixel(x, y, color);
+            }
+        }
+    }
+}
+
+/// Stroke an arc of radius `r` around `c`, from `start` to `end` degrees
+///
+/// Angles run anticlockwise from 3 o'clock, as on a chart rather than in image coordinates.
+pub fn draw_arc(
+    img: &mut RgbaImage,
+    c: (f64, f64),
+    r: f64,
+    (start, end): (f64, f64),
+    width: f64,
+    color: Rgba<u8>,
+) {
+    let point = |deg: f64| {
+        let rad = deg.to_radians();
+        (c.0 + r * rad.cos(), c.1 - r * rad.sin())
+    };
+
+    // Short enough segments that the arc looks smooth at any radius
+    let steps = ((end - start).abs() * r / 20.0).ceil().max(1.0) as usize;
+    let step = (end - start) / steps as f64;
+
+    for i in 0..steps {
+        let a = start + step * i as f64;
+        draw_line(img, point(a), point(a + step), width, color);
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::time::Instant;
+
+    use temp_dir::TempDir;
+
     use super::*;
+    use crate::tarot_cards;
+
+    fn options(card_width: u32, format: CompositeFormat, max_bytes: usize) -> CompositeOptions {
+        CompositeOptions { card_width, format, max_bytes, cache_dir: None }
+    }
+
+    fn spread(count: usize) -> Vec<String> {
+        let mut cards = tarot_cards::get_all_cards();
+        cards.sort_by(|a, b| a.card.cmp(&b.card));
+
+        cards
+            .iter()
+            .take(count)
+            .map(|card| card.image_path().to_string_lossy().to_string())
+            .collect()
+    }
 
     #[test]
     fn test_composite_layout() {
@@ -130,4 +393,103 @@ mod tests {
         assert_eq!((6 + 5 - 1) / 5, 2); // 6 cards = 2 rows
         assert_eq!((10 + 5 - 1) / 5, 2); // 10 cards = 2 rows
     }
+
+    #[test]
+    fn test_composite_spread() {
+        let opts = options(120, CompositeFormat::Png, 0);
+        let upload = create_composite_spread(&spread(3), &opts).unwrap();
+        let img = image::load_from_memory_with_format(&upload.bytes, ImageFormat::Png).unwrap();
+
+        // Three 120x176 cards, 10 pixels apart, with 20 pixels of padding
+        assert_eq!(img.width(), 3 * 120 + 2 * 10 + 2 * 20);
+        assert_eq!(img.height(), 176 + 2 * 20);
+        assert_eq!((upload.width, upload.height), (img.width(), img.height()));
+        assert_eq!(upload.name, "tarot_spread.png");
+        assert_eq!(upload.mime, mime::IMAGE_PNG);
+
+        let opts = options(120, CompositeFormat::Webp, 0);
+        let upload = create_composite_spread(&spread(6), &opts).unwrap();
+        let img = image::load_from_memory_with_format(&upload.bytes, ImageFormat::WebP).unwrap();
+        assert_eq!(img.height(), 2 * 176 + 10 + 2 * 20);
+        assert_eq!(upload.mime.as_ref(), "image/webp");
+
+        assert!(create_composite_spread(&[], &opts).is_err());
+        assert!(create_composite_spread(&spread(11), &opts).is_err());
+    }
+
+    #[test]
+    fn test_thumbnail_cache() {
+        let cache = TempDir::new().unwrap();
+        let mut opts = options(90, CompositeFormat::Jpeg, 0);
+        opts.cache_dir = Some(cache.path().to_path_buf());
+
+        let card = &spread(1)[0];
+        let thumbnail = thumbnail_path(cache.path(), Path::new(card), 90).unwrap();
+        assert_ne!(Some(&thumbnail), thumbnail_path(cache.path(), Path::new(card), 120).as_ref());
+        assert!(!thumbnail.exists());
+
+        let img = load_card(card, &opts).unwrap();
+        assert_eq!((img.width(), img.height()), (90, 132));
+        assert!(is_fresh(&thumbnail, Path::new(card)));
+
+        // Later loads come from the cache
+        let marker = DynamicImage::new_rgb8(10, 10);
+        save_thumbnail(&marker, &thumbnail).unwrap();
+        let cached = load_card(card, &opts).unwrap();
+        assert_eq!((cached.width(), cached.height()), (10, 10));
+    }
+
+    #[test]
+    fn test_encode_budget() {
+        // Noise compresses badly, so the budget forces the quality and size down
+        let noise = RgbaImage::from_fn(480, 360, |x, y| {
+            let v = ((x * 7919 + y * 104_729) % 251) as u8;
+            Rgba([v, v.wrapping_mul(3), v.wrapping_mul(7), 255])
+        });
+
+        let (full, _) =
+            encode_composite(noise.clone(), &options(0, CompositeFormat::Jpeg, 0)).unwrap();
+        let budget = full.len() / 3;
+
+        let (bytes, img) =
+            encode_composite(noise.clone(), &options(0, CompositeFormat::Jpeg, budget)).unwrap();
+        assert!(bytes.len() <= budget);
+        assert!(img.width() <= 480);
+        assert!(image::load_from_memory_with_format(&bytes, ImageFormat::Jpeg).is_ok());
+
+        let res = encode_composite(noise, &options(0, CompositeFormat::Png, 1_000));
+        assert_eq!(res, Err("The composite doesn't fit in 1000 bytes".into()));
+    }
+
+    /// Time a ten-card composite, run with `cargo test --release bench_composite -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_composite() {
+        const RUNS: u32 = 10;
+
+        let cards = spread(10);
+        let cache = TempDir::new().unwrap();
+
+        let time = |name: &str, opts: &CompositeOptions| {
+            let start = Instant::now();
+            let mut size = 0;
+
+            for _ in 0..RUNS {
+                size = create_composite_spread(&cards, opts).unwrap().bytes.len();
+            }
+
+            println!("{:<32} {:>8.1?} per spread, {:>9} bytes", name, start.elapsed() / RUNS, size);
+        };
+
+        time("full size PNG, no cache", &options(0, CompositeFormat::Png, 0));
+        time("240px JPEG, no cache", &options(240, CompositeFormat::Jpeg, 1_000_000));
+
+        let mut cached = options(240, CompositeFormat::Jpeg, 1_000_000);
+        cached.cache_dir = Some(cache.path().to_path_buf());
+        let _ = create_composite_spread(&cards, &cached).unwrap();
+        time("240px JPEG, cached", &cached);
+
+        cached.format = CompositeFormat::Webp;
+        time("240px WebP, cached", &cached);
+    }
 }
@@ -0,0 +1,227 @@
+//! Elemental dignities between neighbouring cards in a spread
+//!
+//! Each card is read through the cards drawn either side of it. Cards of the same element
+//! strengthen each other greatly, and friendly elements (Fire with Air, Water with Earth)
+//! strengthen each other moderately. Contrary elements (Fire with Water, Air with Earth)
+//! weaken each other, and the remaining pairs are neutral.
+use std::fmt;
+
+use crate::tarot_cards::TarotCard;
+
+/// The four classical elements
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Element {
+    Fire,
+    Water,
+    Air,
+    Earth,
+}
+
+impl Element {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Fire" => Some(Element::Fire),
+            "Water" => Some(Element::Water),
+            "Air" => Some(Element::Air),
+            "Earth" => Some(Element::Earth),
+            _ => None,
+        }
+    }
+
+    /// The element of a zodiac sign's triplicity
+    fn of_sign(sign: &str) -> Option<Self> {
+        match sign {
+            "Aries" | "Leo" | "Sagittarius" => Some(Element::Fire),
+            "Cancer" | "Scorpio" | "Pisces" => Some(Element::Water),
+            "Gemini" | "Libra" | "Aquarius" => Some(Element::Air),
+            "Taurus" | "Virgo" | "Capricorn" => Some(Element::Earth),
+            _ => None,
+        }
+    }
+
+    /// How strongly a neighbour of element `other` affects a card of this element
+    fn influence(self, other: Element) -> i32 {
+        use Element::*;
+
+        match (self, other) {
+            (a, b) if a == b => 2,
+            (Fire, Air) | (Air, Fire) | (Water, Earth) | (Earth, Water) => 1,
+            (Fire, Water) | (Water, Fire) | (Air, Earth) | (Earth, Air) => -2,
+            _ => 0,
+        }
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Element::Fire => "Fire",
+            Element::Water => "Water",
+            Element::Air => "Air",
+            Element::Earth => "Earth",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// The element a card is read as
+///
+/// Cards with an element in the deck data use their first one; zodiacal trumps take
+/// the element of their sign. Planetary trumps have no element and are neutral.
+pub fn card_element(card: &TarotCard) -> Option<Element> {
+    card.element_1
+        .as_deref()
+        .and_then(Element::from_name)
+        .or_else(|| card.sign_1.as_deref().and_then(Element::of_sign))
+}
+
+/// How a card's neighbours affect it
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Dignity {
+    Strengthened,
+    Neutral,
+    Weakened,
+}
+
+/// The dignity of one card in a spread
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CardDignity {
+    pub element: Option<Element>,
+    pub dignity: Dignity,
+    /// Whether contrary neighbours outweigh any support, so the card reads ill-dignified
+    pub ill_dignified: bool,
+    /// Elements of the neighbouring cards that have one
+    pub neighbours: Vec<Element>,
+}
+
+impl CardDignity {
+    /// Short form stored with the reading
+    pub fn as_str(&self) -> &'static str {
+        match (self.ill_dignified, self.dignity) {
+            (true, _) => "ill-dignified",
+            (false, Dignity::Strengthened) => "strengthened",
+            (false, Dignity::Neutral) => "neutral",
+            (false, Dignity::Weakened) => "weakened",
+        }
+    }
+
+    /// A sentence describing the card's dignity, for the reading's info text
+    pub fn describe(&self) -> String {
+        let Some(element) = self.element else {
+            return "*Dignity:* no element, so neighbours neither help nor hinder it".to_string();
+        };
+
+        if self.neighbours.is_empty() {
+            return format!("*Dignity:* {} with no elemental neighbours", element);
+        }
+
+        let neighbours: Vec<String> = self.neighbours.iter().map(Element::to_string).collect();
+        let effect = match self.dignity {
+            Dignity::Strengthened => "strengthened",
+            Dignity::Neutral => "neutral",
+            Dignity::Weakened => "weakened",
+        };
+
+        let mut text =
+            format!("*Dignity:* {} beside {}: {}", element, neighbours.join(" and "), effect);
+        if self.ill_dignified {
+            text.push_str(" (**ill-dignified**)");
+        }
+        text
+    }
+}
+
+/// Work out the dignity of each card in a spread from its neighbours in drawing order
+pub fn spread_dignities(cards: &[&TarotCard]) -> Vec<CardDignity> {
+    let elements: Vec<Option<Element>> = cards.iter().map(|card| card_element(card)).collect();
+
+    (0..elements.len())
+        .map(|i| {
+            let neighbours: Vec<Element> = [i.checked_sub(1), Some(i + 1)]
+                .iter()
+                .flatten()
+                .filter_map(|&j| elements.get(j).copied().flatten())
+                .collect();
+
+            let Some(element) = elements[i] else {
+                return CardDignity {
+                    element: None,
+                    dignity: Dignity::Neutral,
+                    ill_dignified: false,
+                    neighbours,
+                };
+            };
+
+            let score: i32 = neighbours.iter().map(|n| element.influence(*n)).sum();
+            let dignity = match score {
+                s if s > 0 => Dignity::Strengthened,
+                0 => Dignity::Neutral,
+                _ => Dignity::Weakened,
+            };
+
+            CardDignity {
+                element: Some(element),
+                dignity,
+                ill_dignified: score <= -2,
+                neighbours,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tarot_cards::find_card;
+
+    fn cards(names: &[&str]) -> Vec<&'static TarotCard> {
+        names.iter().map(|name| find_card(name).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_card_element() {
+        assert_eq!(card_element(find_card("ace of wands").unwrap()), Some(Element::Fire));
+        assert_eq!(card_element(find_card("prince of cups").unwrap()), Some(Element::Water));
+        assert_eq!(card_element(find_card("the emperor").unwrap()), Some(Element::Fire));
+        assert_eq!(card_element(find_card("the fool").unwrap()), Some(Element::Air));
+        assert_eq!(card_element(find_card("the sun").unwrap()), None);
+    }
+
+    #[test]
+    fn test_spread_dignities() {
+        // Fire between Water and Water: weakened twice over
+        let res = spread_dignities(&cards(&["ace of cups", "ace of wands", "two of cups"]));
+        assert_eq!(res[1].dignity, Dignity::Weakened);
+        assert!(res[1].ill_dignified);
+        assert_eq!(res[1].neighbours, vec![Element::Water, Element::Water]);
+        assert_eq!(res[0].as_str(), "ill-dignified");
+
+        // Fire between Air and Water: helped by one, hindered by the other
+        let res = spread_dignities(&cards(&["ace of swords", "ace of wands", "ace of cups"]));
+        assert_eq!(res[1].dignity, Dignity::Weakened);
+        assert!(!res[1].ill_dignified);
+        assert_eq!(res[0].as_str(), "strengthened");
+
+        // Fire beside Earth is neutral, as is a card without an element
+        let res = spread_dignities(&cards(&["ace of wands", "ace of disks", "the sun"]));
+        assert_eq!(res[0].dignity, Dignity::Neutral);
+        assert_eq!(res[2].as_str(), "neutral");
+        assert_eq!(res[2].element, None);
+    }
+
+    #[test]
+    fn test_describe() {
+        let res = spread_dignities(&cards(&["ace of cups", "ace of wands", "two of cups"]));
+        assert_eq!(
+            res[1].describe(),
+            "*Dignity:* Fire beside Water and Water: weakened (**ill-dignified**)"
+        );
+
+        let res = spread_dignities(&cards(&["the sun"]));
+        assert_eq!(
+            res[0].describe(),
+            "*Dignity:* no element, so neighbours neither help nor hinder it"
+        );
+    }
+}
@@ -0,0 +1,585 @@
+//! Export and import of tarot reading history
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::tarot_api::{CardData, CardInReading, ReadingCreate, ReadingHistoryItem};
+use crate::tarot_cards;
+use crate::tarot_dignities;
+use crate::tarot_numerology;
+use crate::tarot_sky::{self, Sky};
+
+/// Columns written to and expected from CSV exports, one row per card
+///
+/// `reading` numbers the readings in the file, so that the rows of each can be told apart
+/// even when two were drawn in the same second.
+const CSV_HEADER: &str =
+    "reading,reading_date,spread_type,room_id,question,notes,position,label,card_name,reversed";
+
+/// File formats for exported readings
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    Markdown,
+}
+
+impl ExportFormat {
+    /// Guess the format from a file's extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            "markdown" | "md" => Ok(ExportFormat::Markdown),
+            _ => Err(format!("Unknown format: '{}' (expected json, csv or markdown)", s)),
+        }
+    }
+}
+
+/// A card within an exported reading
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ExportedCard {
+    pub position: i32,
+    pub card_name: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub reversed: bool,
+}
+
+/// A reading as written to export files, independent of any database IDs
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ExportedReading {
+    pub reading_date: String,
+    pub spread_type: String,
+    #[serde(default)]
+    pub room_id: Option<String>,
+    #[serde(default)]
+    pub question: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    pub cards: Vec<ExportedCard>,
+}
+
+impl From<&CardInReading> for ExportedCard {
+    fn from(card: &CardInReading) -> Self {
+        ExportedCard {
+            position: card.position,
+            card_name: card.card_name.clone(),
+            label: card.label.clone(),
+            reversed: card.reversed,
+        }
+    }
+}
+
+impl From<&ReadingHistoryItem> for ExportedReading {
+    fn from(reading: &ReadingHistoryItem) -> Self {
+        ExportedReading {
+            reading_date: reading.reading_date.clone(),
+            spread_type: reading.spread_type.clone(),
+            room_id: reading.room_id.clone(),
+            question: reading.question.clone(),
+            notes: reading.notes.clone(),
+            cards: reading.cards.iter().map(ExportedCard::from).collect(),
+        }
+    }
+}
+
+impl ExportedReading {
+    /// Key identifying a reading, used to skip duplicates on import
+    fn dedup_key(&self) -> (String, String, Vec<String>) {
+        // Compare dates to the second, since databases differ in sub-second precision
+        let date: String = self.reading_date.replace(' ', "T").chars().take(19).collect();
+        let cards = self.cards.iter().map(|c| c.card_name.clone()).collect();
+
+        (date, self.spread_type.clone(), cards)
+    }
+
+    /// Build the request that saves this reading for `matrix_id`
+    ///
+    /// Dignities, numerology and the sky aren't exported, so they are worked out again from
+    /// the cards and the reading date.
+    pub fn into_create(self, matrix_id: &str) -> ReadingCreate {
+        let known: Option<Vec<_>> =
+            self.cards.iter().map(|c| tarot_cards::find_card(&c.card_name)).collect();
+        let dignities = known
+            .as_ref()
+            .map(|cards| tarot_dignities::spread_dignities(cards))
+            .unwrap_or_default();
+        let numerology = known
+            .filter(|cards| cards.len() > 1)
+            .and_then(|cards| tarot_numerology::spread_numerology(&cards));
+        let sky = tarot_sky::parse_time(&self.reading_date).map(Sky::at);
+
+        let cards = self
+            .cards
+            .into_iter()
+            .enumerate()
+            .map(|(i, card)| {
+                CardData {
+                    position: card.position,
+                    card_name: card.card_name,
+                    card_label: card.label,
+                    is_reversed: card.reversed,
+                    dignity: dignities.get(i).map(|d| d.as_str().to_string()),
+                }
+            })
+            .collect();
+
+        ReadingCreate {
+            matrix_id: matrix_id.to_string(),
+            room_id: self.room_id,
+            spread_type: self.spread_type,
+            cards,
+            notes: self.notes,
+            question: self.question,
+            reading_date: Some(self.reading_date),
+            quintessence: numerology.as_ref().map(|n| n.quintessence.card.clone()),
+            numerology: numerology.as_ref().map(|n| n.to_text()),
+            moon_phase: sky.map(|sky| sky.moon.to_string()),
+            sun_sign: sky.map(|sky| sky.sun_sign_name().to_string()),
+            is_private: false,
+        }
+    }
+}
+
+/// Drop imported readings that already exist, or that appear twice in the import
+pub fn new_readings(
+    imported: Vec<ExportedReading>,
+    existing: &[ExportedReading],
+) -> Vec<ExportedReading> {
+    let mut seen: HashSet<_> = existing.iter().map(ExportedReading::dedup_key).collect();
+
+    imported
+        .into_iter()
+        .filter(|reading| seen.insert(reading.dedup_key()))
+        .collect()
+}
+
+/// Serialize readings in the given format
+pub fn export_readings(
+    readings: &[ExportedReading],
+    format: ExportFormat,
+) -> Result<String, String> {
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_string_pretty(readings)
+                .map_err(|e| format!("Failed to encode JSON: {}", e))
+        },
+        ExportFormat::Csv => Ok(export_csv(readings)),
+        ExportFormat::Markdown => Ok(export_markdown(readings)),
+    }
+}
+
+/// Parse readings previously written by `export_readings`
+pub fn parse_readings(content: &str, format: ExportFormat) -> Result<Vec<ExportedReading>, String> {
+    let readings: Vec<ExportedReading> = match format {
+        ExportFormat::Json => {
+            serde_json::from_str(content).map_err(|e| format!("Failed to parse JSON: {}", e))?
+        },
+        ExportFormat::Csv => parse_csv(content)?,
+        ExportFormat::Markdown => parse_markdown(content)?,
+    };
+
+    // Everything after import works from the date, so junk is turned away here
+    for (i, reading) in readings.iter().enumerate() {
+        if tarot_sky::parse_time(&reading.reading_date).is_none() {
+            return Err(format!("Reading {}: invalid date '{}'", i + 1, reading.reading_date));
+        }
+    }
+
+    Ok(readings)
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn export_csv(readings: &[ExportedReading]) -> String {
+    let mut out = String::from(CSV_HEADER);
+    out.push('\n');
+
+    for (n, reading) in readings.iter().enumerate() {
+        for card in &reading.cards {
+            let fields = [
+                (n + 1).to_string(),
+                csv_field(&reading.reading_date),
+                csv_field(&reading.spread_type),
+                csv_field(reading.room_id.as_deref().unwrap_or_default()),
+                csv_field(reading.question.as_deref().unwrap_or_default()),
+                csv_field(reading.notes.as_deref().unwrap_or_default()),
+                card.position.to_string(),
+                csv_field(card.label.as_deref().unwrap_or_default()),
+                csv_field(&card.card_name),
+                card.reversed.to_string(),
+            ];
+            out.push_str(&fields.join(","));
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+/// Split CSV content into records, allowing quoted fields to span lines
+fn csv_records(content: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            },
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => record.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes => {},
+            '\n' if !in_quotes => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            },
+            _ => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    records
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+fn parse_csv(content: &str) -> Result<Vec<ExportedReading>, String> {
+    let mut records = csv_records(content).into_iter();
+
+    match records.next() {
+        Some(header) if header.join(",") == CSV_HEADER => {},
+        _ => return Err(format!("Expected CSV header: {}", CSV_HEADER)),
+    }
+
+    let mut readings: Vec<ExportedReading> = Vec::new();
+    let mut last_number = None;
+
+    for (i, record) in records.enumerate() {
+        if record.len() == 1 && record[0].is_empty() {
+            continue;
+        }
+
+        let [number, date, spread, room, question, notes, position, label, card_name, reversed] =
+            <[String; 10]>::try_from(record)
+                .map_err(|r| format!("Line {}: expected 10 fields, found {}", i + 2, r.len()))?;
+
+        let card = ExportedCard {
+            position: position
+                .parse()
+                .map_err(|_| format!("Line {}: invalid position '{}'", i + 2, position))?,
+            card_name,
+            label: non_empty(&label),
+            reversed: reversed == "true",
+        };
+
+        // Rows belong to the reading numbered in them
+        let same_reading = last_number.as_ref() == Some(&number);
+        last_number = Some(number);
+
+        match readings.last_mut() {
+            Some(last) if same_reading => {
+                last.cards.push(card);
+            },
+            _ => {
+                readings.push(ExportedReading {
+                    reading_date: date,
+                    spread_type: spread,
+                    room_id: non_empty(&room),
+                    question: non_empty(&question),
+                    notes: non_empty(&notes),
+                    cards: vec![card],
+                });
+            },
+        }
+    }
+
+    Ok(readings)
+}
+
+fn md_escape(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', "<br>")
+}
+
+fn md_unescape(value: &str) -> String {
+    value.replace("<br>", "\n").replace("\\|", "|")
+}
+
+fn export_markdown(readings: &[ExportedReading]) -> String {
+    let mut out = String::from("# Tarot Reading History\n\n");
+
+    for reading in readings {
+        out.push_str(&format!(
+            "## {} | {}\n\n",
+            reading.reading_date,
+            md_escape(&reading.spread_type)
+        ));
+
+        if let Some(room_id) = &reading.room_id {
+            out.push_str(&format!("- Room: {}\n", room_id));
+        }
+        if let Some(question) = &reading.question {
+            out.push_str(&format!("- Question: {}\n", md_escape(question)));
+        }
+        if let Some(notes) = &reading.notes {
+            out.push_str(&format!("- Notes: {}\n", md_escape(notes)));
+        }
+
+        out.push_str("\n| # | Position | Card | Reversed |\n");
+        out.push_str("|---|----------|------|----------|\n");
+
+        for card in &reading.cards {
+            out.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                card.position + 1,
+                md_escape(card.label.as_deref().unwrap_or_default()),
+                md_escape(&card.card_name),
+                if card.reversed { "yes" } else { "no" },
+            ));
+        }
+
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Split a Markdown table row into its cells, honouring escaped pipes
+fn md_cells(row: &str) -> Vec<String> {
+    let row = row.trim().trim_start_matches('|');
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = row.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                cell.push_str("\\|");
+                chars.next();
+            },
+            '|' => cells.push(md_unescape(std::mem::take(&mut cell).trim())),
+            _ => cell.push(c),
+        }
+    }
+
+    cells
+}
+
+fn parse_markdown(content: &str) -> Result<Vec<ExportedReading>, String> {
+    let mut readings: Vec<ExportedReading> = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+
+        if let Some(heading) = line.strip_prefix("## ") {
+            let (date, spread) = heading
+                .split_once(" | ")
+                .ok_or_else(|| format!("Line {}: expected '## <date> | <spread>'", i + 1))?;
+
+            readings.push(ExportedReading {
+                reading_date: date.trim().to_string(),
+                spread_type: md_unescape(spread.trim()),
+                room_id: None,
+                question: None,
+                notes: None,
+                cards: vec![],
+            });
+            continue;
+        }
+
+        let Some(reading) = readings.last_mut() else {
+            continue;
+        };
+
+        if let Some(room_id) = line.strip_prefix("- Room: ") {
+            reading.room_id = Some(room_id.to_string());
+        } else if let Some(question) = line.strip_prefix("- Question: ") {
+            reading.question = Some(md_unescape(question));
+        } else if let Some(notes) = line.strip_prefix("- Notes: ") {
+            reading.notes = Some(md_unescape(notes));
+        } else if line.starts_with('|') && !line.starts_with("| #") && !line.starts_with("|---") {
+            let cells = md_cells(line);
+            if cells.len() != 4 {
+                return Err(format!(
+                    "Line {}: expected 4 table columns, found {}",
+                    i + 1,
+                    cells.len()
+                ));
+            }
+
+            let position: i32 = cells[0]
+                .parse()
+                .map_err(|_| format!("Line {}: invalid position '{}'", i + 1, cells[0]))?;
+
+            reading.cards.push(ExportedCard {
+                position: position - 1,
+                label: non_empty(&cells[1]),
+                card_name: cells[2].clone(),
+                reversed: cells[3] == "yes",
+            });
+        }
+    }
+
+    Ok(readings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_readings() -> Vec<ExportedReading> {
+        vec![
+            ExportedReading {
+                reading_date: "2026-01-05T09:30:00.123456".into(),
+                spread_type: "celtic".into(),
+                room_id: Some("!room:example.com".into()),
+                question: Some("Should I take the job, or \"wait\"?".into()),
+                notes: Some("First line\nSecond | line".into()),
+                cards: vec![
+                    ExportedCard {
+                        position: 0,
+                        card_name: "The Star".into(),
+                        label: Some("Present".into()),
+                        reversed: false,
+                    },
+                    ExportedCard {
+                        position: 1,
+                        card_name: "Six of Swords".into(),
+                        label: Some("Challenge".into()),
+                        reversed: true,
+                    },
+                ],
+            },
+            ExportedReading {
+                reading_date: "2026-01-06T18:00:00".into(),
+                spread_type: "1".into(),
+                room_id: None,
+                question: None,
+                notes: None,
+                cards: vec![ExportedCard {
+                    position: 0,
+                    card_name: "The Fool".into(),
+                    label: None,
+                    reversed: false,
+                }],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(ExportFormat::from_path(Path::new("a/b.json")), Some(ExportFormat::Json));
+        assert_eq!(ExportFormat::from_path(Path::new("b.CSV")), Some(ExportFormat::Csv));
+        assert_eq!(ExportFormat::from_path(Path::new("b.md")), Some(ExportFormat::Markdown));
+        assert_eq!(ExportFormat::from_path(Path::new("b.txt")), None);
+        assert_eq!(ExportFormat::from_path(Path::new("b")), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let readings = sample_readings();
+
+        for format in [
+            ExportFormat::Json,
+            ExportFormat::Csv,
+            ExportFormat::Markdown,
+        ] {
+            let exported = export_readings(&readings, format).unwrap();
+            let parsed = parse_readings(&exported, format).unwrap();
+            assert_eq!(parsed, readings, "{:?} did not round-trip", format);
+        }
+    }
+
+    #[test]
+    fn test_parse_csv_bad_header() {
+        let res = parse_readings("date,card\n", ExportFormat::Csv);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_parse_bad_date() {
+        let mut readings = sample_readings();
+        readings[1].reading_date = "2026-1".into();
+
+        for format in [
+            ExportFormat::Json,
+            ExportFormat::Csv,
+            ExportFormat::Markdown,
+        ] {
+            let exported = export_readings(&readings, format).unwrap();
+            let err = parse_readings(&exported, format).unwrap_err();
+            assert_eq!(err, "Reading 2: invalid date '2026-1'", "{:?}", format);
+        }
+
+        // Cutting the date at ten bytes mustn't land inside a character
+        readings[1].reading_date = "2026-01-0é".into();
+        let exported = export_readings(&readings, ExportFormat::Json).unwrap();
+        assert!(parse_readings(&exported, ExportFormat::Json).is_err());
+    }
+
+    #[test]
+    fn test_readings_in_the_same_second() {
+        let mut readings = sample_readings();
+        let mut again = readings[1].clone();
+        again.cards[0].card_name = "The Magus".into();
+        readings.push(again);
+
+        for format in [
+            ExportFormat::Json,
+            ExportFormat::Csv,
+            ExportFormat::Markdown,
+        ] {
+            let exported = export_readings(&readings, format).unwrap();
+            assert_eq!(parse_readings(&exported, format).unwrap(), readings, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_new_readings() {
+        let existing = sample_readings();
+        let mut imported = sample_readings();
+
+        // Same reading with a different sub-second precision is a duplicate
+        imported[0].reading_date = "2026-01-05 09:30:00".into();
+
+        let mut fresh = imported[1].clone();
+        fresh.reading_date = "2026-02-01T08:00:00".into();
+        imported.push(fresh.clone());
+        imported.push(fresh.clone());
+
+        assert_eq!(new_readings(imported, &existing), vec![fresh]);
+    }
+}
@@ -0,0 +1,550 @@
+//! Learning the deck with spaced-repetition flashcards
+//!
+//! Each card is asked about whatever it has of a name, element, planet, sign, sephira and
+//! meaning. Every card and question pair is a flashcard, scheduled with SuperMemo's SM-2
+//! algorithm: good answers push its next review further out, and a miss brings it back the
+//! next day.
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use chrono::{Days, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::tarot_cards::{self, normalize_card_name, TarotCard};
+use crate::tarot_spreads;
+
+/// The ease a flashcard starts with, and the lowest it can fall to
+const START_EASE: f64 = 2.5;
+const MIN_EASE: f64 = 1.3;
+
+/// Flashcards reviewed at least this many days apart count as mastered
+const MASTERED_DAYS: u32 = 21;
+
+/// Words too common to say anything about a card's meaning
+const STOPWORDS: [&str; 16] = [
+    "that", "with", "from", "this", "have", "into", "which", "their", "they", "them", "very",
+    "often", "being", "much", "when", "will",
+];
+
+/// The groups that mastery is tallied by
+pub const SUITS: [&str; 5] = ["Major Arcana", "Wands", "Cups", "Swords", "Disks"];
+
+/// What a flashcard asks about its card
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum QuizField {
+    Title,
+    Element,
+    Planet,
+    Sign,
+    Sephira,
+    Meaning,
+}
+
+impl QuizField {
+    pub const ALL: [QuizField; 6] = [
+        QuizField::Title,
+        QuizField::Element,
+        QuizField::Planet,
+        QuizField::Sign,
+        QuizField::Sephira,
+        QuizField::Meaning,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            QuizField::Title => "title",
+            QuizField::Element => "element",
+            QuizField::Planet => "planet",
+            QuizField::Sign => "sign",
+            QuizField::Sephira => "sephira",
+            QuizField::Meaning => "meaning",
+        }
+    }
+
+    pub fn question(&self) -> &'static str {
+        match self {
+            QuizField::Title => "Which card is this?",
+            QuizField::Element => "Which element does this card belong to?",
+            QuizField::Planet => "Which planets are attributed to this card?",
+            QuizField::Sign => "Which zodiac signs does this card cover?",
+            QuizField::Sephira => "Which sephira does this card sit on?",
+            QuizField::Meaning => "What does this card mean?",
+        }
+    }
+
+    /// What a card has to say to this question, or nothing if there's nothing to ask
+    pub fn answers(&self, card: &TarotCard) -> Vec<String> {
+        let values = match self {
+            QuizField::Title => {
+                return std::iter::once(&card.card).chain(&card.title).cloned().collect();
+            },
+            QuizField::Meaning => return upright_meaning(card).into_iter().collect(),
+            QuizField::Element => card.attribute_values("element"),
+            QuizField::Planet => card.attribute_values("planet"),
+            QuizField::Sign => card.attribute_values("sign"),
+            QuizField::Sephira => card.attribute_values("sephira"),
+        };
+
+        values.into_iter().map(str::to_string).collect()
+    }
+}
+
+/// How well a flashcard was answered, as an SM-2 quality from 0 to 5
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Grade {
+    /// Nothing was given, so the answer is shown.
+    Blank = 0,
+    Wrong = 1,
+    /// Some of the answer, or one idea in a meaning.
+    Partial = 3,
+    Correct = 5,
+}
+
+impl Grade {
+    pub fn quality(self) -> u8 {
+        self as u8
+    }
+
+    pub fn passed(self) -> bool {
+        self.quality() >= 3
+    }
+}
+
+/// The card's meaning when well dignified, without what it means ill-dignified
+fn upright_meaning(card: &TarotCard) -> Option<String> {
+    let info = card.info.as_ref()?;
+    let upright = info.split("Ill-dignified").next().unwrap_or(info).trim();
+
+    Some(upright.to_string()).filter(|s| !s.is_empty())
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Whether two words are the same but for an ending, like "hope" and "hopeful"
+fn same_stem(a: &str, b: &str) -> bool {
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+
+    short.len() >= 4 && long.starts_with(short)
+}
+
+/// Grade an answer to a question about a card
+pub fn grade(field: QuizField, card: &TarotCard, answer: &str) -> Grade {
+    if answer.trim().is_empty() {
+        return Grade::Blank;
+    }
+
+    match field {
+        QuizField::Title => {
+            let given = normalize_card_name(answer);
+            let matches = |name: &String| {
+                let name = normalize_card_name(name);
+                name == given || name.replace("of", "") == given.replace("of", "")
+            };
+
+            if field.answers(card).iter().any(matches) {
+                Grade::Correct
+            } else {
+                Grade::Wrong
+            }
+        },
+        QuizField::Meaning => {
+            let meaning = field.answers(card).join(" ");
+            let meaning = words(&meaning);
+            let mut given = words(answer);
+            given.retain(|w| w.len() >= 4 && !STOPWORDS.contains(&w.as_str()));
+            given.sort();
+            given.dedup();
+
+            match given.iter().filter(|w| meaning.iter().any(|m| same_stem(w, m))).count() {
+                0 => Grade::Wrong,
+                1 => Grade::Partial,
+                _ => Grade::Correct,
+            }
+        },
+        _ => {
+            let expected: Vec<String> =
+                field.answers(card).iter().map(|a| normalize_card_name(a)).collect();
+            let given: Vec<String> = answer
+                .split([',', '/', '&', ';'])
+                .flat_map(|part| part.split(" and "))
+                .map(normalize_card_name)
+                .filter(|part| !part.is_empty())
+                .collect();
+
+            let right = expected.iter().filter(|e| given.contains(e)).count();
+            let wrong = given.iter().filter(|g| !expected.contains(g)).count();
+
+            if right == expected.len() && wrong == 0 {
+                Grade::Correct
+            } else if right > 0 {
+                Grade::Partial
+            } else {
+                Grade::Wrong
+            }
+        },
+    }
+}
+
+/// When a flashcard is next due, and how its reviews have gone so far
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Review {
+    pub ease: f64,
+    /// Days until the next review
+    pub interval: u32,
+    /// Reviews passed in a row
+    pub repetitions: u32,
+    pub due: NaiveDate,
+}
+
+impl Review {
+    pub fn new(today: NaiveDate) -> Self {
+        Review {
+            ease: START_EASE,
+            interval: 0,
+            repetitions: 0,
+            due: today,
+        }
+    }
+
+    /// Schedule the next review after an answer of the given grade, as SM-2 does
+    pub fn grade(&mut self, grade: Grade, today: NaiveDate) {
+        let q = f64::from(grade.quality());
+
+        if grade.passed() {
+            self.interval = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (f64::from(self.interval) * self.ease).round() as u32,
+            };
+            self.repetitions += 1;
+        } else {
+            self.interval = 1;
+            self.repetitions = 0;
+        }
+
+        self.ease = (self.ease + 0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02)).max(MIN_EASE);
+        self.due = today + Days::new(self.interval.into());
+    }
+
+    pub fn is_mastered(&self) -> bool {
+        self.interval >= MASTERED_DAYS
+    }
+}
+
+/// A question about a card
+#[derive(Clone, Copy, Debug)]
+pub struct Flashcard {
+    pub card: &'static TarotCard,
+    pub field: QuizField,
+}
+
+impl PartialEq for Flashcard {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Flashcard {}
+
+impl Flashcard {
+    /// Identifies the flashcard in the saved progress
+    pub fn key(&self) -> String {
+        format!("{}/{}", self.card.search_key(), self.field.name())
+    }
+}
+
+/// Every flashcard that can be made from a card
+fn card_flashcards(card: &'static TarotCard) -> impl Iterator<Item = Flashcard> {
+    QuizField::ALL
+        .iter()
+        .copied()
+        .filter(move |field| !field.answers(card).is_empty())
+        .map(move |field| Flashcard { card, field })
+}
+
+/// Which of [SUITS] a card is tallied under
+pub fn card_suit(card: &TarotCard) -> &str {
+    if card.is_major() {
+        SUITS[0]
+    } else {
+        card.suit_1.as_deref().or(card.suit_2.as_deref()).unwrap_or(SUITS[0])
+    }
+}
+
+/// How far along the learner is with one suit
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SuitStats {
+    pub suit: &'static str,
+    pub total: usize,
+    pub seen: usize,
+    pub mastered: usize,
+    pub due: usize,
+}
+
+impl SuitStats {
+    /// The share of the suit's flashcards that are mastered, from 0 to 100
+    pub fn mastery(&self) -> usize {
+        (self.mastered * 100).checked_div(self.total).unwrap_or(0)
+    }
+}
+
+/// Every review so far, as saved under the profile's data directory
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct LearnProgress {
+    pub reviews: BTreeMap<String, Review>,
+}
+
+impl LearnProgress {
+    /// Pick the next flashcard: the most overdue one, or else a new card's from the deck
+    pub fn next_flashcard(
+        &self,
+        today: NaiveDate,
+        deck: &[&'static TarotCard],
+    ) -> Option<Flashcard> {
+        let due = deck
+            .iter()
+            .flat_map(|card| card_flashcards(card))
+            .filter_map(|fc| Some((self.reviews.get(&fc.key())?.due, fc)))
+            .filter(|(due, _)| *due <= today)
+            .min_by_key(|(due, fc)| (*due, fc.key()));
+
+        if let Some((_, fc)) = due {
+            return Some(fc);
+        }
+
+        deck.iter()
+            .flat_map(|card| card_flashcards(card))
+            .find(|fc| !self.reviews.contains_key(&fc.key()))
+    }
+
+    /// Grade an answer to a flashcard and schedule its next review
+    pub fn answer(&mut self, fc: &Flashcard, answer: &str, today: NaiveDate) -> (Grade, &Review) {
+        let grade = grade(fc.field, fc.card, answer);
+        let review = self.reviews.entry(fc.key()).or_insert_with(|| Review::new(today));
+        review.grade(grade, today);
+
+        (grade, review)
+    }
+
+    /// Tally the flashcards of each suit
+    pub fn stats(&self, today: NaiveDate, cards: &[&'static TarotCard]) -> Vec<SuitStats> {
+        let mut stats: Vec<SuitStats> =
+            SUITS.iter().map(|suit| SuitStats { suit, ..Default::default() }).collect();
+
+        for card in cards {
+            let Some(entry) = stats.iter_mut().find(|s| s.suit == card_suit(card)) else {
+                continue;
+            };
+
+            for fc in card_flashcards(card) {
+                entry.total += 1;
+
+                if let Some(review) = self.reviews.get(&fc.key()) {
+                    entry.seen += 1;
+                    entry.mastered += usize::from(review.is_mastered());
+                    entry.due += usize::from(review.due <= today);
+                }
+            }
+        }
+
+        stats
+    }
+}
+
+/// The `:tarot learn` window's flashcards and the progress they're scheduled from
+pub struct LearnSession {
+    path: PathBuf,
+    pub progress: LearnProgress,
+    /// The flashcard being asked, if any are left
+    pub current: Option<Flashcard>,
+    /// How the last answer went, shown above the next question
+    pub feedback: Option<(Grade, String)>,
+}
+
+impl LearnSession {
+    /// Load the saved progress, starting afresh if there is none yet
+    pub fn load(path: PathBuf) -> Result<Self, String> {
+        let progress = match std::fs::read_to_string(&path) {
+            Ok(json) => {
+                serde_json::from_str(&json)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => LearnProgress::default(),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+
+        let mut session = LearnSession { path, progress, current: None, feedback: None };
+        session.advance(today());
+
+        Ok(session)
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&self.progress).map_err(|e| e.to_string())?;
+
+        std::fs::write(&self.path, json)
+            .map_err(|e| format!("Failed to save {}: {}", self.path.display(), e))
+    }
+
+    /// Move on to the next flashcard
+    fn advance(&mut self, today: NaiveDate) {
+        self.current = self.progress.next_flashcard(today, &tarot_spreads::shuffle_deck());
+    }
+
+    /// Grade an answer to the current flashcard, save the new schedule and ask the next one
+    pub fn answer(&mut self, answer: &str) -> Result<(), String> {
+        let today = today();
+        let Some(fc) = self.current else {
+            return Err("Every flashcard has been reviewed for today".into());
+        };
+
+        let (grade, review) = self.progress.answer(&fc, answer, today);
+        let next = match review.interval {
+            1 => "again tomorrow".to_string(),
+            n => format!("again in {} days", n),
+        };
+        let answers = fc.field.answers(fc.card).join(", ");
+        let feedback = match grade {
+            Grade::Correct => format!("Correct! {}: {} ({})", fc.card.card, answers, next),
+            Grade::Partial => format!("Partly. {}: {} ({})", fc.card.card, answers, next),
+            Grade::Wrong | Grade::Blank => format!("{}: {} ({})", fc.card.card, answers, next),
+        };
+
+        self.feedback = Some((grade, feedback));
+        self.save()?;
+        self.advance(today);
+
+        Ok(())
+    }
+
+    pub fn stats(&self) -> Vec<SuitStats> {
+        self.progress.stats(today(), &tarot_cards::get_all_cards())
+    }
+}
+
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use temp_dir::TempDir;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 1, day).unwrap()
+    }
+
+    fn card(name: &str) -> &'static TarotCard {
+        tarot_cards::find_card(name).unwrap()
+    }
+
+    #[test]
+    fn test_grade_answers() {
+        let swords = card("six of swords");
+
+        assert_eq!(grade(QuizField::Title, swords, "Six of Swords"), Grade::Correct);
+        assert_eq!(grade(QuizField::Title, swords, "science"), Grade::Correct);
+        assert_eq!(grade(QuizField::Title, swords, "six swords"), Grade::Correct);
+        assert_eq!(grade(QuizField::Title, swords, "seven of swords"), Grade::Wrong);
+        assert_eq!(grade(QuizField::Title, swords, "  "), Grade::Blank);
+
+        assert_eq!(grade(QuizField::Element, swords, "air"), Grade::Correct);
+        assert_eq!(grade(QuizField::Element, swords, "Fire"), Grade::Wrong);
+        assert_eq!(grade(QuizField::Planet, swords, "Mercury, Sol"), Grade::Correct);
+        assert_eq!(grade(QuizField::Planet, swords, "sol and mercury"), Grade::Correct);
+        assert_eq!(grade(QuizField::Planet, swords, "Mercury"), Grade::Partial);
+        assert_eq!(grade(QuizField::Planet, swords, "Mercury, Venus"), Grade::Partial);
+        assert_eq!(grade(QuizField::Sephira, swords, "Tiphareth"), Grade::Correct);
+
+        // Meanings are matched idea by idea, ignoring endings and anything ill-dignified
+        let star = card("star");
+        assert_eq!(grade(QuizField::Meaning, star, "hopeful, with clarity"), Grade::Correct);
+        assert_eq!(grade(QuizField::Meaning, star, "hope"), Grade::Partial);
+        assert_eq!(grade(QuizField::Meaning, star, "disappointment"), Grade::Wrong);
+    }
+
+    #[test]
+    fn test_sm2_schedule() {
+        let mut review = Review::new(date(1));
+
+        review.grade(Grade::Correct, date(1));
+        assert_eq!((review.interval, review.repetitions, review.due), (1, 1, date(2)));
+        assert!((review.ease - 2.6).abs() < 1e-9);
+
+        review.grade(Grade::Correct, date(2));
+        assert_eq!((review.interval, review.due), (6, date(8)));
+
+        review.grade(Grade::Partial, date(8));
+        assert_eq!(review.interval, 16);
+        assert!((review.ease - 2.56).abs() < 1e-9);
+
+        review.grade(Grade::Correct, date(24));
+        assert!(review.is_mastered());
+
+        // A miss starts the flashcard over, and costs it some ease
+        review.grade(Grade::Wrong, date(30));
+        assert_eq!((review.interval, review.repetitions, review.due), (1, 0, date(31)));
+        assert!((review.ease - 2.12).abs() < 1e-9);
+
+        for _ in 0..10 {
+            review.grade(Grade::Blank, date(31));
+        }
+        assert_eq!(review.ease, MIN_EASE);
+    }
+
+    #[test]
+    fn test_next_flashcard() {
+        let deck = [card("star"), card("six of swords")];
+        let mut progress = LearnProgress::default();
+
+        // New cards are asked in deck order, a question at a time
+        let fc = progress.next_flashcard(date(1), &deck).unwrap();
+        assert_eq!(fc.key(), "star/title");
+
+        progress.answer(&fc, "The Star", date(1));
+        let fc = progress.next_flashcard(date(1), &deck).unwrap();
+        assert_eq!(fc.key(), "star/planet");
+
+        // Once due, reviews come before new flashcards
+        let fc = progress.next_flashcard(date(2), &deck).unwrap();
+        assert_eq!(fc.key(), "star/title");
+
+        let stats = progress.stats(date(2), &deck);
+        let major = &stats[0];
+        assert_eq!((major.suit, major.total, major.seen, major.due), ("Major Arcana", 4, 1, 1));
+        let swords = stats.iter().find(|s| s.suit == "Swords").unwrap();
+        assert_eq!((swords.total, swords.seen, swords.mastery()), (6, 0, 0));
+    }
+
+    #[test]
+    fn test_learn_session() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tarot_learn.json");
+
+        let mut session = LearnSession::load(path.clone()).unwrap();
+        assert!(session.current.is_some());
+
+        let fc = Flashcard { card: card("star"), field: QuizField::Sign };
+        session.current = Some(fc);
+        session.answer("Aquarius").unwrap();
+
+        let (grade, feedback) = session.feedback.clone().unwrap();
+        assert_eq!(grade, Grade::Correct);
+        assert_eq!(feedback, "Correct! The Star: Aquarius (again tomorrow)");
+        assert_ne!(session.current, Some(fc));
+
+        // Progress is kept between sessions
+        let session = LearnSession::load(path.clone()).unwrap();
+        assert_eq!(session.progress.reviews[&fc.key()].repetitions, 1);
+        assert_eq!(session.stats().iter().map(|s| s.seen).sum::<usize>(), 1);
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(LearnSession::load(path).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+//! Numerology of a spread: the quintessence and Golden Dawn counts
+use std::collections::BTreeMap;
+
+use crate::tarot_cards::{self, Court, Rank, TarotCard};
+
+/// Golden Dawn meanings for a number turning up several times in one spread
+fn number_meaning(n: u8) -> &'static str {
+    match n {
+        1 => "strength and new force",
+        2 => "meetings and exchanges",
+        3 => "determination and growth",
+        4 => "rest and stability",
+        5 => "strife and upheaval",
+        6 => "harmony and gain",
+        7 => "setbacks and bargains",
+        8 => "news and movement",
+        9 => "added responsibility",
+        10 => "completion and burdens",
+        _ => "",
+    }
+}
+
+/// Golden Dawn meanings for several court cards of one rank
+fn court_meaning(court: Court) -> &'static str {
+    match court {
+        Court::Princess => "new ideas and plans",
+        Court::Prince => "meetings with people of influence",
+        Court::Queen => "authority and influence",
+        Court::Knight => "swiftness and rushing",
+    }
+}
+
+fn court_name(court: Court) -> &'static str {
+    match court {
+        Court::Princess => "Princesses",
+        Court::Prince => "Princes",
+        Court::Queen => "Queens",
+        Court::Knight => "Knights",
+    }
+}
+
+/// Reduce a sum of card numbers to a trump number by adding its digits
+///
+/// Sums up to XXI already name a trump; larger ones are reduced until they do.
+pub fn reduce(mut sum: u32) -> u8 {
+    while sum > 21 {
+        sum = sum.to_string().chars().filter_map(|c| c.to_digit(10)).sum();
+    }
+
+    sum as u8
+}
+
+/// Numerological summary of a spread
+#[derive(Clone, Debug)]
+pub struct SpreadNumerology {
+    /// Sum of the trump numbers and pip values; courts don't count
+    pub sum: u32,
+    /// The trump the sum reduces to
+    pub quintessence: &'static TarotCard,
+    pub trumps: usize,
+    pub courts: usize,
+    pub pips: usize,
+    /// Pip values drawn more than once, with how often, most frequent first
+    pub dominant_numbers: Vec<(u8, usize)>,
+    /// Court ranks drawn more than once, with how often
+    pub dominant_courts: Vec<(Court, usize)>,
+}
+
+/// Work out the quintessence and counts for a spread
+///
+/// Returns `None` if the deck has no trump for the quintessence.
+pub fn spread_numerology(cards: &[&TarotCard]) -> Option<SpreadNumerology> {
+    let mut sum = 0;
+    let (mut trumps, mut courts, mut pips) = (0, 0, 0);
+    let mut numbers: BTreeMap<u8, usize> = BTreeMap::new();
+    let mut court_counts: BTreeMap<Court, usize> = BTreeMap::new();
+
+    for card in cards {
+        match card.rank() {
+            Some(Rank::Trump(n)) => {
+                trumps += 1;
+                sum += u32::from(n);
+            },
+            Some(Rank::Pip(n)) => {
+                pips += 1;
+                sum += u32::from(n);
+                *numbers.entry(n).or_default() += 1;
+            },
+            Some(Rank::Court(court)) => {
+                courts += 1;
+                *court_counts.entry(court).or_default() += 1;
+            },
+            None => {},
+        }
+    }
+
+    let mut dominant_numbers: Vec<(u8, usize)> =
+        numbers.into_iter().filter(|(_, c)| *c > 1).collect();
+    dominant_numbers.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let mut dominant_courts: Vec<(Court, usize)> =
+        court_counts.into_iter().filter(|(_, c)| *c > 1).collect();
+    dominant_courts.sort_by(|a, b| b.1.cmp(&a.1).then(b.0.cmp(&a.0)));
+
+    Some(SpreadNumerology {
+        sum,
+        quintessence: tarot_cards::find_trump(reduce(sum))?,
+        trumps,
+        courts,
+        pips,
+        dominant_numbers,
+        dominant_courts,
+    })
+}
+
+impl SpreadNumerology {
+    /// One-line description of the quintessence, as saved with the reading
+    pub fn quintessence_text(&self) -> String {
+        let number = self.quintessence.number().unwrap_or_default();
+
+        format!(
+            "{} ({}), from a sum of {}",
+            self.quintessence.card,
+            tarot_cards::roman_numeral(number),
+            self.sum
+        )
+    }
+
+    /// The summary appended to a posted reading
+    pub fn to_text(&self) -> String {
+        let total = self.trumps + self.courts + self.pips;
+
+        let mut out = String::from("**Numerology**\n");
+        out.push_str(&format!("Quintessence: {}\n", self.quintessence_text()));
+        out.push_str(&format!(
+            "Trumps: {} · Courts: {} · Pips: {}\n",
+            self.trumps, self.courts, self.pips
+        ));
+
+        // Golden Dawn: a majority of trumps or courts colours the whole reading
+        if self.trumps * 2 > total {
+            out.push_str("Mostly trumps: forces beyond the querent's control are at work.\n");
+        } else if self.courts * 2 > total {
+            out.push_str("Mostly courts: other people shape this matter.\n");
+        }
+
+        for (n, count) in &self.dominant_numbers {
+            out.push_str(&format!("{} × {}: {}\n", count, n, number_meaning(*n)));
+        }
+
+        for (court, count) in &self.dominant_courts {
+            out.push_str(&format!("{} {}: {}\n", count, court_name(*court), court_meaning(*court)));
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tarot_cards::find_card;
+
+    fn cards(names: &[&str]) -> Vec<&'static TarotCard> {
+        names.iter().map(|name| find_card(name).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_reduce() {
+        assert_eq!(reduce(0), 0);
+        assert_eq!(reduce(21), 21);
+        assert_eq!(reduce(22), 4);
+        assert_eq!(reduce(99), 18);
+        assert_eq!(reduce(199), 19);
+    }
+
+    #[test]
+    fn test_spread_numerology() {
+        // 17 + 6 + 6 + 2 = 31 -> 4, The Emperor
+        let res = spread_numerology(&cards(&[
+            "the star",
+            "six of swords",
+            "six of cups",
+            "two of wands",
+            "queen of cups",
+        ]))
+        .unwrap();
+
+        assert_eq!(res.sum, 31);
+        assert_eq!(res.quintessence.card, "The Emperor");
+        assert_eq!((res.trumps, res.courts, res.pips), (1, 1, 3));
+        assert_eq!(res.dominant_numbers, vec![(6, 2)]);
+        assert!(res.dominant_courts.is_empty());
+        assert_eq!(res.quintessence_text(), "The Emperor (IV), from a sum of 31");
+
+        let text = res.to_text();
+        assert!(text.contains("Trumps: 1 · Courts: 1 · Pips: 3"));
+        assert!(text.contains("2 × 6: harmony and gain"));
+    }
+
+    #[test]
+    fn test_courts_and_trumps() {
+        let res =
+            spread_numerology(&cards(&["knight of cups", "knight of wands", "queen of disks"]))
+                .unwrap();
+        assert_eq!(res.sum, 0);
+        assert_eq!(res.quintessence.card, "The Fool");
+        assert_eq!(res.dominant_courts, vec![(Court::Knight, 2)]);
+        assert!(res.to_text().contains("Mostly courts"));
+        assert!(res.to_text().contains("2 Knights: swiftness and rushing"));
+
+        let res = spread_numerology(&cards(&["the sun", "the moon", "ace of cups"])).unwrap();
+        // 19 + 18 + 1 = 38 -> 11
+        assert_eq!(res.quintessence.card, "Lust");
+        assert!(res.to_text().contains("Mostly trumps"));
+    }
+}
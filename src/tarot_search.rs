@@ -0,0 +1,393 @@
+//! Full-text search over the deck's meanings and the user's notes
+//!
+//! Every card's name and title, `info`, `deepinfo` and note are split into words and
+//! reduced to rough stems, so that "journeys" finds "journey" and "hoping" finds "hope".
+//! Cards are ranked first by how many of the query's words they contain, then by a TF-IDF
+//! score that counts names and titles over short meanings, and those over long ones.
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use crate::tarot_annotations::Annotations;
+use crate::tarot_cards::TarotCard;
+
+/// Words too common to search by
+const STOPWORDS: [&str; 14] = [
+    "a", "an", "and", "by", "for", "in", "is", "it", "of", "on", "or", "the", "to", "with",
+];
+
+/// Endings taken off words, and what replaces them, tried in order
+const SUFFIXES: [(&str, &str); 18] = [
+    ("ational", "ate"),
+    ("fulness", "ful"),
+    ("iveness", "ive"),
+    ("ousness", "ous"),
+    ("ingly", ""),
+    ("edly", ""),
+    ("ments", ""),
+    ("ment", ""),
+    ("ness", ""),
+    ("sses", "ss"),
+    ("ss", "ss"),
+    ("ings", ""),
+    ("ing", ""),
+    ("ies", "y"),
+    ("ied", "y"),
+    ("ed", ""),
+    ("es", ""),
+    ("s", ""),
+];
+
+/// Characters of context kept either side of the first match in a snippet
+const SNIPPET_CONTEXT: usize = 50;
+
+/// Where on a card a word was found
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SearchField {
+    Title,
+    Info,
+    Note,
+    DeepInfo,
+}
+
+impl SearchField {
+    fn weight(&self) -> f64 {
+        match self {
+            SearchField::Title => 5.0,
+            SearchField::Info | SearchField::Note => 2.0,
+            SearchField::DeepInfo => 1.0,
+        }
+    }
+}
+
+/// Reduce a word to a rough stem, so that different endings of it match each other
+pub fn stem(word: &str) -> String {
+    let word = word.to_lowercase();
+    let word = word.trim_end_matches("'s").trim_matches('\'');
+
+    let mut stem = SUFFIXES
+        .iter()
+        .find(|(suffix, _)| word.ends_with(suffix) && word.len() >= suffix.len() + 3)
+        .map(|(suffix, replacement)| {
+            format!("{}{}", &word[..word.len() - suffix.len()], replacement)
+        })
+        .unwrap_or_else(|| word.to_string());
+
+    // "hope", "hoped" and "hopes" all come down to "hop"
+    if stem.len() >= 4 && stem.ends_with('e') {
+        stem.pop();
+    }
+
+    stem
+}
+
+/// Split text into words, with where each one is
+fn tokens(text: &str) -> impl Iterator<Item = (Range<usize>, &str)> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '\'';
+    let mut start = None;
+
+    text.char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+        .filter_map(move |(i, c)| {
+            match (start, is_word(c)) {
+                (None, true) => {
+                    start = Some(i);
+                    None
+                },
+                (Some(s), false) => {
+                    start = None;
+                    Some((s..i, &text[s..i]))
+                },
+                _ => None,
+            }
+        })
+}
+
+/// The stems of a query's words, without any stopwords
+fn query_terms(query: &str) -> Vec<String> {
+    let mut terms = vec![];
+
+    for (_, word) in tokens(query) {
+        let term = stem(word);
+
+        if !STOPWORDS.contains(&word.to_lowercase().as_str()) && !terms.contains(&term) {
+            terms.push(term);
+        }
+    }
+
+    terms
+}
+
+struct Posting {
+    doc: usize,
+    field: SearchField,
+    count: usize,
+}
+
+struct Document {
+    card: &'static TarotCard,
+    fields: Vec<(SearchField, String)>,
+}
+
+/// A passage from a card, with the words that matched the query marked
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Snippet {
+    pub text: String,
+    /// Byte ranges of the matching words
+    pub highlights: Vec<Range<usize>>,
+}
+
+/// A card found by a search
+#[derive(Clone, Debug)]
+pub struct SearchHit {
+    pub card: &'static TarotCard,
+    pub score: f64,
+    /// How many of the query's words the card contains
+    pub matched: usize,
+    pub snippet: Snippet,
+}
+
+/// An inverted index from word stems to the cards they appear in
+pub struct SearchIndex {
+    docs: Vec<Document>,
+    postings: HashMap<String, Vec<Posting>>,
+}
+
+impl SearchIndex {
+    /// Index the cards' names, titles and meanings, along with the user's notes on them
+    pub fn new(cards: &[&'static TarotCard], notes: &Annotations) -> Self {
+        let mut index = SearchIndex { docs: vec![], postings: HashMap::new() };
+
+        for card in cards {
+            let title = match &card.title {
+                Some(title) => format!("{} ({})", card.card, title),
+                None => card.card.clone(),
+            };
+            let fields = vec![
+                (SearchField::Title, Some(title)),
+                (SearchField::Info, card.info.clone()),
+                (SearchField::Note, notes.get(card).map(str::to_string)),
+                (SearchField::DeepInfo, card.deepinfo.clone()),
+            ];
+            let fields = fields
+                .into_iter()
+                .filter_map(|(field, text)| Some((field, text?)))
+                .collect();
+
+            index.add(Document { card, fields });
+        }
+
+        index
+    }
+
+    fn add(&mut self, doc: Document) {
+        let id = self.docs.len();
+
+        for (field, text) in &doc.fields {
+            let mut counts: HashMap<String, usize> = HashMap::new();
+
+            for (_, word) in tokens(text) {
+                if !STOPWORDS.contains(&word.to_lowercase().as_str()) {
+                    *counts.entry(stem(word)).or_default() += 1;
+                }
+            }
+
+            for (term, count) in counts {
+                self.postings.entry(term).or_default().push(Posting {
+                    doc: id,
+                    field: *field,
+                    count,
+                });
+            }
+        }
+
+        self.docs.push(doc);
+    }
+
+    /// Find the cards matching a query, best first
+    pub fn search(&self, query: &str) -> Vec<SearchHit> {
+        let terms = query_terms(query);
+        let total = self.docs.len() as f64;
+        let mut scores: HashMap<usize, (f64, HashSet<&str>)> = HashMap::new();
+
+        for term in &terms {
+            let Some(postings) = self.postings.get(term) else {
+                continue;
+            };
+
+            let docs: HashSet<usize> = postings.iter().map(|p| p.doc).collect();
+            let idf = (1.0 + total / docs.len() as f64).ln();
+
+            for p in postings {
+                let entry = scores.entry(p.doc).or_default();
+                entry.0 += p.field.weight() * (1.0 + (p.count as f64).ln()) * idf;
+                entry.1.insert(term);
+            }
+        }
+
+        // Cards with the query's words side by side, as it was typed, come first among equals
+        let phrase = query.trim().to_lowercase();
+        let is_phrase = terms.len() > 1;
+
+        let mut hits: Vec<SearchHit> = scores
+            .into_iter()
+            .map(|(id, (mut score, matched))| {
+                let doc = &self.docs[id];
+
+                if is_phrase &&
+                    doc.fields.iter().any(|(_, text)| text.to_lowercase().contains(&phrase))
+                {
+                    score *= 2.0;
+                }
+
+                SearchHit {
+                    card: doc.card,
+                    score,
+                    matched: matched.len(),
+                    snippet: doc.snippet(&terms),
+                }
+            })
+            .collect();
+
+        hits.sort_by(|a, b| {
+            b.matched
+                .cmp(&a.matched)
+                .then(b.score.total_cmp(&a.score))
+                .then_with(|| a.card.card.cmp(&b.card.card))
+        });
+
+        hits
+    }
+}
+
+impl Document {
+    /// A passage around the first match in the card's meanings, or the start of its meaning
+    fn snippet(&self, terms: &[String]) -> Snippet {
+        let is_match = |word: &str| terms.contains(&stem(word));
+        let texts = self.fields.iter().filter(|(field, _)| *field != SearchField::Title);
+
+        let found = texts
+            .clone()
+            .find_map(|(_, text)| {
+                let (range, _) = tokens(text).find(|(_, word)| is_match(word))?;
+                Some((text, range.start))
+            })
+            .or_else(|| texts.clone().next().map(|(_, text)| (text, 0)));
+
+        let Some((text, at)) = found else {
+            return Snippet { text: String::new(), highlights: vec![] };
+        };
+
+        // Widen to whole words either side of the match
+        let mut start = at.saturating_sub(SNIPPET_CONTEXT);
+        while !text.is_char_boundary(start) {
+            start -= 1;
+        }
+        if start > 0 {
+            start = text[start..].find(' ').map(|i| start + i + 1).unwrap_or(start);
+        }
+
+        let mut end = (at + SNIPPET_CONTEXT * 2).min(text.len());
+        while !text.is_char_boundary(end) {
+            end += 1;
+        }
+        if end < text.len() {
+            end = text[..end].rfind(' ').filter(|i| *i > at).unwrap_or(end);
+        }
+
+        let prefix = if start > 0 { "…" } else { "" };
+        let suffix = if end < text.len() { "…" } else { "" };
+        let passage = &text[start..end];
+        let highlights = tokens(passage)
+            .filter(|(_, word)| is_match(word))
+            .map(|(range, _)| range.start + prefix.len()..range.end + prefix.len())
+            .collect();
+
+        Snippet {
+            text: format!("{}{}{}", prefix, passage, suffix),
+            highlights,
+        }
+    }
+}
+
+/// The results shown in the `:tarot search` window
+#[derive(Clone, Debug)]
+pub struct SearchResults {
+    pub query: String,
+    pub hits: Vec<SearchHit>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tarot_cards;
+
+    fn deck() -> SearchIndex {
+        SearchIndex::new(&tarot_cards::get_all_cards(), &Annotations::default())
+    }
+
+    fn names(hits: &[SearchHit]) -> Vec<&str> {
+        hits.iter().map(|hit| hit.card.card.as_str()).collect()
+    }
+
+    #[test]
+    fn test_stem() {
+        assert_eq!(stem("journeys"), stem("journey"));
+        assert_eq!(stem("hoping"), stem("hope"));
+        assert_eq!(stem("hoped"), stem("Hopes"));
+        assert_eq!(stem("successes"), stem("success"));
+        assert_eq!(stem("changes"), stem("changing"));
+        assert_eq!(stem("dreaminess"), "dreami");
+        assert_eq!(stem("Thoth's"), "thoth");
+        assert_eq!(stem("is"), "is");
+    }
+
+    #[test]
+    fn test_search_deck() {
+        let index = deck();
+
+        let hits = index.search("journey by water");
+        assert_eq!(hits[0].card.card, "Six of Swords");
+        assert_eq!(hits[0].matched, 2);
+
+        // Different endings match, and titles count
+        let hits = index.search("Journeys over WATERS");
+        assert_eq!(hits[0].card.card, "Six of Swords");
+        assert_eq!(names(&index.search("science"))[0], "Six of Swords");
+
+        // The card with the exact phrase comes before those with the words apart
+        let hits = index.search("Ill-dignified: conceit");
+        assert_eq!(hits[0].card.card, "Three of Wands");
+        assert!(names(&hits).contains(&"Six of Swords"));
+
+        assert!(index.search("xyzzy").is_empty());
+        assert!(index.search("the of and").is_empty());
+    }
+
+    #[test]
+    fn test_snippet() {
+        let hits = deck().search("journey by water");
+        let snippet = &hits[0].snippet;
+
+        let marked: Vec<&str> =
+            snippet.highlights.iter().map(|r| &snippet.text[r.clone()]).collect();
+        assert_eq!(marked, vec!["Journey", "water"]);
+        assert!(snippet.text.starts_with('…'));
+        assert!(snippet.text.contains("Passage from difficulty. Journey by water."));
+
+        // Cards found by their title alone show the start of their meaning
+        let hits = deck().search("science");
+        assert!(hits[0].snippet.text.starts_with("Science. Intelligence"));
+    }
+
+    #[test]
+    fn test_search_notes() {
+        let fool = tarot_cards::find_card("fool").unwrap();
+        let mut notes = Annotations::default();
+        notes.set(fool, "Always turns up before a move abroad", 1);
+
+        let index = SearchIndex::new(&tarot_cards::get_all_cards(), &notes);
+        let hits = index.search("moving abroad");
+        assert_eq!(hits[0].card.card, "The Fool");
+        assert_eq!(hits[0].snippet.text, "Always turns up before a move abroad");
+    }
+}
@@ -0,0 +1,269 @@
+//! The Moon's phase and the Sun's sign when a reading was drawn
+//!
+//! Both are worked out from the time of the reading with low-precision formulas for the
+//! Sun's and Moon's ecliptic longitudes, good to a fraction of a degree. That is close
+//! enough to name the phase and sign except within an hour or so of a change.
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::tarot_api::ReadingHistoryItem;
+use crate::tarot_wheel;
+
+/// The eight phases of the Moon, each an eighth of its cycle
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum MoonPhase {
+    New,
+    WaxingCrescent,
+    FirstQuarter,
+    WaxingGibbous,
+    Full,
+    WaningGibbous,
+    LastQuarter,
+    WaningCrescent,
+}
+
+impl MoonPhase {
+    pub const ALL: [MoonPhase; 8] = [
+        MoonPhase::New,
+        MoonPhase::WaxingCrescent,
+        MoonPhase::FirstQuarter,
+        MoonPhase::WaxingGibbous,
+        MoonPhase::Full,
+        MoonPhase::WaningGibbous,
+        MoonPhase::LastQuarter,
+        MoonPhase::WaningCrescent,
+    ];
+
+    /// The phase for the Moon's elongation from the Sun, in degrees
+    fn from_elongation(elongation: f64) -> Self {
+        let idx = (elongation.rem_euclid(360.0) / 45.0 + 0.5).floor() as usize % 8;
+        MoonPhase::ALL[idx]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MoonPhase::New => "New Moon",
+            MoonPhase::WaxingCrescent => "Waxing Crescent",
+            MoonPhase::FirstQuarter => "First Quarter",
+            MoonPhase::WaxingGibbous => "Waxing Gibbous",
+            MoonPhase::Full => "Full Moon",
+            MoonPhase::WaningGibbous => "Waning Gibbous",
+            MoonPhase::LastQuarter => "Last Quarter",
+            MoonPhase::WaningCrescent => "Waning Crescent",
+        }
+    }
+
+    pub fn glyph(&self) -> char {
+        match self {
+            MoonPhase::New => '🌑',
+            MoonPhase::WaxingCrescent => '🌒',
+            MoonPhase::FirstQuarter => '🌓',
+            MoonPhase::WaxingGibbous => '🌔',
+            MoonPhase::Full => '🌕',
+            MoonPhase::WaningGibbous => '🌖',
+            MoonPhase::LastQuarter => '🌗',
+            MoonPhase::WaningCrescent => '🌘',
+        }
+    }
+}
+
+impl fmt::Display for MoonPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for MoonPhase {
+    type Err = String;
+
+    /// Parse a phase as typed in a command, e.g. "full" or "waxing-crescent"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let phase = match s.to_lowercase().as_str() {
+            "new" => MoonPhase::New,
+            "waxing-crescent" => MoonPhase::WaxingCrescent,
+            "first-quarter" => MoonPhase::FirstQuarter,
+            "waxing-gibbous" => MoonPhase::WaxingGibbous,
+            "full" => MoonPhase::Full,
+            "waning-gibbous" => MoonPhase::WaningGibbous,
+            "last-quarter" | "third-quarter" => MoonPhase::LastQuarter,
+            "waning-crescent" => MoonPhase::WaningCrescent,
+            _ => {
+                return Err(format!(
+                    "Unknown moon phase: '{}' (expected new, waxing-crescent, first-quarter, \
+                     waxing-gibbous, full, waning-gibbous, last-quarter or waning-crescent)",
+                    s
+                ))
+            },
+        };
+
+        Ok(phase)
+    }
+}
+
+/// Days since the J2000.0 epoch, for a time in UTC
+fn days_since_j2000(time: NaiveDateTime) -> f64 {
+    // 2000-01-01 12:00 UTC, as seconds since the Unix epoch
+    const J2000: f64 = 946_728_000.0;
+
+    (time.and_utc().timestamp() as f64 - J2000) / 86_400.0
+}
+
+fn sin_deg(deg: f64) -> f64 {
+    deg.to_radians().sin()
+}
+
+/// The Sun's ecliptic longitude, in degrees from 0° Aries
+fn sun_longitude(n: f64) -> f64 {
+    let mean = 280.460 + 0.985_647_4 * n;
+    let anomaly = 357.528 + 0.985_600_3 * n;
+
+    (mean + 1.915 * sin_deg(anomaly) + 0.020 * sin_deg(2.0 * anomaly)).rem_euclid(360.0)
+}
+
+/// The Moon's ecliptic longitude, with its largest periodic terms
+fn moon_longitude(n: f64) -> f64 {
+    let mean = 218.316 + 13.176_396 * n;
+    let anomaly = 134.963 + 13.064_993 * n;
+    let elongation = 297.850 + 12.190_749 * n;
+    let sun_anomaly = 357.528 + 0.985_600_3 * n;
+
+    (mean +
+        6.289 * sin_deg(anomaly) +
+        1.274 * sin_deg(2.0 * elongation - anomaly) +
+        0.658 * sin_deg(2.0 * elongation) -
+        0.186 * sin_deg(sun_anomaly))
+    .rem_euclid(360.0)
+}
+
+/// The Sun and Moon at a moment in time
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sky {
+    /// The Sun's longitude in degrees from 0° Aries
+    pub sun_longitude: f64,
+    pub moon: MoonPhase,
+    /// How much of the Moon's disc is lit, from 0 to 1
+    pub illumination: f64,
+}
+
+impl Sky {
+    /// Work out the sky at a time in UTC
+    pub fn at(time: NaiveDateTime) -> Self {
+        let n = days_since_j2000(time);
+        let sun = sun_longitude(n);
+        let elongation = (moon_longitude(n) - sun).rem_euclid(360.0);
+
+        Sky {
+            sun_longitude: sun,
+            moon: MoonPhase::from_elongation(elongation),
+            illumination: (1.0 - elongation.to_radians().cos()) / 2.0,
+        }
+    }
+
+    /// The Sun's sign, from 0 for Aries
+    pub fn sun_sign(&self) -> usize {
+        (self.sun_longitude / 30.0) as usize % 12
+    }
+
+    pub fn sun_sign_name(&self) -> &'static str {
+        tarot_wheel::SIGNS[self.sun_sign()]
+    }
+
+    /// The line stamped on a posted reading
+    pub fn header(&self) -> String {
+        format!(
+            "**Sky:** {} {} ({:.0}% lit) · Sun in {} {}\n\n",
+            self.moon.glyph(),
+            self.moon,
+            self.illumination * 100.0,
+            tarot_wheel::sign_glyph(self.sun_sign()),
+            self.sun_sign_name()
+        )
+    }
+}
+
+/// Parse a reading date in UTC, as sent by the server or written in an export
+///
+/// Dates without a time of day are taken to be at noon.
+pub fn parse_time(date: &str) -> Option<NaiveDateTime> {
+    let date = date.replace(' ', "T");
+
+    date.get(..19)
+        .and_then(|s| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").ok())
+        .or_else(|| {
+            let day = NaiveDate::parse_from_str(date.get(..10)?, "%Y-%m-%d").ok()?;
+            day.and_hms_opt(12, 0, 0)
+        })
+}
+
+/// The time a reading was drawn, in UTC
+pub fn reading_time(reading: &ReadingHistoryItem) -> Option<NaiveDateTime> {
+    parse_time(&reading.reading_date)
+}
+
+/// The moon phase and Sun sign of a reading, as saved with it or else worked out from its date
+pub fn reading_sky(reading: &ReadingHistoryItem) -> Option<(String, String)> {
+    if let (Some(moon), Some(sun)) = (&reading.moon_phase, &reading.sun_sign) {
+        return Some((moon.clone(), sun.clone()));
+    }
+
+    let sky = Sky::at(reading_time(reading)?);
+    Some((sky.moon.to_string(), sky.sun_sign_name().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_moon_phase() {
+        // Full moon 2024-01-25 17:54 UTC, new moon 2024-02-09 22:59 UTC
+        let sky = Sky::at(time("2024-01-25T18:00:00"));
+        assert_eq!(sky.moon, MoonPhase::Full);
+        assert!(sky.illumination > 0.99);
+
+        let sky = Sky::at(time("2024-02-09T23:00:00"));
+        assert_eq!(sky.moon, MoonPhase::New);
+        assert!(sky.illumination < 0.01);
+
+        // First quarter 2024-02-16 15:01 UTC, with the crescent still waxing two days before
+        assert_eq!(Sky::at(time("2024-02-16T15:00:00")).moon, MoonPhase::FirstQuarter);
+        assert_eq!(Sky::at(time("2024-02-12T12:00:00")).moon, MoonPhase::WaxingCrescent);
+    }
+
+    #[test]
+    fn test_sun_sign() {
+        assert_eq!(Sky::at(time("2026-01-05T12:00:00")).sun_sign_name(), "Capricorn");
+
+        // The March equinox of 2026 falls at 14:46 UTC on the 20th
+        assert_eq!(Sky::at(time("2026-03-20T12:00:00")).sun_sign_name(), "Pisces");
+        assert_eq!(Sky::at(time("2026-03-20T18:00:00")).sun_sign_name(), "Aries");
+    }
+
+    #[test]
+    fn test_parse_phase() {
+        assert_eq!("full".parse(), Ok(MoonPhase::Full));
+        assert_eq!("Waxing-Crescent".parse(), Ok(MoonPhase::WaxingCrescent));
+        assert_eq!("third-quarter".parse(), Ok(MoonPhase::LastQuarter));
+        assert!("blue".parse::<MoonPhase>().is_err());
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("2026-01-05T08:30:00.123456"), Some(time("2026-01-05T08:30:00")));
+        assert_eq!(parse_time("2026-01-05 08:30:00"), Some(time("2026-01-05T08:30:00")));
+        assert_eq!(parse_time("2026-01-05"), Some(time("2026-01-05T12:00:00")));
+        assert_eq!(parse_time("yesterday"), None);
+    }
+
+    #[test]
+    fn test_header() {
+        let header = Sky::at(time("2024-01-25T18:00:00")).header();
+        assert_eq!(header, "**Sky:** 🌕 Full Moon (100% lit) · Sun in ♒ Aquarius\n\n");
+    }
+}
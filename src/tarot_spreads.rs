@@ -0,0 +1,874 @@
+//! Spread layouts and drawing of tarot readings
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use chrono::{Local, NaiveDate, TimeZone};
+use matrix_sdk::ruma::{OwnedEventId, OwnedRoomId};
+use rand::seq::SliceRandom;
+
+use crate::base::{SendAction, TarotFlags};
+use crate::image_upload::ImageUpload;
+use crate::tarot_annotations::Annotations;
+use crate::tarot_api;
+use crate::tarot_calendar::Today;
+use crate::tarot_cards::{self, TarotCard, Translation};
+use crate::tarot_composite::{self, CompositeOptions};
+use crate::tarot_dignities;
+use crate::tarot_numerology;
+use crate::tarot_sky::{self, Sky};
+use crate::tarot_store::ReadingStore;
+use crate::tarot_tree;
+use crate::tarot_wheel;
+
+/// Position labels for the Celtic Cross, in drawing order
+const CELTIC_CROSS: [&str; 10] = [
+    "Present",
+    "Challenge",
+    "Foundation",
+    "Recent Past",
+    "Crown",
+    "Near Future",
+    "Self",
+    "Environment",
+    "Hopes and Fears",
+    "Outcome",
+];
+
+/// Spread type recorded for the card of the day
+pub const DAILY: &str = "daily";
+
+/// A spread layout: its name and a label for each position
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Spread {
+    /// Name recorded as the reading's spread type
+    pub name: String,
+    /// Label for each position, in drawing order
+    pub positions: Vec<String>,
+}
+
+impl Spread {
+    /// A spread of `count` unnamed positions ("Card 1", "Card 2", ...)
+    pub fn numbered(count: usize) -> Self {
+        Spread {
+            name: count.to_string(),
+            positions: (1..=count).map(|i| format!("Card {}", i)).collect(),
+        }
+    }
+
+    /// The ten-card Celtic Cross
+    pub fn celtic_cross() -> Self {
+        Spread {
+            name: "celtic".to_string(),
+            positions: CELTIC_CROSS.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    /// The one card drawn each day by `:tarot daily`
+    pub fn daily() -> Self {
+        Spread {
+            name: DAILY.to_string(),
+            positions: vec!["Card of the Day".to_string()],
+        }
+    }
+
+    /// Number of cards drawn for this spread
+    pub fn card_count(&self) -> usize {
+        self.positions.len()
+    }
+}
+
+/// Look up a named spread
+pub fn find_spread(name: &str) -> Option<Spread> {
+    match name.to_lowercase().as_str() {
+        "celtic" | "celticcross" | "celtic-cross" => Some(Spread::celtic_cross()),
+        "threecard" | "three-card" => Some(Spread::numbered(3)),
+        _ => None,
+    }
+}
+
+/// Message header showing the querent's question
+pub fn question_header(question: &str) -> String {
+    format!("**Question:** {}\n\n", question)
+}
+
+/// Who a reading is drawn for, where it is posted and saved, their own notes on the cards and
+/// the language they read them in
+#[derive(Clone)]
+pub struct Querent {
+    /// The user whose history the reading is saved to
+    pub matrix_id: String,
+    /// The room the reading is posted to, saved with it
+    pub room_id: Option<String>,
+    /// The user's notes, shown after the deck's meanings
+    pub notes: Annotations,
+    /// Where the reading is saved
+    pub store: Arc<dyn ReadingStore>,
+    /// The language card names and meanings are shown in
+    pub language: Arc<Translation>,
+}
+
+/// Build the action that posts a single card from the deck, with the meanings asked for
+pub fn post_card(
+    card: &TarotCard,
+    question: Option<String>,
+    flags: TarotFlags,
+    notes: &Annotations,
+    lang: &Translation,
+) -> SendAction {
+    let file_path = card.image_path().to_string_lossy().to_string();
+
+    let info_text = flags.intersects(TarotFlags::INFO | TarotFlags::DEEPINFO).then(|| {
+        let show_info = flags.contains(TarotFlags::INFO);
+        let show_deepinfo = flags.contains(TarotFlags::DEEPINFO);
+        tarot_cards::format_card_info(card, show_info, show_deepinfo, None, notes.get(card), lang)
+    });
+
+    // Upload the card image, captioned with the question if one was asked
+    match (question, info_text) {
+        (Some(question), info_text) => {
+            let caption = format!("Question: {}", question);
+            let text = info_text.map(|info| question_header(&question) + info.as_str());
+            SendAction::UploadWithCaption(file_path, caption, text)
+        },
+        (None, Some(info_text)) => SendAction::UploadWithText(file_path, info_text),
+        (None, None) => SendAction::Upload(file_path),
+    }
+}
+
+/// Build the action that posts the small card ruling the Sun's current decan, with the trump
+/// of its sign
+pub fn post_today(
+    today: &Today,
+    flags: TarotFlags,
+    notes: &Annotations,
+    lang: &Translation,
+) -> SendAction {
+    let card = today.decan.card;
+
+    let mut text = today.to_text();
+    if flags.intersects(TarotFlags::INFO | TarotFlags::DEEPINFO) {
+        let show_info = flags.contains(TarotFlags::INFO);
+        let show_deepinfo = flags.contains(TarotFlags::DEEPINFO);
+        text.push('\n');
+        text.push_str(&tarot_cards::format_card_info(
+            card,
+            show_info,
+            show_deepinfo,
+            None,
+            notes.get(card),
+            lang,
+        ));
+    }
+
+    SendAction::UploadWithText(card.image_path().to_string_lossy().to_string(), text)
+}
+
+/// Find today's card among the reading history, if it has already been drawn
+///
+/// Days run from midnight to midnight in local time.
+pub fn daily_reading(
+    readings: &[tarot_api::ReadingHistoryItem],
+    today: NaiveDate,
+) -> Option<&tarot_api::ReadingHistoryItem> {
+    readings.iter().find(|reading| {
+        let drawn = tarot_sky::reading_time(reading)
+            .map(|time| Local.from_utc_datetime(&time).date_naive());
+        reading.spread_type == DAILY && drawn == Some(today)
+    })
+}
+
+/// Build the action that posts today's card again, when it has already been drawn
+pub fn repeat_daily(
+    reading: &tarot_api::ReadingHistoryItem,
+    flags: TarotFlags,
+    notes: &Annotations,
+    lang: &Translation,
+) -> Result<SendAction, String> {
+    let name = reading.cards.first().map(|c| c.card_name.as_str()).unwrap_or_default();
+    let card = tarot_cards::find_card(name)
+        .ok_or_else(|| format!("Unknown card in today's reading: {}", name))?;
+
+    let name = lang.name(card);
+    let mut text = format!(
+        "**Card of the Day:** {}\nAlready drawn today; a new card can be drawn tomorrow.\n\n",
+        name
+    );
+
+    if flags.intersects(TarotFlags::INFO | TarotFlags::DEEPINFO) {
+        let show_info = flags.contains(TarotFlags::INFO);
+        let show_deepinfo = flags.contains(TarotFlags::DEEPINFO);
+        text.push_str(&tarot_cards::format_card_info(
+            card,
+            show_info,
+            show_deepinfo,
+            None,
+            notes.get(card),
+            lang,
+        ));
+    }
+
+    Ok(SendAction::UploadWithText(card.image_path().to_string_lossy().to_string(), text))
+}
+
+/// A spread posted face down, whose cards are turned over one at a time by editing the post
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FaceDown {
+    /// Image paths of the cards, in drawing order
+    pub cards: Vec<String>,
+    pub names: Vec<String>,
+    pub labels: Vec<String>,
+    pub revealed: Vec<bool>,
+    pub question: Option<String>,
+    /// The face-down message, once it has been sent
+    pub event_id: Option<OwnedEventId>,
+    /// Sent once every card has been turned over: the reading's text, tree and wheel
+    pub finale: Vec<SendAction>,
+}
+
+impl FaceDown {
+    /// Turn over the card at a position, counting from 1, or else the first face-down one
+    ///
+    /// Returns the position that was turned over, from 0.
+    pub fn reveal(&mut self, position: Option<usize>) -> Result<usize, String> {
+        let idx = match position {
+            Some(n) if n == 0 || n > self.cards.len() => {
+                return Err(format!("Invalid position. Valid range: 1-{}", self.cards.len()));
+            },
+            Some(n) => n - 1,
+            None => {
+                self.revealed
+                    .iter()
+                    .position(|up| !up)
+                    .ok_or_else(|| "Every card has already been revealed".to_string())?
+            },
+        };
+
+        if self.revealed[idx] {
+            return Err(format!("{} has already been revealed", self.labels[idx]));
+        }
+
+        self.revealed[idx] = true;
+        Ok(idx)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.revealed.iter().all(|up| *up)
+    }
+
+    /// The images making up the composite: each card, or its back if still face down
+    pub fn composite_paths(&self) -> Vec<String> {
+        let back = tarot_cards::card_back_path().to_string_lossy().to_string();
+
+        self.cards
+            .iter()
+            .zip(&self.revealed)
+            .map(|(card, up)| if *up { card.clone() } else { back.clone() })
+            .collect()
+    }
+
+    /// The caption of the posted image, listing the cards turned over so far
+    pub fn caption(&self) -> String {
+        let mut caption = match &self.question {
+            Some(question) => format!("Question: {}", question),
+            None => "Face-down spread".to_string(),
+        };
+
+        let turned: Vec<String> = self
+            .labels
+            .iter()
+            .zip(&self.names)
+            .zip(&self.revealed)
+            .filter(|(_, up)| **up)
+            .map(|((label, name), _)| format!("{}: {}", label, name))
+            .collect();
+
+        if !turned.is_empty() {
+            caption.push_str(&format!(" | {}", turned.join(", ")));
+        }
+
+        caption
+    }
+}
+
+/// An image posted to a spread's thread, with a Markdown caption
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ThreadPost {
+    pub image: ImageUpload,
+    pub caption: String,
+}
+
+/// A spread posted as a thread, so that the room's timeline shows a single post per reading
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SpreadThread {
+    /// The composite image, captioned with the question, sky and a list of the cards
+    pub root: ThreadPost,
+    /// Each position's card and its meaning, followed by the Tree of Life and zodiac wheel
+    pub replies: Vec<ThreadPost>,
+}
+
+/// Draw `count` distinct cards at random
+pub fn draw_cards(count: usize) -> Result<Vec<&'static TarotCard>, String> {
+    let all_cards = tarot_cards::get_all_cards();
+
+    if all_cards.len() < count {
+        return Err(format!(
+            "Not enough tarot cards in database. Need {}, have {}",
+            count,
+            all_cards.len()
+        ));
+    }
+
+    let mut rng = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let mut selected = HashSet::new();
+    while selected.len() < count {
+        rng = rng.wrapping_mul(1103515245).wrapping_add(12345);
+        let idx = (rng as usize) % all_cards.len();
+        selected.insert(idx);
+    }
+
+    Ok(selected.into_iter().map(|idx| all_cards[idx]).collect())
+}
+
+/// Shuffle the whole deck
+pub fn shuffle_deck() -> Vec<&'static TarotCard> {
+    let mut deck = tarot_cards::get_all_cards();
+    deck.shuffle(&mut rand::thread_rng());
+
+    deck
+}
+
+/// A spread whose cards the querent is choosing from a face-down fan in the
+/// `:tarot <spread> pick` window
+#[derive(Clone, Debug)]
+pub struct PickSession {
+    pub spread: Spread,
+    pub question: Option<String>,
+    pub flags: TarotFlags,
+    /// The room to post the reading in once every card is chosen
+    pub room_id: OwnedRoomId,
+    pub deck: Vec<&'static TarotCard>,
+    /// Positions in the deck of the chosen cards, in the order they were chosen
+    pub chosen: Vec<usize>,
+}
+
+impl PickSession {
+    pub fn new(
+        spread: Spread,
+        question: Option<String>,
+        flags: TarotFlags,
+        room_id: OwnedRoomId,
+    ) -> Self {
+        PickSession {
+            spread,
+            question,
+            flags,
+            room_id,
+            deck: shuffle_deck(),
+            chosen: vec![],
+        }
+    }
+
+    /// Choose the card at a position in the fan, or put it back if it was already chosen
+    pub fn toggle(&mut self, idx: usize) -> Result<(), String> {
+        if idx >= self.deck.len() {
+            return Err("No card there".to_string());
+        }
+
+        if let Some(pos) = self.chosen.iter().position(|i| *i == idx) {
+            self.chosen.remove(pos);
+        } else if self.is_complete() {
+            return Err(format!("All {} cards have been chosen", self.spread.card_count()));
+        } else {
+            self.chosen.push(idx);
+        }
+
+        Ok(())
+    }
+
+    /// Which spread position a card in the fan was chosen for, from 0
+    pub fn position_of(&self, idx: usize) -> Option<usize> {
+        self.chosen.iter().position(|i| *i == idx)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.chosen.len() >= self.spread.card_count()
+    }
+
+    /// The chosen cards, in the order of the spread's positions
+    pub fn cards(&self) -> Vec<&'static TarotCard> {
+        self.chosen.iter().map(|i| self.deck[*i]).collect()
+    }
+
+    /// What to pick next, shown above the fan
+    pub fn status(&self) -> String {
+        match self.spread.positions.get(self.chosen.len()) {
+            Some(label) => {
+                format!(
+                    "Choose a card for {} ({} of {})",
+                    label,
+                    self.chosen.len() + 1,
+                    self.spread.card_count()
+                )
+            },
+            None => "Every position is filled".to_string(),
+        }
+    }
+}
+
+/// Draw a spread, save it to the reading history with the room it is posted to, and build the
+/// actions that post it
+///
+/// The spread is posted first, followed by the Tree of Life if [TarotFlags::TREE] is given
+/// and the zodiac wheel if [TarotFlags::WHEEL] is. Spreads of more than one card start a
+/// thread: its root is the composite and a summary, and each position's card and meaning,
+/// then the tree and wheel, follow as replies. With [TarotFlags::FACEDOWN], only the card
+/// backs are posted, and the rest is held back until `:tarot reveal` turns over the last
+/// card.
+pub fn perform_reading(
+    querent: &Querent,
+    spread: &Spread,
+    question: Option<String>,
+    flags: TarotFlags,
+    composite: &CompositeOptions,
+) -> Result<Vec<SendAction>, String> {
+    let cards = draw_cards(spread.card_count())?;
+
+    post_reading(querent, spread, cards, question, flags, composite)
+}
+
+/// Save a spread of cards already drawn, in position order, to the querent's history with the
+/// room it is posted to, and build the actions that post it
+///
+/// This is how [perform_reading] posts random draws and how the `:tarot <spread> pick`
+/// window posts the cards the querent chose.
+pub fn post_reading(
+    querent: &Querent,
+    spread: &Spread,
+    cards: Vec<&'static TarotCard>,
+    question: Option<String>,
+    flags: TarotFlags,
+    composite: &CompositeOptions,
+) -> Result<Vec<SendAction>, String> {
+    if cards.len() != spread.card_count() {
+        return Err(format!("The {} spread needs {} cards", spread.name, spread.card_count()));
+    }
+
+    let card_paths: Vec<String> = cards
+        .iter()
+        .map(|card| card.image_path().to_string_lossy().to_string())
+        .collect();
+
+    // Cards are saved under their English names, but shown in the reader's language
+    let lang = &querent.language;
+
+    let mut facedown = flags.contains(TarotFlags::FACEDOWN).then(|| {
+        FaceDown {
+            cards: card_paths.clone(),
+            names: cards.iter().map(|card| lang.name(card).to_string()).collect(),
+            labels: spread.positions.clone(),
+            revealed: vec![false; cards.len()],
+            question: question.clone(),
+            event_id: None,
+            finale: vec![],
+        }
+    });
+
+    let composite_cards = match &facedown {
+        Some(facedown) => facedown.composite_paths(),
+        None => card_paths,
+    };
+    let composite_image = tarot_composite::create_composite_spread(&composite_cards, composite)
+        .map_err(|e| format!("Failed to create composite image: {}", e))?;
+
+    let sky = Sky::at(chrono::Utc::now().naive_utc());
+
+    let dignities = tarot_dignities::spread_dignities(&cards);
+
+    // A lone card has no quintessence worth reporting
+    let numerology = Some(&cards)
+        .filter(|cards| cards.len() > 1)
+        .and_then(|cards| tarot_numerology::spread_numerology(cards));
+
+    let card_data: Vec<tarot_api::CardData> = cards
+        .iter()
+        .zip(&spread.positions)
+        .zip(&dignities)
+        .enumerate()
+        .map(|(i, ((card, label), dignity))| {
+            tarot_api::CardData {
+                position: i as i32,
+                card_name: card.card.clone(),
+                card_label: Some(label.clone()),
+                is_reversed: false,
+                dignity: Some(dignity.as_str().to_string()),
+            }
+        })
+        .collect();
+
+    let reading_request = tarot_api::ReadingCreate {
+        matrix_id: querent.matrix_id.clone(),
+        room_id: querent.room_id.clone(),
+        spread_type: spread.name.clone(),
+        cards: card_data,
+        notes: None,
+        question: question.clone(),
+        reading_date: None,
+        quintessence: numerology.as_ref().map(|n| n.quintessence.card.clone()),
+        numerology: numerology.as_ref().map(|n| n.to_text()),
+        moon_phase: Some(sky.moon.to_string()),
+        sun_sign: Some(sky.sun_sign_name().to_string()),
+        is_private: false,
+    };
+
+    // Failures are logged but don't stop the reading
+    match querent.store.save(reading_request) {
+        Ok(response) => tracing::debug!("Saved tarot reading {}", response.reading_id),
+        Err(e) => tracing::warn!(err = %e, "Failed to save reading to history"),
+    }
+
+    let show_info = flags.contains(TarotFlags::INFO);
+    let show_deepinfo = flags.contains(TarotFlags::DEEPINFO);
+
+    // Every reading is posted with the sky it was drawn under
+    let mut text = question.as_deref().map(question_header).unwrap_or_default();
+    text.push_str(&sky.header());
+
+    // Spreads of several cards list them at the root of a thread, with the details in replies
+    let threaded = facedown.is_none() && cards.len() > 1;
+
+    if threaded {
+        for (i, (card, label)) in cards.iter().zip(&spread.positions).enumerate() {
+            text.push_str(&format!("{}. **{}:** {}\n", i + 1, label, lang.name(card)));
+        }
+        text.push('\n');
+    } else if show_info || show_deepinfo {
+        for ((card, label), dignity) in cards.iter().zip(&spread.positions).zip(&dignities) {
+            text.push_str(&format!("**{}:**\n", label));
+            text.push_str(&tarot_cards::format_card_info(
+                card,
+                show_info,
+                show_deepinfo,
+                Some(dignity),
+                querent.notes.get(card),
+                lang,
+            ));
+            text.push('\n');
+        }
+    }
+
+    if let Some(numerology) = &numerology {
+        text.push_str(&numerology.to_text());
+    }
+
+    let mut extras = vec![];
+
+    if flags.contains(TarotFlags::TREE) {
+        let highlights = tarot_tree::tree_highlights(&cards);
+        let tree_image = tarot_tree::tree_upload(&highlights)
+            .map_err(|e| format!("Failed to create Tree of Life image: {}", e))?;
+        let legend = format!("**Tree of Life**\n{}", highlights.legend());
+
+        extras.push(ThreadPost { image: tree_image, caption: legend });
+    }
+
+    if flags.contains(TarotFlags::WHEEL) {
+        let wheel = tarot_wheel::reading_wheel(spread.name.clone(), &cards);
+        let wheel_image = tarot_wheel::wheel_upload(&wheel)
+            .map_err(|e| format!("Failed to create zodiac wheel image: {}", e))?;
+        let legend = format!("**Zodiac**\n{}", wheel.legend());
+
+        extras.push(ThreadPost { image: wheel_image, caption: legend });
+    }
+
+    if threaded {
+        // The thread always carries each card's meaning, since that is where it is read
+        let mut replies = cards
+            .iter()
+            .zip(&spread.positions)
+            .zip(&dignities)
+            .enumerate()
+            .map(|(i, ((card, label), dignity))| {
+                let info = tarot_cards::format_card_info(
+                    card,
+                    true,
+                    show_deepinfo,
+                    Some(dignity),
+                    querent.notes.get(card),
+                    lang,
+                );
+                let caption = format!("**{}. {}:** {}", i + 1, label, info);
+                let image = ImageUpload::from_file(&card.image_path().to_string_lossy())?;
+
+                Ok(ThreadPost { image, caption })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        replies.append(&mut extras);
+
+        let root = ThreadPost { image: composite_image, caption: text };
+        return Ok(vec![SendAction::TarotSpread(Box::new(SpreadThread {
+            root,
+            replies,
+        }))]);
+    }
+
+    // Face-down spreads hold back the text until the last card is turned over
+    let mut acts = match (facedown.as_mut(), question) {
+        (Some(facedown), _) => {
+            facedown.finale.push(SendAction::SendText(text));
+            vec![]
+        },
+        (None, Some(question)) => {
+            let caption = format!("Question: {}", question);
+            vec![SendAction::UploadBytes(
+                Box::new(composite_image.clone()),
+                Some(caption),
+                Some(text),
+            )]
+        },
+        (None, None) => {
+            vec![SendAction::UploadBytes(
+                Box::new(composite_image.clone()),
+                None,
+                Some(text),
+            )]
+        },
+    };
+
+    acts.extend(
+        extras
+            .into_iter()
+            .map(|post| SendAction::UploadBytes(Box::new(post.image), None, Some(post.caption))),
+    );
+
+    if let Some(mut facedown) = facedown {
+        facedown.finale.append(&mut acts);
+        acts.push(SendAction::UploadFaceDown(Box::new(composite_image), Box::new(facedown)));
+    }
+
+    Ok(acts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_spread() {
+        let celtic = find_spread("celtic").unwrap();
+        assert_eq!(celtic.name, "celtic");
+        assert_eq!(celtic.card_count(), 10);
+        assert_eq!(celtic.positions[0], "Present");
+        assert_eq!(celtic.positions[9], "Outcome");
+
+        assert_eq!(find_spread("Celtic-Cross"), Some(Spread::celtic_cross()));
+        assert_eq!(find_spread("threecard"), Some(Spread::numbered(3)));
+        assert_eq!(find_spread("fool"), None);
+    }
+
+    fn reading(date: &str, spread_type: &str, card: &str) -> tarot_api::ReadingHistoryItem {
+        tarot_api::ReadingHistoryItem {
+            reading_id: 1,
+            reading_date: date.to_string(),
+            spread_type: spread_type.to_string(),
+            card_count: 1,
+            cards: vec![tarot_api::CardInReading {
+                position: 0,
+                card_name: card.to_string(),
+                label: None,
+                reversed: false,
+                dignity: None,
+            }],
+            notes: None,
+            question: None,
+            room_id: None,
+            quintessence: None,
+            moon_phase: None,
+            sun_sign: None,
+        }
+    }
+
+    #[test]
+    fn test_daily_reading() {
+        let readings = vec![
+            reading("2026-10-18T12:00:00", "3", "The Fool"),
+            reading("2026-10-18T11:00:00", DAILY, "The Star"),
+            reading("2026-10-17T12:00:00", DAILY, "The Moon"),
+        ];
+        let day = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+
+        let today = daily_reading(&readings, day("2026-10-18")).unwrap();
+        assert_eq!(today.cards[0].card_name, "The Star");
+        assert_eq!(
+            daily_reading(&readings, day("2026-10-17")).unwrap().cards[0].card_name,
+            "The Moon"
+        );
+        assert!(daily_reading(&readings, day("2026-10-19")).is_none());
+
+        let english = Translation::default();
+        match repeat_daily(today, TarotFlags::NONE, &Annotations::default(), &english).unwrap() {
+            SendAction::UploadWithText(_, text) => {
+                assert!(text.starts_with("**Card of the Day:** The Star\n"))
+            },
+            act => panic!("unexpected action {:?}", act),
+        }
+
+        let german = Translation::load(&tarot_cards::get_deck_dir(), "de").unwrap();
+        match repeat_daily(today, TarotFlags::NONE, &Annotations::default(), &german).unwrap() {
+            SendAction::UploadWithText(_, text) => {
+                assert!(text.starts_with("**Card of the Day:** Der Stern\n"))
+            },
+            act => panic!("unexpected action {:?}", act),
+        }
+    }
+
+    #[test]
+    fn test_post_card() {
+        let fool = tarot_cards::find_card("fool").unwrap();
+        let path = fool.image_path().to_string_lossy().to_string();
+        let mut notes = Annotations::default();
+        notes.set(fool, "Leaping before looking", 10);
+        let english = Translation::default();
+
+        let act = post_card(fool, None, TarotFlags::NONE, &notes, &english);
+        assert_eq!(act, SendAction::Upload(path.clone()));
+
+        match post_card(fool, Some("What now?".into()), TarotFlags::INFO, &notes, &english) {
+            SendAction::UploadWithCaption(file, caption, Some(text)) => {
+                assert_eq!(file, path);
+                assert_eq!(caption, "Question: What now?");
+                assert!(text.starts_with("**Question:** What now?\n\n**The Fool**\n\n"));
+                assert!(text.ends_with("**My notes:** Leaping before looking\n\n"));
+            },
+            act => panic!("unexpected action {:?}", act),
+        }
+
+        let now = NaiveDate::from_ymd_opt(2026, 10, 18)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        let today = Today::at(now).unwrap();
+        let card = today.decan.card;
+        notes.set(card, "Came up the week I moved", 20);
+
+        match post_today(&today, TarotFlags::INFO, &notes, &english) {
+            SendAction::UploadWithText(file, text) => {
+                assert_eq!(file, card.image_path().to_string_lossy());
+                assert!(text.starts_with("**Today:** "));
+                assert!(text.ends_with("**My notes:** Came up the week I moved\n\n"));
+            },
+            act => panic!("unexpected action {:?}", act),
+        }
+    }
+
+    #[test]
+    fn test_post_reading_saves_for_querent() {
+        use crate::config::CompositeFormat;
+        use crate::tarot_store::MemoryStore;
+
+        let store = Arc::new(MemoryStore::default());
+        let querent = Querent {
+            matrix_id: "@alice:example.com".into(),
+            room_id: Some("!tarot:example.com".into()),
+            notes: Annotations::default(),
+            store: store.clone(),
+            language: Arc::new(Translation::load(&tarot_cards::get_deck_dir(), "es").unwrap()),
+        };
+        let composite = CompositeOptions {
+            card_width: 60,
+            format: CompositeFormat::Png,
+            max_bytes: 0,
+            cache_dir: None,
+        };
+        let cards = vec![tarot_cards::find_card("fool").unwrap()];
+
+        let acts =
+            post_reading(&querent, &Spread::numbered(1), cards, None, TarotFlags::INFO, &composite)
+                .unwrap();
+        match acts.as_slice() {
+            [SendAction::UploadBytes(_, None, Some(text))] => {
+                assert!(text.contains("**El Loco**"), "{}", text)
+            },
+            acts => panic!("unexpected actions {:?}", acts),
+        }
+
+        // Saved under the deck's English name, whatever language it was shown in
+        let history = store.history("@alice:example.com").unwrap();
+        assert_eq!(history.total_readings, 1);
+        assert_eq!(history.readings[0].cards[0].card_name, "The Fool");
+        assert_eq!(history.readings[0].room_id.as_deref(), Some("!tarot:example.com"));
+        assert!(store.history("@bob:example.com").unwrap().readings.is_empty());
+    }
+
+    #[test]
+    fn test_facedown_reveal() {
+        let mut facedown = FaceDown {
+            cards: vec!["fool.jpg".into(), "star.jpg".into(), "moon.jpg".into()],
+            names: vec!["The Fool".into(), "The Star".into(), "The Moon".into()],
+            labels: vec!["Card 1".into(), "Card 2".into(), "Card 3".into()],
+            revealed: vec![false; 3],
+            question: Some("What now?".into()),
+            event_id: None,
+            finale: vec![],
+        };
+        assert_eq!(facedown.caption(), "Question: What now?");
+        assert!(facedown.composite_paths().iter().all(|p| p.ends_with("card_back.jpg")));
+
+        assert_eq!(facedown.reveal(Some(2)), Ok(1));
+        assert_eq!(facedown.reveal(None), Ok(0));
+        assert_eq!(facedown.caption(), "Question: What now? | Card 1: The Fool, Card 2: The Star");
+        assert_eq!(facedown.composite_paths()[..2], ["fool.jpg", "star.jpg"]);
+        assert!(facedown.composite_paths()[2].ends_with("card_back.jpg"));
+
+        assert_eq!(facedown.reveal(Some(1)), Err("Card 1 has already been revealed".into()));
+        assert_eq!(facedown.reveal(Some(4)), Err("Invalid position. Valid range: 1-3".into()));
+        assert!(!facedown.is_complete());
+
+        assert_eq!(facedown.reveal(None), Ok(2));
+        assert!(facedown.is_complete());
+        assert_eq!(facedown.reveal(None), Err("Every card has already been revealed".into()));
+    }
+
+    #[test]
+    fn test_shuffle_deck() {
+        let deck = shuffle_deck();
+        assert_eq!(deck.len(), tarot_cards::get_all_cards().len());
+
+        let names: HashSet<&str> = deck.iter().map(|card| card.card.as_str()).collect();
+        assert_eq!(names.len(), deck.len());
+    }
+
+    #[test]
+    fn test_pick_session() {
+        let room_id = matrix_sdk::ruma::owned_room_id!("!tarot:example.com");
+        let mut session = PickSession::new(Spread::numbered(2), None, TarotFlags::NONE, room_id);
+        assert_eq!(session.status(), "Choose a card for Card 1 (1 of 2)");
+
+        session.toggle(5).unwrap();
+        session.toggle(9).unwrap();
+        assert!(session.is_complete());
+        assert_eq!(session.status(), "Every position is filled");
+        assert_eq!(session.position_of(9), Some(1));
+        assert_eq!(session.toggle(3), Err("All 2 cards have been chosen".into()));
+
+        // Choosing a card again puts it back
+        session.toggle(5).unwrap();
+        assert_eq!(session.chosen, vec![9]);
+        assert_eq!(session.status(), "Choose a card for Card 2 (2 of 2)");
+        assert_eq!(session.toggle(100), Err("No card there".into()));
+
+        session.toggle(0).unwrap();
+        let names: Vec<&str> = session.cards().iter().map(|c| c.card.as_str()).collect();
+        assert_eq!(names, vec![session.deck[9].card.as_str(), session.deck[0].card.as_str()]);
+    }
+
+    #[test]
+    fn test_numbered_spread() {
+        let spread = Spread::numbered(2);
+        assert_eq!(spread.name, "2");
+        assert_eq!(spread.positions, vec!["Card 1", "Card 2"]);
+    }
+}
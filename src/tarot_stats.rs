@@ -0,0 +1,443 @@
+//! Comparison of drawn cards against what the deck gives by chance
+use std::collections::{BTreeMap, HashMap};
+
+use crate::tarot_api::ReadingHistoryItem;
+use crate::tarot_cards::{self, TarotCard};
+
+/// Significance level for the tests, before correcting for the number of categories
+const ALPHA: f64 = 0.05;
+
+/// Smallest expected count per category for the chi-square approximation to hold
+const MIN_EXPECTED: f64 = 5.0;
+
+/// Attribute types compared against the deck, besides individual cards
+pub const ATTRIBUTE_TYPES: [&str; 5] = ["suit", "element", "planet", "sign", "sephira"];
+
+/// Observed against expected count for one category
+#[derive(Clone, Debug, PartialEq)]
+pub struct Deviation {
+    pub value: String,
+    pub observed: i32,
+    pub expected: f64,
+    /// Two-sided exact binomial p-value
+    pub p_value: f64,
+}
+
+/// Result of comparing observed counts for an attribute with the deck's composition
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatsReport {
+    pub attribute: String,
+    /// Total number of tallies (cards, or attribute values on cards)
+    pub total: i32,
+    pub chi_square: f64,
+    pub degrees_of_freedom: usize,
+    pub p_value: f64,
+    /// Smallest expected count in any category
+    pub min_expected: f64,
+    /// Categories that deviate significantly, after a Bonferroni correction
+    pub deviations: Vec<Deviation>,
+}
+
+/// How often each value of an attribute appears across the whole deck
+///
+/// The `card` attribute type counts each card once.
+pub fn deck_counts(attribute: &str) -> HashMap<String, i32> {
+    deck_counts_of(&tarot_cards::get_all_cards(), attribute)
+}
+
+fn deck_counts_of(deck: &[&TarotCard], attribute: &str) -> HashMap<String, i32> {
+    let mut counts = HashMap::new();
+
+    for card in deck {
+        if attribute == "card" {
+            *counts.entry(card.card.clone()).or_default() += 1;
+            continue;
+        }
+
+        for value in card.attribute_values(attribute) {
+            *counts.entry(value.to_string()).or_default() += 1;
+        }
+    }
+
+    counts
+}
+
+/// Count an attribute over all cards drawn in a user's readings
+///
+/// The `card` attribute type counts the cards themselves.
+pub fn observed_counts(readings: &[ReadingHistoryItem], attribute: &str) -> HashMap<String, i32> {
+    let mut counts = HashMap::new();
+
+    for drawn in readings.iter().flat_map(|r| &r.cards) {
+        if attribute == "card" {
+            *counts.entry(drawn.card_name.clone()).or_default() += 1;
+        } else if let Some(card) = tarot_cards::find_card(&drawn.card_name) {
+            for value in card.attribute_values(attribute) {
+                *counts.entry(value.to_string()).or_default() += 1;
+            }
+        }
+    }
+
+    counts
+}
+
+/// Compare observed counts with the proportions in `deck`
+///
+/// Values missing from the deck are ignored, since the deck can't produce them.
+pub fn compare(
+    attribute: &str,
+    observed: &HashMap<String, i32>,
+    deck: &HashMap<String, i32>,
+) -> StatsReport {
+    let deck_total: i32 = deck.values().sum();
+    let total: i32 = deck.keys().map(|value| observed.get(value).copied().unwrap_or(0)).sum();
+
+    // Sort categories so reports come out in a stable order
+    let deck: BTreeMap<&String, i32> = deck.iter().map(|(value, &count)| (value, count)).collect();
+    let alpha = ALPHA / deck.len().max(1) as f64;
+
+    let mut chi_square = 0.0;
+    let mut min_expected = f64::INFINITY;
+    let mut deviations = Vec::new();
+
+    for (value, count) in &deck {
+        let p = *count as f64 / deck_total as f64;
+        let expected = total as f64 * p;
+        let observed = observed.get(*value).copied().unwrap_or(0);
+
+        min_expected = min_expected.min(expected);
+        if expected > 0.0 {
+            chi_square += (observed as f64 - expected).powi(2) / expected;
+        }
+
+        let p_value = binomial_test(observed as u64, total as u64, p);
+        if p_value < alpha {
+            let value = value.to_string();
+            deviations.push(Deviation { value, observed, expected, p_value });
+        }
+    }
+
+    deviations.sort_by(|a, b| a.p_value.total_cmp(&b.p_value));
+
+    let degrees_of_freedom = deck.len().saturating_sub(1).max(1);
+
+    StatsReport {
+        attribute: attribute.to_string(),
+        total,
+        chi_square,
+        degrees_of_freedom,
+        p_value: chi_square_p_value(chi_square, degrees_of_freedom),
+        min_expected: if min_expected.is_finite() {
+            min_expected
+        } else {
+            0.0
+        },
+        deviations,
+    }
+}
+
+/// Compare a user's draws for an attribute (or `card`) with the full deck
+pub fn compare_with_deck(readings: &[ReadingHistoryItem], attribute: &str) -> StatsReport {
+    compare(attribute, &observed_counts(readings, attribute), &deck_counts(attribute))
+}
+
+impl StatsReport {
+    /// Whether there were enough draws for the chi-square test to be trusted
+    pub fn is_reliable(&self) -> bool {
+        self.min_expected >= MIN_EXPECTED
+    }
+
+    /// Draws needed before every category is expected at least `MIN_EXPECTED` times
+    fn draws_needed(&self) -> i32 {
+        if self.min_expected <= 0.0 {
+            return 0;
+        }
+
+        (MIN_EXPECTED / self.min_expected * self.total as f64).ceil() as i32
+    }
+
+    /// A one-line verdict, for the summary
+    pub fn verdict(&self) -> String {
+        if self.total == 0 {
+            return "no draws yet".to_string();
+        }
+
+        if !self.is_reliable() {
+            return format!(
+                "too few draws to judge ({} so far; about {} needed)",
+                self.total,
+                self.draws_needed()
+            );
+        }
+
+        match self.deviations.first() {
+            None => format!("in line with chance (p = {:.2}, {} draws)", self.p_value, self.total),
+            Some(top) => {
+                format!(
+                    "{} drawn {} times vs {:.1} expected (p = {}, {} draws)",
+                    top.value,
+                    top.observed,
+                    top.expected,
+                    format_p(top.p_value),
+                    self.total
+                )
+            },
+        }
+    }
+
+    /// A full account of the test, for the attribute graphs
+    pub fn to_text(&self) -> String {
+        let mut out = format!("**Compared with the deck ({} draws)**\n", self.total);
+
+        out.push_str(&format!(
+            "Chi-square = {:.2} with {} degrees of freedom, p = {}\n",
+            self.chi_square,
+            self.degrees_of_freedom,
+            format_p(self.p_value)
+        ));
+
+        if !self.is_reliable() {
+            out.push_str(&format!(
+                "Some {} values are expected fewer than {} times, so these numbers are unreliable. \
+                 Around {} draws are needed before a lopsided count means much.\n",
+                self.attribute,
+                MIN_EXPECTED,
+                self.draws_needed()
+            ));
+        }
+
+        if self.deviations.is_empty() {
+            out.push_str("No value deviates from the deck by more than chance would explain.\n");
+        } else {
+            out.push_str("Deviations unlikely to be chance (binomial test, corrected for the number of values):\n");
+            for dev in &self.deviations {
+                let direction = if dev.observed as f64 > dev.expected {
+                    "more"
+                } else {
+                    "fewer"
+                };
+                out.push_str(&format!(
+                    "  {} - {} drawn, {:.1} expected ({} than chance, p = {})\n",
+                    dev.value,
+                    dev.observed,
+                    dev.expected,
+                    direction,
+                    format_p(dev.p_value)
+                ));
+            }
+        }
+
+        out
+    }
+}
+
+fn format_p(p: f64) -> String {
+    if p < 0.001 {
+        "< 0.001".to_string()
+    } else {
+        format!("{:.3}", p)
+    }
+}
+
+/// Natural log of the gamma function, using the Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection formula
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |acc, (i, c)| acc + c / (x + i as f64 + 1.0));
+
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// Regularized upper incomplete gamma function Q(a, x)
+fn gamma_q(a: f64, x: f64) -> f64 {
+    const EPSILON: f64 = 1e-14;
+    const MAX_ITERATIONS: usize = 1000;
+
+    if x <= 0.0 {
+        return 1.0;
+    }
+
+    let ln_prefix = a * x.ln() - x - ln_gamma(a);
+
+    if x < a + 1.0 {
+        // Series for P(a, x)
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..MAX_ITERATIONS {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+
+        1.0 - sum * ln_prefix.exp()
+    } else {
+        // Continued fraction for Q(a, x), by Lentz's method
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..MAX_ITERATIONS {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+
+        ln_prefix.exp() * h
+    }
+}
+
+/// Probability of a chi-square statistic at least this large by chance
+pub fn chi_square_p_value(chi_square: f64, degrees_of_freedom: usize) -> f64 {
+    gamma_q(degrees_of_freedom as f64 / 2.0, chi_square / 2.0).clamp(0.0, 1.0)
+}
+
+/// Two-sided exact binomial test of `k` successes in `n` trials with success probability `p`
+///
+/// Sums the probability of every outcome no more likely than the one observed.
+pub fn binomial_test(k: u64, n: u64, p: f64) -> f64 {
+    if n == 0 || p <= 0.0 || p >= 1.0 {
+        return 1.0;
+    }
+
+    let ln_pmf = |i: u64| {
+        let (i, n) = (i as f64, n as f64);
+        ln_gamma(n + 1.0) - ln_gamma(i + 1.0) - ln_gamma(n - i + 1.0) +
+            i * p.ln() +
+            (n - i) * (1.0 - p).ln()
+    };
+
+    // Allow for rounding when comparing outcomes as likely as the observed one
+    let threshold = ln_pmf(k) + 1e-7;
+    let total: f64 = (0..=n).map(ln_pmf).filter(|&lp| lp <= threshold).map(f64::exp).sum();
+
+    total.min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(pairs: &[(&str, i32)]) -> HashMap<String, i32> {
+        pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_ln_gamma() {
+        assert_close(ln_gamma(1.0), 0.0);
+        assert_close(ln_gamma(5.0), 24f64.ln());
+        assert_close(ln_gamma(0.5), std::f64::consts::PI.sqrt().ln());
+    }
+
+    #[test]
+    fn test_chi_square_p_value() {
+        // Critical values from standard tables
+        assert_close(chi_square_p_value(3.841, 1), 0.05);
+        assert_close(chi_square_p_value(7.815, 3), 0.05);
+        assert_close(chi_square_p_value(18.307, 10), 0.05);
+        assert_close(chi_square_p_value(0.0, 4), 1.0);
+    }
+
+    #[test]
+    fn test_binomial_test() {
+        // 9 heads in 10 fair flips: P = 22/1024
+        assert_close(binomial_test(9, 10, 0.5), 22.0 / 1024.0);
+        assert_close(binomial_test(5, 10, 0.5), 1.0);
+        assert_eq!(binomial_test(0, 0, 0.5), 1.0);
+    }
+
+    #[test]
+    fn test_deck_counts() {
+        let suits = deck_counts("suit");
+        assert_eq!(suits.len(), 4);
+        // Fourteen Swords, plus three courts whose second suit is Swords
+        assert_eq!(suits["Swords"], 17);
+
+        let cards = deck_counts("card");
+        assert_eq!(cards.len(), 78);
+        assert_eq!(cards.values().sum::<i32>(), 78);
+    }
+
+    #[test]
+    fn test_compare_balanced() {
+        let deck = counts(&[("Cups", 14), ("Disks", 14), ("Swords", 14), ("Wands", 14)]);
+        let observed = counts(&[("Cups", 25), ("Disks", 25), ("Swords", 26), ("Wands", 24)]);
+        let report = compare("suit", &observed, &deck);
+
+        assert_eq!(report.total, 100);
+        assert_eq!(report.degrees_of_freedom, 3);
+        assert!(report.is_reliable());
+        assert!(report.p_value > 0.9);
+        assert!(report.deviations.is_empty());
+        assert!(report.verdict().starts_with("in line with chance"));
+    }
+
+    #[test]
+    fn test_compare_lopsided() {
+        let deck = counts(&[("Cups", 14), ("Disks", 14), ("Swords", 14), ("Wands", 14)]);
+        let observed = counts(&[("Cups", 15), ("Disks", 15), ("Swords", 55), ("Wands", 15)]);
+        let report = compare("suit", &observed, &deck);
+
+        assert!(report.p_value < 0.001);
+        assert_eq!(report.deviations[0].value, "Swords");
+        assert_close(report.deviations[0].expected, 25.0);
+        assert!(report.verdict().starts_with("Swords drawn 55 times vs 25.0 expected"));
+        assert!(report
+            .to_text()
+            .contains("Swords - 55 drawn, 25.0 expected (more than chance"));
+    }
+
+    #[test]
+    fn test_compare_small_sample() {
+        let deck = counts(&[("Cups", 14), ("Disks", 14), ("Swords", 14), ("Wands", 14)]);
+        let observed = counts(&[("Swords", 3), ("Cups", 1)]);
+        let report = compare("suit", &observed, &deck);
+
+        assert!(!report.is_reliable());
+        assert_eq!(report.verdict(), "too few draws to judge (4 so far; about 20 needed)");
+        assert!(report.to_text().contains("unreliable"));
+
+        let report = compare("suit", &HashMap::new(), &deck);
+        assert_eq!(report.verdict(), "no draws yet");
+    }
+}
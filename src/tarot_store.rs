@@ -0,0 +1,933 @@
+//! Where readings are saved, and where the reading history and its analytics come from
+//!
+//! Every `:tarot` and `:tarothistory` command goes through a [ReadingStore], chosen with
+//! `tarot.history` in the config. The tarot API keeps readings on the server; the SQLite
+//! journal keeps them on this device, the memory store keeps them until iamb exits, and the
+//! Matrix store keeps them encrypted in the account's data, so they follow it to other devices.
+//! The other stores work out the analytics the server would from the saved readings.
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use matrix_sdk::ruma::api::client::config::{get_room_account_data, set_room_account_data};
+use matrix_sdk::ruma::api::client::error::ErrorKind;
+use matrix_sdk::ruma::events::{GlobalAccountDataEventType, RoomAccountDataEventType};
+use matrix_sdk::ruma::serde::{Base64, Raw};
+use matrix_sdk::ruma::{OwnedRoomId, OwnedUserId};
+use matrix_sdk::Client;
+use matrix_sdk_store_encryption::{EncryptedValueBase64, StoreCipher};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::runtime::Handle;
+
+use crate::config::{ApplicationSettings, HistoryBackend, Tarot};
+use crate::tarot_api::{
+    AnalyticsSummary,
+    AttributeFrequency,
+    CardDetail,
+    CardInReading,
+    HistoryResponse,
+    HttpStore,
+    ReadingCreate,
+    ReadingCreateResponse,
+    ReadingDetails,
+    ReadingHistoryItem,
+    SpreadTypeCount,
+    TopAttribute,
+};
+use crate::tarot_cards;
+use crate::tarot_stats::{self, ATTRIBUTE_TYPES};
+use crate::tarot_trends::{self, ReadingFilter};
+
+/// Values listed for each attribute type in the summary
+const TOP_ATTRIBUTES: usize = 3;
+
+/// The account data event that indexes the reading history's chunks
+pub const HISTORY_EVENT_TYPE: &str = "com.endlessperfect.tarot.history";
+
+/// The most reading JSON put in one chunk, leaving room under the homeserver's 64 KiB event
+/// limit for the encryption and base64 around it
+const CHUNK_BYTES: usize = 32 * 1024;
+
+/// Saves readings and answers questions about them
+///
+/// Methods block, so callers on the async runtime should use `spawn_blocking`.
+pub trait ReadingStore: Send + Sync {
+    /// Save a reading, returning the ID it was saved under
+    fn save(&self, reading: ReadingCreate) -> Result<ReadingCreateResponse, String>;
+
+    /// A user's readings, newest first
+    fn history(&self, matrix_id: &str) -> Result<HistoryResponse, String>;
+
+    /// A reading's cards with their meanings, and the attributes they count towards
+    fn details(&self, reading_id: i32) -> Result<ReadingDetails, String>;
+
+    /// How often each value of an attribute type has been drawn across a user's readings
+    fn attribute_frequency(
+        &self,
+        matrix_id: &str,
+        attribute_type: &str,
+    ) -> Result<AttributeFrequency, String>;
+
+    /// Totals of a user's readings, spreads and most drawn attributes
+    fn summary(&self, matrix_id: &str) -> Result<AnalyticsSummary, String>;
+}
+
+/// Open the store configured under `[settings.tarot]`
+pub fn open(settings: &ApplicationSettings) -> Result<Arc<dyn ReadingStore>, String> {
+    let store: Arc<dyn ReadingStore> = match settings.tunables.tarot.history {
+        HistoryBackend::Http => Arc::new(HttpStore::default()),
+        HistoryBackend::Sqlite => Arc::new(SqliteStore::open(&settings.tarot_history_db)?),
+        HistoryBackend::Memory => Arc::new(MemoryStore::default()),
+        HistoryBackend::Matrix => {
+            if settings.tunables.tarot.history_passphrase.is_none() {
+                return Err("tarot.history = \"matrix\" needs a tarot.history_passphrase".into());
+            }
+
+            // Replaced by a MatrixStore once iamb has logged in
+            Arc::new(AwaitingLogin)
+        },
+    };
+
+    Ok(store)
+}
+
+/// A reading saved by one of the local stores
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Entry {
+    reading_id: i32,
+    reading_date: String,
+    reading: ReadingCreate,
+}
+
+impl Entry {
+    /// Date a new reading, with the time it was saved unless it is being imported
+    fn new(reading_id: i32, reading: ReadingCreate) -> Self {
+        let reading_date = reading
+            .reading_date
+            .clone()
+            .unwrap_or_else(|| chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string());
+
+        Entry { reading_id, reading_date, reading }
+    }
+
+    fn history_item(&self) -> ReadingHistoryItem {
+        let reading = &self.reading;
+        let cards = reading
+            .cards
+            .iter()
+            .map(|card| {
+                CardInReading {
+                    position: card.position,
+                    card_name: card.card_name.clone(),
+                    label: card.card_label.clone(),
+                    reversed: card.is_reversed,
+                    dignity: card.dignity.clone(),
+                }
+            })
+            .collect();
+
+        ReadingHistoryItem {
+            reading_id: self.reading_id,
+            spread_type: reading.spread_type.clone(),
+            reading_date: self.reading_date.clone(),
+            card_count: reading.cards.len(),
+            cards,
+            notes: reading.notes.clone(),
+            question: reading.question.clone(),
+            room_id: reading.room_id.clone(),
+            quintessence: reading.quintessence.clone(),
+            moon_phase: reading.moon_phase.clone(),
+            sun_sign: reading.sun_sign.clone(),
+        }
+    }
+
+    fn details(&self) -> ReadingDetails {
+        let reading = &self.reading;
+        let cards = reading
+            .cards
+            .iter()
+            .map(|card| {
+                let found = tarot_cards::find_card(&card.card_name);
+
+                CardDetail {
+                    position: card.position,
+                    card_name: card.card_name.clone(),
+                    label: card.card_label.clone(),
+                    info: found.and_then(|c| c.info.clone()),
+                    deepinfo: found.and_then(|c| c.deepinfo.clone()),
+                    dignity: card.dignity.clone(),
+                }
+            })
+            .collect();
+
+        ReadingDetails {
+            spread_type: reading.spread_type.clone(),
+            reading_date: self.reading_date.clone(),
+            notes: reading.notes.clone(),
+            question: reading.question.clone(),
+            numerology: reading.numerology.clone(),
+            cards,
+        }
+    }
+}
+
+/// A user's readings, newest first
+fn history_of(entries: &[Entry]) -> HistoryResponse {
+    let mut readings: Vec<ReadingHistoryItem> = entries.iter().map(Entry::history_item).collect();
+    readings.sort_by(|a, b| (&b.reading_date, b.reading_id).cmp(&(&a.reading_date, a.reading_id)));
+
+    HistoryResponse { total_readings: readings.len(), readings }
+}
+
+fn attribute_frequency_of(entries: &[Entry], attribute_type: &str) -> AttributeFrequency {
+    let readings = history_of(entries).readings;
+    let (frequencies, percentages) =
+        tarot_trends::attribute_frequencies(&readings, attribute_type, &ReadingFilter::default());
+
+    AttributeFrequency {
+        total_count: frequencies.values().sum(),
+        frequencies,
+        percentages,
+    }
+}
+
+/// Counts sorted from most to least common, then by name
+fn ranked(counts: HashMap<String, i32>) -> Vec<(String, i32)> {
+    let mut ranked: Vec<(String, i32)> = counts.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked
+}
+
+fn summary_of(entries: &[Entry]) -> AnalyticsSummary {
+    let readings = history_of(entries).readings;
+
+    let mut spreads = HashMap::new();
+    for reading in &readings {
+        *spreads.entry(reading.spread_type.clone()).or_default() += 1;
+    }
+
+    let spread_types = ranked(spreads)
+        .into_iter()
+        .map(|(spread_type, count)| SpreadTypeCount { spread_type, count })
+        .collect();
+
+    let top_attributes = ATTRIBUTE_TYPES
+        .iter()
+        .map(|attr| {
+            let top = ranked(tarot_stats::observed_counts(&readings, attr))
+                .into_iter()
+                .take(TOP_ATTRIBUTES)
+                .map(|(value, count)| TopAttribute { value, count })
+                .collect();
+
+            (attr.to_string(), top)
+        })
+        .collect();
+
+    AnalyticsSummary {
+        total_readings: readings.len() as i32,
+        total_cards_drawn: readings.iter().map(|r| r.card_count as i32).sum(),
+        spread_types,
+        top_attributes,
+    }
+}
+
+/// Readings kept only until iamb exits, for trying things out and for tests
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    entries: Mutex<Vec<Entry>>,
+}
+
+impl MemoryStore {
+    fn entries_of(&self, matrix_id: &str) -> Vec<Entry> {
+        let entries = self.entries.lock().unwrap();
+
+        entries
+            .iter()
+            .filter(|e| e.reading.matrix_id == matrix_id)
+            .cloned()
+            .collect()
+    }
+}
+
+impl ReadingStore for MemoryStore {
+    fn save(&self, reading: ReadingCreate) -> Result<ReadingCreateResponse, String> {
+        let mut entries = self.entries.lock().unwrap();
+        let reading_id = entries.len() as i32 + 1;
+        entries.push(Entry::new(reading_id, reading));
+
+        Ok(ReadingCreateResponse { reading_id })
+    }
+
+    fn history(&self, matrix_id: &str) -> Result<HistoryResponse, String> {
+        Ok(history_of(&self.entries_of(matrix_id)))
+    }
+
+    fn details(&self, reading_id: i32) -> Result<ReadingDetails, String> {
+        let entries = self.entries.lock().unwrap();
+
+        entries
+            .iter()
+            .find(|e| e.reading_id == reading_id)
+            .map(Entry::details)
+            .ok_or_else(|| format!("No reading with ID {}", reading_id))
+    }
+
+    fn attribute_frequency(
+        &self,
+        matrix_id: &str,
+        attribute_type: &str,
+    ) -> Result<AttributeFrequency, String> {
+        Ok(attribute_frequency_of(&self.entries_of(matrix_id), attribute_type))
+    }
+
+    fn summary(&self, matrix_id: &str) -> Result<AnalyticsSummary, String> {
+        Ok(summary_of(&self.entries_of(matrix_id)))
+    }
+}
+
+/// Readings kept in a SQLite journal in the profile's data directory
+///
+/// Each reading is stored as it was saved, as JSON, so the journal reads back exactly what
+/// the server would have been sent.
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+fn sql_error(e: rusqlite::Error) -> String {
+    format!("Reading journal error: {}", e)
+}
+
+impl SqliteStore {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+
+        let conn = Connection::open(path).map_err(sql_error)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS readings (
+                reading_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                matrix_id TEXT NOT NULL,
+                reading_date TEXT NOT NULL,
+                reading TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS readings_by_user ON readings (matrix_id);",
+        )
+        .map_err(sql_error)?;
+
+        Ok(SqliteStore { conn: Mutex::new(conn) })
+    }
+
+    fn entry(reading_id: i32, reading_date: String, json: &str) -> Result<Entry, String> {
+        let reading = serde_json::from_str(json)
+            .map_err(|e| format!("Reading {} in the journal is corrupt: {}", reading_id, e))?;
+
+        Ok(Entry { reading_id, reading_date, reading })
+    }
+
+    fn entries_of(&self, matrix_id: &str) -> Result<Vec<Entry>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT reading_id, reading_date, reading FROM readings WHERE matrix_id = ?1")
+            .map_err(sql_error)?;
+        let rows = stmt
+            .query_map(params![matrix_id], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get::<_, String>(2)?))
+            })
+            .map_err(sql_error)?;
+
+        rows.map(|row| {
+            let (reading_id, reading_date, json) = row.map_err(sql_error)?;
+            SqliteStore::entry(reading_id, reading_date, &json)
+        })
+        .collect()
+    }
+}
+
+impl ReadingStore for SqliteStore {
+    fn save(&self, reading: ReadingCreate) -> Result<ReadingCreateResponse, String> {
+        let json = serde_json::to_string(&reading).map_err(|e| e.to_string())?;
+        let entry = Entry::new(0, reading);
+
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO readings (matrix_id, reading_date, reading) VALUES (?1, ?2, ?3)",
+            params![entry.reading.matrix_id, entry.reading_date, json],
+        )
+        .map_err(sql_error)?;
+
+        Ok(ReadingCreateResponse { reading_id: conn.last_insert_rowid() as i32 })
+    }
+
+    fn history(&self, matrix_id: &str) -> Result<HistoryResponse, String> {
+        Ok(history_of(&self.entries_of(matrix_id)?))
+    }
+
+    fn details(&self, reading_id: i32) -> Result<ReadingDetails, String> {
+        let conn = self.conn.lock().unwrap();
+        let row: Option<(String, String)> = conn
+            .query_row(
+                "SELECT reading_date, reading FROM readings WHERE reading_id = ?1",
+                params![reading_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(sql_error)?;
+
+        let (reading_date, json) =
+            row.ok_or_else(|| format!("No reading with ID {}", reading_id))?;
+
+        Ok(SqliteStore::entry(reading_id, reading_date, &json)?.details())
+    }
+
+    fn attribute_frequency(
+        &self,
+        matrix_id: &str,
+        attribute_type: &str,
+    ) -> Result<AttributeFrequency, String> {
+        Ok(attribute_frequency_of(&self.entries_of(matrix_id)?, attribute_type))
+    }
+
+    fn summary(&self, matrix_id: &str) -> Result<AnalyticsSummary, String> {
+        Ok(summary_of(&self.entries_of(matrix_id)?))
+    }
+}
+
+/// Stands in for the Matrix store until there is a logged in client to build it with
+struct AwaitingLogin;
+
+impl AwaitingLogin {
+    fn error<T>() -> Result<T, String> {
+        Err("The reading history in Matrix account data is unavailable until it has been \
+             opened after logging in (see the log for why it couldn't be)"
+            .into())
+    }
+}
+
+impl ReadingStore for AwaitingLogin {
+    fn save(&self, _: ReadingCreate) -> Result<ReadingCreateResponse, String> {
+        AwaitingLogin::error()
+    }
+
+    fn history(&self, _: &str) -> Result<HistoryResponse, String> {
+        AwaitingLogin::error()
+    }
+
+    fn details(&self, _: i32) -> Result<ReadingDetails, String> {
+        AwaitingLogin::error()
+    }
+
+    fn attribute_frequency(&self, _: &str, _: &str) -> Result<AttributeFrequency, String> {
+        AwaitingLogin::error()
+    }
+
+    fn summary(&self, _: &str) -> Result<AnalyticsSummary, String> {
+        AwaitingLogin::error()
+    }
+}
+
+/// Where the history lives in account data: a room's, or the whole account's
+struct AccountData {
+    client: Client,
+    room_id: Option<OwnedRoomId>,
+}
+
+impl AccountData {
+    fn user_id(&self) -> Result<OwnedUserId, matrix_sdk::Error> {
+        let user_id = self.client.user_id().ok_or(matrix_sdk::Error::AuthenticationRequired)?;
+
+        Ok(user_id.to_owned())
+    }
+
+    /// Fetch an event's content from the homeserver, or `None` if it hasn't been set
+    async fn get<T: DeserializeOwned>(&self, event_type: &str) -> Result<Option<T>, String> {
+        let error = |e| format!("Failed to read {} from account data: {}", event_type, e);
+
+        let raw = match &self.room_id {
+            Some(room_id) => {
+                let user_id = self.user_id().map_err(error)?;
+                let event_type = RoomAccountDataEventType::from(event_type);
+                let room_id = room_id.clone();
+                let request = get_room_account_data::v3::Request::new(user_id, room_id, event_type);
+
+                match self.client.send(request).await {
+                    Ok(response) => Some(response.account_data.cast()),
+                    Err(e) if e.client_api_error_kind() == Some(&ErrorKind::NotFound) => None,
+                    Err(e) => return Err(error(e.into())),
+                }
+            },
+            None => {
+                let event_type = GlobalAccountDataEventType::from(event_type);
+                let account = self.client.account();
+                account.fetch_account_data(event_type).await.map_err(error)?.map(Raw::cast)
+            },
+        };
+
+        raw.map(|raw: Raw<serde_json::Value>| {
+            raw.deserialize_as::<T>()
+                .map_err(|e| format!("Failed to read {} from account data: {}", event_type, e))
+        })
+        .transpose()
+    }
+
+    async fn put<T: Serialize>(&self, event_type: &str, content: &T) -> Result<(), String> {
+        let error = |e| format!("Failed to write {} to account data: {}", event_type, e);
+        let content = Raw::new(content).map_err(|e| e.to_string())?;
+
+        match &self.room_id {
+            Some(room_id) => {
+                let user_id = self.user_id().map_err(error)?;
+                let event_type = RoomAccountDataEventType::from(event_type);
+                let request = set_room_account_data::v3::Request::new_raw(
+                    user_id,
+                    room_id.clone(),
+                    event_type,
+                    content.cast(),
+                );
+
+                self.client.send(request).await.map_err(|e| error(e.into()))?;
+            },
+            None => {
+                let event_type = GlobalAccountDataEventType::from(event_type);
+                self.client
+                    .account()
+                    .set_account_data_raw(event_type, content.cast())
+                    .await
+                    .map_err(error)?;
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// The index stored under [HISTORY_EVENT_TYPE]
+#[derive(Debug, Deserialize, Serialize)]
+struct HistoryIndex {
+    /// The key the chunks are encrypted with, itself encrypted with the passphrase
+    key: Base64,
+    /// How many chunks there are, numbered from 0
+    chunks: usize,
+    /// The ID the next reading will be saved under
+    next_id: i32,
+}
+
+/// A chunk of readings, as stored in account data
+#[derive(Debug, Deserialize, Serialize)]
+struct EncryptedChunk {
+    encrypted: EncryptedValueBase64,
+}
+
+fn chunk_event_type(chunk: usize) -> String {
+    format!("{}.{}", HISTORY_EVENT_TYPE, chunk)
+}
+
+/// The readings as of the last time the index was read
+#[derive(Default)]
+struct Journal {
+    next_id: i32,
+    chunks: Vec<Vec<Entry>>,
+}
+
+/// Readings kept in the Matrix account's data, so that they follow it across devices
+///
+/// Readings are packed into chunks of [CHUNK_BYTES], each encrypted and stored as its own
+/// event, alongside an index under [HISTORY_EVENT_TYPE]. The index is read before every
+/// operation, so that readings saved on other devices are picked up, and a chunk is always
+/// written before the index that counts it.
+pub struct MatrixStore {
+    data: AccountData,
+    runtime: Handle,
+    cipher: StoreCipher,
+    key: Base64,
+    journal: Mutex<Journal>,
+}
+
+impl MatrixStore {
+    /// Open the history in the account data of `tarot.history_room`, or of the account,
+    /// starting a new one if there isn't one yet
+    pub async fn open(client: Client, tarot: &Tarot) -> Result<Self, String> {
+        let passphrase = tarot
+            .history_passphrase
+            .as_deref()
+            .ok_or("tarot.history = \"matrix\" needs a tarot.history_passphrase")?;
+        let data = AccountData { client, room_id: tarot.history_room.clone() };
+
+        let (cipher, key) = match data.get::<HistoryIndex>(HISTORY_EVENT_TYPE).await? {
+            Some(index) => {
+                let cipher =
+                    StoreCipher::import(passphrase, index.key.as_bytes()).map_err(|_| {
+                        "Can't unlock the reading history; is tarot.history_passphrase right?"
+                    })?;
+
+                (cipher, index.key)
+            },
+            None => {
+                let cipher = StoreCipher::new().map_err(|e| e.to_string())?;
+                let key = Base64::new(cipher.export(passphrase).map_err(|e| e.to_string())?);
+                let index = HistoryIndex { key, chunks: 0, next_id: 1 };
+                data.put(HISTORY_EVENT_TYPE, &index).await?;
+
+                (cipher, index.key)
+            },
+        };
+
+        let store = MatrixStore {
+            data,
+            runtime: Handle::current(),
+            cipher,
+            key,
+            journal: Mutex::default(),
+        };
+
+        Ok(store)
+    }
+
+    /// Wait on a request to the homeserver from the blocking [ReadingStore] methods
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        if Handle::try_current().is_ok() {
+            tokio::task::block_in_place(|| self.runtime.block_on(future))
+        } else {
+            self.runtime.block_on(future)
+        }
+    }
+
+    fn read_chunk(&self, chunk: usize) -> Result<Vec<Entry>, String> {
+        let event_type = chunk_event_type(chunk);
+        let stored = self
+            .block_on(self.data.get::<EncryptedChunk>(&event_type))?
+            .ok_or_else(|| format!("Chunk {} of the reading history is missing", chunk))?;
+        let json = self
+            .cipher
+            .decrypt_value_base64_data(stored.encrypted)
+            .map_err(|e| format!("Failed to decrypt chunk {} of the history: {}", chunk, e))?;
+
+        serde_json::from_slice(&json)
+            .map_err(|e| format!("Chunk {} of the reading history is corrupt: {}", chunk, e))
+    }
+
+    fn write_chunk(&self, chunk: usize, entries: &[Entry]) -> Result<(), String> {
+        let json = serde_json::to_vec(entries).map_err(|e| e.to_string())?;
+        let encrypted = self.cipher.encrypt_value_base64_data(json).map_err(|e| e.to_string())?;
+
+        self.block_on(self.data.put(&chunk_event_type(chunk), &EncryptedChunk { encrypted }))
+    }
+
+    /// Bring the journal up to date with the index, reloading it if another device has saved
+    fn refresh(&self, journal: &mut Journal) -> Result<(), String> {
+        let index = self
+            .block_on(self.data.get::<HistoryIndex>(HISTORY_EVENT_TYPE))?
+            .ok_or("The reading history has been removed from account data")?;
+
+        if index.next_id == journal.next_id && index.chunks == journal.chunks.len() {
+            return Ok(());
+        }
+
+        let chunks = (0..index.chunks).map(|n| self.read_chunk(n)).collect::<Result<_, _>>()?;
+        *journal = Journal { next_id: index.next_id, chunks };
+
+        Ok(())
+    }
+
+    fn entries(&self) -> Result<Vec<Entry>, String> {
+        let mut journal = self.journal.lock().unwrap();
+        self.refresh(&mut journal)?;
+
+        Ok(journal.chunks.iter().flatten().cloned().collect())
+    }
+
+    fn entries_of(&self, matrix_id: &str) -> Result<Vec<Entry>, String> {
+        let mut entries = self.entries()?;
+        entries.retain(|e| e.reading.matrix_id == matrix_id);
+
+        Ok(entries)
+    }
+
+    /// Add an entry to the last chunk, or to a new one if it is full, and count it in the index
+    ///
+    /// An entry too large to fit in a chunk of its own is refused rather than sent as an event
+    /// the homeserver may reject.
+    fn append(&self, journal: &mut Journal, entry: Entry) -> Result<(), String> {
+        let size = |entries: &[Entry]| serde_json::to_vec(entries).map_or(0, |json| json.len());
+        if size(std::slice::from_ref(&entry)) > CHUNK_BYTES {
+            return Err(format!(
+                "This reading is too large to keep in Matrix account data (over {} KiB)",
+                CHUNK_BYTES / 1024
+            ));
+        }
+
+        let fits = journal.chunks.last().is_some_and(|last| {
+            let mut grown = last.clone();
+            grown.push(entry.clone());
+            size(&grown) <= CHUNK_BYTES
+        });
+
+        if !fits {
+            journal.chunks.push(vec![]);
+        }
+
+        let chunk = journal.chunks.len() - 1;
+        journal.chunks[chunk].push(entry);
+        journal.next_id += 1;
+
+        self.write_chunk(chunk, &journal.chunks[chunk])?;
+
+        let index = HistoryIndex {
+            key: self.key.clone(),
+            chunks: journal.chunks.len(),
+            next_id: journal.next_id,
+        };
+
+        self.block_on(self.data.put(HISTORY_EVENT_TYPE, &index))
+    }
+}
+
+impl ReadingStore for MatrixStore {
+    fn save(&self, reading: ReadingCreate) -> Result<ReadingCreateResponse, String> {
+        let mut journal = self.journal.lock().unwrap();
+        self.refresh(&mut journal)?;
+
+        let reading_id = journal.next_id;
+        if let Err(e) = self.append(&mut journal, Entry::new(reading_id, reading)) {
+            // Reload from the homeserver next time, rather than trust a half-made save
+            *journal = Journal::default();
+            return Err(e);
+        }
+
+        Ok(ReadingCreateResponse { reading_id })
+    }
+
+    fn history(&self, matrix_id: &str) -> Result<HistoryResponse, String> {
+        Ok(history_of(&self.entries_of(matrix_id)?))
+    }
+
+    fn details(&self, reading_id: i32) -> Result<ReadingDetails, String> {
+        self.entries()?
+            .iter()
+            .find(|e| e.reading_id == reading_id)
+            .map(Entry::details)
+            .ok_or_else(|| format!("No reading with ID {}", reading_id))
+    }
+
+    fn attribute_frequency(
+        &self,
+        matrix_id: &str,
+        attribute_type: &str,
+    ) -> Result<AttributeFrequency, String> {
+        Ok(attribute_frequency_of(&self.entries_of(matrix_id)?, attribute_type))
+    }
+
+    fn summary(&self, matrix_id: &str) -> Result<AnalyticsSummary, String> {
+        Ok(summary_of(&self.entries_of(matrix_id)?))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::tarot_api::CardData;
+    use crate::tests::homeserver::{Homeserver, MAX_EVENT_BYTES};
+    use matrix_sdk::ruma::RoomId;
+    use temp_dir::TempDir;
+
+    pub const USER: &str = "@querent:example.com";
+
+    /// A reading of the given cards, drawn at noon on the given day
+    pub fn reading(matrix_id: &str, date: &str, cards: &[&str]) -> ReadingCreate {
+        let cards: Vec<CardData> = cards
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                CardData {
+                    position: i as i32,
+                    card_name: name.to_string(),
+                    card_label: Some(format!("Card {}", i + 1)),
+                    is_reversed: false,
+                    dignity: None,
+                }
+            })
+            .collect();
+
+        ReadingCreate {
+            matrix_id: matrix_id.to_string(),
+            room_id: None,
+            spread_type: cards.len().to_string(),
+            cards,
+            notes: None,
+            question: Some("Should I take the job?".into()),
+            reading_date: Some(format!("{}T12:00:00", date)),
+            quintessence: None,
+            numerology: None,
+            moon_phase: None,
+            sun_sign: None,
+            is_private: false,
+        }
+    }
+
+    /// Exercise a store the same way whatever it keeps readings in
+    fn check_store(store: &dyn ReadingStore) {
+        let first = store
+            .save(reading(USER, "2026-03-01", &["Six of Swords", "The Star"]))
+            .unwrap();
+        let second = store.save(reading(USER, "2026-04-01", &["Two of Swords"])).unwrap();
+        store
+            .save(reading("@other:example.com", "2026-05-01", &["The Fool"]))
+            .unwrap();
+        assert_ne!(first.reading_id, second.reading_id);
+
+        let history = store.history(USER).unwrap();
+        assert_eq!(history.total_readings, 2);
+        assert_eq!(history.readings[0].reading_id, second.reading_id);
+        assert_eq!(history.readings[1].cards[1].card_name, "The Star");
+        assert_eq!(history.readings[1].card_count, 2);
+        assert!(store.history("@nobody:example.com").unwrap().readings.is_empty());
+
+        let details = store.details(first.reading_id).unwrap();
+        assert_eq!(details.reading_date, "2026-03-01T12:00:00");
+        assert_eq!(details.cards[0].label.as_deref(), Some("Card 1"));
+        assert!(details.cards[0].info.as_deref().unwrap().contains("Journey by water"));
+        assert!(store.details(999).is_err());
+
+        let freq = store.attribute_frequency(USER, "suit").unwrap();
+        assert_eq!(freq.frequencies["Swords"], 2);
+        assert_eq!(freq.total_count, freq.frequencies.values().sum::<i32>());
+
+        let summary = store.summary(USER).unwrap();
+        assert_eq!(summary.total_readings, 2);
+        assert_eq!(summary.total_cards_drawn, 3);
+        assert_eq!(summary.spread_types[0].spread_type, "1");
+        assert_eq!(summary.top_attributes["suit"][0].value, "Swords");
+    }
+
+    #[test]
+    fn test_memory_store() {
+        check_store(&MemoryStore::default());
+    }
+
+    #[test]
+    fn test_sqlite_store() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("profile").join("tarot_history.sqlite3");
+
+        check_store(&SqliteStore::open(&path).unwrap());
+
+        // Readings are still there when the journal is opened again
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.history(USER).unwrap().total_readings, 2);
+    }
+
+    #[test]
+    fn test_new_readings_are_dated() {
+        let store = MemoryStore::default();
+        let mut new = reading(USER, "2026-03-01", &["The Fool"]);
+        new.reading_date = None;
+        store.save(new).unwrap();
+
+        let history = store.history(USER).unwrap();
+        let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        assert!(history.readings[0].reading_date.starts_with(&today));
+    }
+
+    fn matrix_settings(room_id: Option<&str>) -> Tarot {
+        Tarot {
+            history: HistoryBackend::Matrix,
+            history_room: room_id.map(|id| RoomId::parse(id).unwrap()),
+            history_passphrase: Some("correct horse battery staple".into()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_matrix_store() {
+        let server = Homeserver::start();
+        let settings = matrix_settings(None);
+        let store = MatrixStore::open(server.client(USER).await, &settings).await.unwrap();
+
+        check_store(&store);
+
+        // Readings are stored encrypted, in the account's data
+        let chunk = chunk_event_type(0);
+        assert_eq!(server.event_types(None), vec![HISTORY_EVENT_TYPE.to_string(), chunk.clone()]);
+        let stored = server.account_data(None, &chunk).unwrap().to_string();
+        assert!(!stored.contains("Six of Swords"));
+
+        // Another device sees the same history, and what it saves
+        let other = MatrixStore::open(server.client(USER).await, &settings).await.unwrap();
+        assert_eq!(other.history(USER).unwrap().total_readings, 2);
+        let saved = other.save(reading(USER, "2026-06-01", &["The Sun"])).unwrap();
+        assert_eq!(saved.reading_id, 4);
+
+        let history = store.history(USER).unwrap();
+        assert_eq!(history.total_readings, 3);
+        assert_eq!(history.readings[0].cards[0].card_name, "The Sun");
+
+        // The history can't be opened without the passphrase
+        let wrong = Tarot {
+            history_passphrase: Some("hunter2".into()),
+            ..settings
+        };
+        let res = MatrixStore::open(server.client(USER).await, &wrong).await;
+        assert_eq!(
+            res.err().unwrap(),
+            "Can't unlock the reading history; is tarot.history_passphrase right?"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_matrix_store_chunks() {
+        let server = Homeserver::start();
+        let room_id = "!journal:example.com";
+        let settings = matrix_settings(Some(room_id));
+        let store = MatrixStore::open(server.client(USER).await, &settings).await.unwrap();
+
+        // Enough readings that no one event could hold them all
+        for day in 1..=20 {
+            let mut long = reading(USER, &format!("2026-03-{:02}", day), &["The Hermit"]);
+            long.notes = Some("A long night of journaling. ".repeat(200));
+            store.save(long).unwrap();
+        }
+
+        let history = store.history(USER).unwrap();
+        assert_eq!(history.total_readings, 20);
+        assert_eq!(history.readings[0].reading_date, "2026-03-20T12:00:00");
+        assert!(history.readings.iter().all(|r| r.notes.as_ref().unwrap().len() == 5600));
+
+        // The readings went to the room's account data, split across events under the limit
+        assert!(server.event_types(None).is_empty());
+        let index = server.account_data(Some(room_id), HISTORY_EVENT_TYPE).unwrap();
+        let chunks = index["chunks"].as_u64().unwrap() as usize;
+        assert!(chunks > 1);
+
+        for chunk in 0..chunks {
+            let stored = server.account_data(Some(room_id), &chunk_event_type(chunk)).unwrap();
+            assert!(stored.to_string().len() < MAX_EVENT_BYTES);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_matrix_store_oversized_reading() {
+        let server = Homeserver::start();
+        let settings = matrix_settings(None);
+        let store = MatrixStore::open(server.client(USER).await, &settings).await.unwrap();
+
+        store.save(reading(USER, "2026-03-01", &["The Star"])).unwrap();
+
+        let mut huge = reading(USER, "2026-03-02", &["The Tower"]);
+        huge.notes = Some("x".repeat(CHUNK_BYTES));
+        let err = store.save(huge).unwrap_err();
+        assert!(err.contains("too large"), "{}", err);
+
+        // Nothing was sent for it, and the next reading still saves
+        let index = server.account_data(None, HISTORY_EVENT_TYPE).unwrap();
+        assert_eq!(index["chunks"], 1);
+        assert_eq!(index["next_id"], 2);
+
+        store.save(reading(USER, "2026-03-03", &["The Sun"])).unwrap();
+        assert_eq!(store.history(USER).unwrap().total_readings, 2);
+    }
+}
@@ -0,0 +1,312 @@
+//! The Tree of Life: the ten sephiroth, the 22 paths between them and the cards on each
+//!
+//! Pips sit on the sephira of their number and trumps on their Hebrew-letter path, as
+//! given by each card's `sephira` and `path` ("15.2.6" is path 15, from Chokmah to
+//! Tiphareth). Courts sit where the Golden Dawn places them: Knights in Chokmah,
+//! Queens in Binah, Princes in Tiphareth and Princesses in Malkuth.
+use std::collections::BTreeMap;
+
+use image::{ImageBuffer, Rgba, RgbaImage};
+
+use crate::image_upload::ImageUpload;
+use crate::tarot_cards::{Court, Rank, TarotCard};
+use crate::tarot_composite::{draw_disc, draw_line};
+
+/// A sephira and where it sits on the tree
+///
+/// `x` runs from -1 (the Pillar of Severity) to 1 (the Pillar of Mercy), and `y` from 0
+/// at Kether down to 8 at Malkuth.
+pub struct Sephira {
+    pub number: u8,
+    pub name: &'static str,
+    pub x: f64,
+    pub y: f64,
+}
+
+/// A path between two sephiroth, numbered 11 to 32
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TreePath {
+    pub number: u8,
+    pub from: u8,
+    pub to: u8,
+}
+
+pub const SEPHIROTH: [Sephira; 10] = [
+    Sephira { number: 1, name: "Kether", x: 0.0, y: 0.0 },
+    Sephira { number: 2, name: "Chokmah", x: 1.0, y: 1.0 },
+    Sephira { number: 3, name: "Binah", x: -1.0, y: 1.0 },
+    Sephira { number: 4, name: "Chesed", x: 1.0, y: 3.0 },
+    Sephira { number: 5, name: "Geburah", x: -1.0, y: 3.0 },
+    Sephira { number: 6, name: "Tiphareth", x: 0.0, y: 4.0 },
+    Sephira { number: 7, name: "Netzach", x: 1.0, y: 5.0 },
+    Sephira { number: 8, name: "Hod", x: -1.0, y: 5.0 },
+    Sephira { number: 9, name: "Yesod", x: 0.0, y: 6.0 },
+    Sephira { number: 10, name: "Malkuth", x: 0.0, y: 8.0 },
+];
+
+const fn path(number: u8, from: u8, to: u8) -> TreePath {
+    TreePath { number, from, to }
+}
+
+pub const PATHS: [TreePath; 22] = [
+    path(11, 1, 2),
+    path(12, 1, 3),
+    path(13, 1, 6),
+    path(14, 2, 3),
+    path(15, 2, 6),
+    path(16, 2, 4),
+    path(17, 3, 6),
+    path(18, 3, 5),
+    path(19, 4, 5),
+    path(20, 4, 6),
+    path(21, 4, 7),
+    path(22, 5, 6),
+    path(23, 5, 8),
+    path(24, 6, 7),
+    path(25, 6, 9),
+    path(26, 6, 8),
+    path(27, 7, 8),
+    path(28, 7, 9),
+    path(29, 7, 10),
+    path(30, 8, 9),
+    path(31, 8, 10),
+    path(32, 9, 10),
+];
+
+/// Look up a sephira by its number
+pub fn sephira(number: u8) -> Option<&'static Sephira> {
+    SEPHIROTH.iter().find(|s| s.number == number)
+}
+
+/// Look up a sephira's number by name, ignoring case
+pub fn sephira_number(name: &str) -> Option<u8> {
+    SEPHIROTH
+        .iter()
+        .find(|s| s.name.eq_ignore_ascii_case(name))
+        .map(|s| s.number)
+}
+
+/// Parse a card's `path` attribute, e.g. "15.2.6"
+pub fn parse_path(path: &str) -> Option<TreePath> {
+    let mut parts = path.split('.').map(|part| part.trim().parse::<u8>().ok());
+
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Some(number)), Some(Some(from)), Some(Some(to)), None) => {
+            Some(TreePath { number, from, to })
+        },
+        _ => None,
+    }
+}
+
+/// The sephira a card sits on, if it isn't a trump
+pub fn card_sephira(card: &TarotCard) -> Option<u8> {
+    if let Some(number) = card.sephira.as_deref().and_then(sephira_number) {
+        return Some(number);
+    }
+
+    match card.rank()? {
+        Rank::Court(Court::Knight) => Some(2),
+        Rank::Court(Court::Queen) => Some(3),
+        Rank::Court(Court::Prince) => Some(6),
+        Rank::Court(Court::Princess) => Some(10),
+        _ => None,
+    }
+}
+
+/// The path a trump sits on
+pub fn card_path(card: &TarotCard) -> Option<TreePath> {
+    card.path.as_deref().and_then(parse_path)
+}
+
+/// The sephiroth and paths touched by a reading, with the cards on each
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TreeHighlights {
+    pub sephiroth: BTreeMap<u8, Vec<String>>,
+    pub paths: BTreeMap<u8, Vec<String>>,
+}
+
+impl TreeHighlights {
+    pub fn is_empty(&self) -> bool {
+        self.sephiroth.is_empty() && self.paths.is_empty()
+    }
+
+    pub fn has_sephira(&self, number: u8) -> bool {
+        self.sephiroth.contains_key(&number)
+    }
+
+    pub fn has_path(&self, number: u8) -> bool {
+        self.paths.contains_key(&number)
+    }
+
+    /// List the lit sephiroth and paths with their cards, from the top of the tree down
+    pub fn legend(&self) -> String {
+        let mut out = String::new();
+
+        for (number, cards) in &self.sephiroth {
+            let name = sephira(*number).map(|s| s.name).unwrap_or_default();
+            out.push_str(&format!("{} ({}): {}\n", name, number, cards.join(", ")));
+        }
+
+        for (number, cards) in &self.paths {
+            let ends = PATHS
+                .iter()
+                .find(|p| p.number == *number)
+                .and_then(|p| Some((sephira(p.from)?.name, sephira(p.to)?.name)));
+
+            match ends {
+                Some((from, to)) => {
+                    out.push_str(&format!(
+                        "Path {} ({}–{}): {}\n",
+                        number,
+                        from,
+                        to,
+                        cards.join(", ")
+                    ))
+                },
+                None => out.push_str(&format!("Path {}: {}\n", number, cards.join(", "))),
+            }
+        }
+
+        out
+    }
+}
+
+/// Work out which sephiroth and paths the cards of a reading touch
+pub fn tree_highlights(cards: &[&TarotCard]) -> TreeHighlights {
+    let mut highlights = TreeHighlights::default();
+
+    for card in cards {
+        if let Some(path) = card_path(card) {
+            highlights.paths.entry(path.number).or_default().push(card.card.clone());
+        } else if let Some(number) = card_sephira(card) {
+            highlights.sephiroth.entry(number).or_default().push(card.card.clone());
+        }
+    }
+
+    highlights
+}
+
+/// A reading's tree, as shown in the `:tarothistory tree` window
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TreeView {
+    pub title: String,
+    pub highlights: TreeHighlights,
+}
+
+const BACKGROUND: Rgba<u8> = Rgba([20, 20, 30, 255]);
+const DIM: Rgba<u8> = Rgba([70, 70, 90, 255]);
+const LIT: Rgba<u8> = Rgba([235, 190, 60, 255]);
+
+/// Pixels between the pillars, and between levels of the tree
+const UNIT_X: f64 = 100.0;
+const UNIT_Y: f64 = 70.0;
+const MARGIN: f64 = 50.0;
+const RADIUS: f64 = 22.0;
+
+fn to_pixels(x: f64, y: f64) -> (f64, f64) {
+    (MARGIN + (x + 1.0) * UNIT_X, MARGIN + y * UNIT_Y)
+}
+
+/// Draw the tree as a PNG, lighting up the sephiroth and paths of a reading
+pub fn render_tree_png(highlights: &TreeHighlights) -> Result<Vec<u8>, String> {
+    let (width, height) = to_pixels(1.0, 8.0);
+    let mut img: RgbaImage =
+        ImageBuffer::from_pixel((width + MARGIN) as u32, (height + MARGIN) as u32, BACKGROUND);
+
+    // Unlit paths first, so lit ones are drawn over them where they cross
+    let mut paths: Vec<&TreePath> = PATHS.iter().collect();
+    paths.sort_by_key(|p| highlights.has_path(p.number));
+
+    for p in paths {
+        let (Some(from), Some(to)) = (sephira(p.from), sephira(p.to)) else {
+            continue;
+        };
+
+        let (width, color) = if highlights.has_path(p.number) {
+            (7.0, LIT)
+        } else {
+            (3.0, DIM)
+        };
+        draw_line(&mut img, to_pixels(from.x, from.y), to_pixels(to.x, to.y), width, color);
+    }
+
+    for s in SEPHIROTH.iter() {
+        let fill = if highlights.has_sephira(s.number) {
+            LIT
+        } else {
+            BACKGROUND
+        };
+        draw_disc(&mut img, to_pixels(s.x, s.y), RADIUS, LIT);
+        draw_disc(&mut img, to_pixels(s.x, s.y), RADIUS - 3.0, fill);
+    }
+
+    let mut png_bytes: Vec<u8> = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut png_bytes), image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+
+    Ok(png_bytes)
+}
+
+/// Render the tree for a reading, ready to upload
+pub fn tree_upload(highlights: &TreeHighlights) -> Result<ImageUpload, String> {
+    ImageUpload::from_bytes("tarot_tree.png", mime::IMAGE_PNG, render_tree_png(highlights)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tarot_cards::{find_card, get_major_arcana};
+
+    #[test]
+    fn test_parse_path() {
+        assert_eq!(parse_path("15.2.6"), Some(path(15, 2, 6)));
+        assert_eq!(parse_path("32.9.10"), Some(path(32, 9, 10)));
+        assert_eq!(parse_path("15.2"), None);
+        assert_eq!(parse_path("15.2.6.1"), None);
+        assert_eq!(parse_path("fifteen"), None);
+    }
+
+    #[test]
+    fn test_trump_paths_match_tree() {
+        let trumps = get_major_arcana();
+        assert_eq!(trumps.len(), 22);
+
+        for card in trumps {
+            let path = card_path(card).unwrap();
+            assert!(PATHS.contains(&path), "{} is on an unknown path", card.card);
+        }
+    }
+
+    #[test]
+    fn test_tree_highlights() {
+        let cards: Vec<&TarotCard> = ["the star", "six of swords", "prince of cups", "ace of cups"]
+            .iter()
+            .map(|name| find_card(name).unwrap())
+            .collect();
+        let res = tree_highlights(&cards);
+
+        assert_eq!(res.paths.keys().copied().collect::<Vec<_>>(), vec![15]);
+        assert_eq!(res.sephiroth.keys().copied().collect::<Vec<_>>(), vec![1, 6]);
+        assert_eq!(res.sephiroth[&6], vec!["Six of Swords", "Prince of Cups"]);
+
+        let legend = res.legend();
+        assert!(legend.contains("Kether (1): Ace of Cups\n"));
+        assert!(legend.contains("Tiphareth (6): Six of Swords, Prince of Cups\n"));
+        assert!(legend.contains("Path 15 (Chokmah–Tiphareth): The Star\n"));
+    }
+
+    #[test]
+    fn test_render_tree_png() {
+        let cards = vec![find_card("the universe").unwrap()];
+        let png = render_tree_png(&tree_highlights(&cards)).unwrap();
+        let img = image::load_from_memory(&png).unwrap().to_rgba8();
+
+        // Halfway along path 32, between Yesod and Malkuth
+        let (x, y) = to_pixels(0.0, 7.0);
+        assert_eq!(*img.get_pixel(x as u32, y as u32), LIT);
+
+        // Halfway along path 31, between Hod and Malkuth, is unlit
+        let (x, y) = to_pixels(-0.5, 6.5);
+        assert_eq!(*img.get_pixel(x as u32, y as u32), DIM);
+    }
+}
@@ -0,0 +1,614 @@
+//! Trends in tarot reading history over time
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime};
+
+use crate::tarot_api::{self, ReadingHistoryItem};
+use crate::tarot_cards;
+use crate::tarot_sky::{self, MoonPhase, Sky};
+use crate::tarot_wheel;
+
+/// Number of periods the rolling "most frequent card" looks back over
+const ROLLING_PERIODS: usize = 4;
+
+/// Number of streaks to show in a report
+const MAX_STREAKS: usize = 5;
+
+/// Length of periods that readings are grouped into
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Period {
+    Week,
+    Month,
+}
+
+impl Period {
+    /// The first day of the period containing `date`
+    fn start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Week => date - Duration::days(date.weekday().num_days_from_monday().into()),
+            Period::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    /// The first day of the period following the one starting at `start`
+    fn next(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Week => start + Duration::days(7),
+            Period::Month => start + Months::new(1),
+        }
+    }
+
+    fn label(self, start: NaiveDate) -> String {
+        match self {
+            Period::Week => start.format("%G-W%V").to_string(),
+            Period::Month => start.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+/// Which readings to include: an inclusive range of dates, and optionally one phase of
+/// the Moon or one sign for the Sun
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ReadingFilter {
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+    pub moon: Option<MoonPhase>,
+    /// The Sun's sign, from 0 for Aries
+    pub sun: Option<usize>,
+}
+
+impl ReadingFilter {
+    pub fn is_empty(&self) -> bool {
+        self.since.is_none() && self.until.is_none() && self.moon.is_none() && self.sun.is_none()
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.since.is_none_or(|since| date >= since) && self.until.is_none_or(|until| date <= until)
+    }
+
+    /// Whether a reading drawn at `time` passes the moon phase and Sun sign filters
+    pub fn matches_sky(&self, time: NaiveDateTime) -> bool {
+        if self.moon.is_none() && self.sun.is_none() {
+            return true;
+        }
+
+        let sky = Sky::at(time);
+        self.moon.is_none_or(|moon| sky.moon == moon) &&
+            self.sun.is_none_or(|sun| sky.sun_sign() == sun)
+    }
+
+    /// Whether a reading passes both the dates and the sky filters
+    pub fn includes(&self, reading: &ReadingHistoryItem) -> bool {
+        match tarot_sky::reading_time(reading) {
+            Some(time) => self.contains(time.date()) && self.matches_sky(time),
+            None => false,
+        }
+    }
+
+    /// Describe the filter for report headings, e.g. "since 2026-01-01, at Full Moon"
+    pub fn describe(&self) -> String {
+        let mut parts = match (self.since, self.until) {
+            (Some(since), Some(until)) => vec![format!("{} to {}", since, until)],
+            (Some(since), None) => vec![format!("since {}", since)],
+            (None, Some(until)) => vec![format!("until {}", until)],
+            (None, None) => vec![],
+        };
+
+        if let Some(moon) = self.moon {
+            parts.push(format!("at {}", moon));
+        }
+
+        if let Some(sun) = self.sun {
+            parts.push(format!("with the Sun in {}", tarot_wheel::SIGNS[sun]));
+        }
+
+        if parts.is_empty() {
+            return "all time".to_string();
+        }
+
+        parts.join(", ")
+    }
+}
+
+/// Pull `since <date>`, `until <date>`, `moon <phase>` and `sun <sign>` out of `args`,
+/// returning the remaining arguments
+pub fn parse_reading_filter(args: &[String]) -> Result<(ReadingFilter, Vec<String>), String> {
+    let mut filter = ReadingFilter::default();
+    let mut rest = Vec::new();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let slot = match arg.as_str() {
+            "since" => &mut filter.since,
+            "until" => &mut filter.until,
+            "moon" => {
+                let phase = iter.next().ok_or("Expected a phase after 'moon'")?;
+                filter.moon = Some(phase.parse()?);
+                continue;
+            },
+            "sun" => {
+                let sign = iter.next().ok_or("Expected a sign after 'sun'")?;
+                let sign = tarot_wheel::sign_index(sign)
+                    .ok_or_else(|| format!("Unknown sign: '{}'", sign))?;
+                filter.sun = Some(sign);
+                continue;
+            },
+            _ => {
+                rest.push(arg.clone());
+                continue;
+            },
+        };
+
+        let date = iter.next().ok_or_else(|| format!("Expected a date after '{}'", arg))?;
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| format!("Invalid date: '{}' (expected YYYY-MM-DD)", date))?;
+        *slot = Some(date);
+    }
+
+    Ok((filter, rest))
+}
+
+/// What to chart: an attribute type, the period length and the reading filter
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TrendQuery {
+    /// Attribute type, as understood by [tarot_cards::TarotCard::attribute_values]
+    pub attribute: String,
+    pub period: Period,
+    pub filter: ReadingFilter,
+}
+
+impl TrendQuery {
+    /// Parse `[suits|elements|planets|signs|sephira|arcana] [weekly|monthly] [since DATE] [until DATE]`
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let (filter, rest) = parse_reading_filter(args)?;
+        let mut query = TrendQuery {
+            attribute: "suit".into(),
+            period: Period::Month,
+            filter,
+        };
+
+        for arg in rest {
+            match arg.as_str() {
+                "weekly" | "week" => query.period = Period::Week,
+                "monthly" | "month" => query.period = Period::Month,
+                other => {
+                    query.attribute = attribute_type(other)
+                        .ok_or_else(|| format!("Unknown trend: '{}'", other))?
+                        .to_string();
+                },
+            }
+        }
+
+        Ok(query)
+    }
+}
+
+/// Map a command argument such as "suits" to its attribute type
+pub fn attribute_type(arg: &str) -> Option<&'static str> {
+    match arg {
+        "suits" | "suit" => Some("suit"),
+        "elements" | "element" => Some("element"),
+        "planets" | "planet" => Some("planet"),
+        "signs" | "sign" => Some("sign"),
+        "sephira" => Some("sephira"),
+        "arcana" | "major" => Some("arcana"),
+        _ => None,
+    }
+}
+
+/// A card that came up in several readings in a row
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Streak {
+    pub card: String,
+    pub length: usize,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+/// Attribute counts per period, with the rolling most frequent card and card streaks
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrendReport {
+    pub title: String,
+    /// Label of each period, oldest first
+    pub periods: Vec<String>,
+    /// Readings in each period
+    pub readings: Vec<i32>,
+    /// Count of each attribute value per period
+    pub series: Vec<(String, Vec<i32>)>,
+    /// Most frequent card over the last few periods, and its count, as of each period
+    pub leaders: Vec<Option<(String, i32)>>,
+    pub streaks: Vec<Streak>,
+}
+
+fn reading_day(reading: &ReadingHistoryItem) -> Option<NaiveDate> {
+    let date = reading.reading_date.get(..10)?;
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// Readings matching `filter`, oldest first
+pub fn dated_readings<'a>(
+    readings: &'a [ReadingHistoryItem],
+    filter: &ReadingFilter,
+) -> Vec<(NaiveDate, &'a ReadingHistoryItem)> {
+    let mut dated: Vec<_> = readings
+        .iter()
+        .filter(|reading| filter.includes(reading))
+        .filter_map(|reading| Some((reading_day(reading)?, reading)))
+        .collect();
+
+    dated.sort_by(|a, b| a.1.reading_date.cmp(&b.1.reading_date));
+    dated
+}
+
+fn count_attribute(
+    counts: &mut HashMap<String, i32>,
+    reading: &ReadingHistoryItem,
+    attribute: &str,
+) {
+    for card in reading.cards.iter().filter_map(|c| tarot_cards::find_card(&c.card_name)) {
+        for value in card.attribute_values(attribute) {
+            *counts.entry(value.to_string()).or_default() += 1;
+        }
+    }
+}
+
+/// Count an attribute over the readings matching `filter`, returning frequencies and percentages
+///
+/// This mirrors the server's attribute frequency endpoint, for when only some readings count.
+pub fn attribute_frequencies(
+    readings: &[ReadingHistoryItem],
+    attribute: &str,
+    filter: &ReadingFilter,
+) -> (HashMap<String, i32>, HashMap<String, f64>) {
+    let mut frequencies = HashMap::new();
+
+    for (_, reading) in dated_readings(readings, filter) {
+        count_attribute(&mut frequencies, reading, attribute);
+    }
+
+    let total: i32 = frequencies.values().sum();
+    let percentages = frequencies
+        .iter()
+        .map(|(value, &count)| (value.clone(), count as f64 / total as f64 * 100.0))
+        .collect();
+
+    (frequencies, percentages)
+}
+
+/// Find cards that were drawn in consecutive readings, longest and most recent first
+pub fn card_streaks(readings: &[(NaiveDate, &ReadingHistoryItem)]) -> Vec<Streak> {
+    let mut streaks = Vec::new();
+    let mut current: HashMap<String, Streak> = HashMap::new();
+
+    for (date, reading) in readings {
+        let cards: HashSet<&String> = reading.cards.iter().map(|c| &c.card_name).collect();
+
+        // Streaks of cards missing from this reading have ended
+        let ended: Vec<String> = current.keys().filter(|c| !cards.contains(c)).cloned().collect();
+        for card in ended {
+            streaks.extend(current.remove(&card).filter(|s| s.length > 1));
+        }
+
+        for card in cards {
+            let streak = current.entry(card.clone()).or_insert_with(|| {
+                Streak {
+                    card: card.clone(),
+                    length: 0,
+                    start: *date,
+                    end: *date,
+                }
+            });
+            streak.length += 1;
+            streak.end = *date;
+        }
+    }
+
+    streaks.extend(current.into_values().filter(|s| s.length > 1));
+    streaks
+        .sort_by(|a, b| b.length.cmp(&a.length).then(b.end.cmp(&a.end)).then(a.card.cmp(&b.card)));
+    streaks
+}
+
+/// Build the trend report for `query` from a user's reading history
+pub fn trend_report(readings: &[ReadingHistoryItem], query: &TrendQuery) -> TrendReport {
+    let dated = dated_readings(readings, &query.filter);
+    let title = format!("{} trends, {}", query.attribute, query.filter.describe());
+
+    let (Some((first, _)), Some((last, _))) = (dated.first(), dated.last()) else {
+        return TrendReport { title, ..Default::default() };
+    };
+
+    // Every period from the first reading to the last, including empty ones
+    let mut starts = vec![query.period.start(*first)];
+    while let Some(next) = starts.last().map(|s| query.period.next(*s)).filter(|n| n <= last) {
+        starts.push(next);
+    }
+
+    let mut attributes: Vec<HashMap<String, i32>> = vec![HashMap::new(); starts.len()];
+    let mut cards: Vec<HashMap<String, i32>> = vec![HashMap::new(); starts.len()];
+    let mut counts = vec![0; starts.len()];
+
+    for (date, reading) in &dated {
+        let idx = starts.partition_point(|start| start <= date) - 1;
+        counts[idx] += 1;
+        count_attribute(&mut attributes[idx], reading, &query.attribute);
+
+        for card in &reading.cards {
+            *cards[idx].entry(card.card_name.clone()).or_default() += 1;
+        }
+    }
+
+    let values: BTreeMap<&String, ()> =
+        attributes.iter().flat_map(|a| a.keys()).map(|k| (k, ())).collect();
+    let series = values
+        .into_keys()
+        .map(|value| {
+            let row = attributes.iter().map(|a| a.get(value).copied().unwrap_or(0)).collect();
+            (value.clone(), row)
+        })
+        .collect();
+
+    let leaders = (0..starts.len())
+        .map(|idx| {
+            let mut window: HashMap<&String, i32> = HashMap::new();
+            for period in &cards[(idx + 1).saturating_sub(ROLLING_PERIODS)..=idx] {
+                for (card, count) in period {
+                    *window.entry(card).or_default() += count;
+                }
+            }
+
+            // Ties go to the alphabetically first card, so reports are stable
+            window
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(a.0)))
+                .map(|(card, count)| (card.clone(), count))
+        })
+        .collect();
+
+    let mut streaks = card_streaks(&dated);
+    streaks.truncate(MAX_STREAKS);
+
+    TrendReport {
+        title,
+        periods: starts.iter().map(|s| query.period.label(*s)).collect(),
+        readings: counts,
+        series,
+        leaders,
+        streaks,
+    }
+}
+
+impl TrendReport {
+    /// Notes on the rolling most frequent card and streaks, shown below the charts
+    pub fn notes(&self) -> String {
+        let mut out = String::new();
+
+        if let Some((period, Some((card, count)))) = self.periods.last().zip(self.leaders.last()) {
+            out.push_str(&format!(
+                "Most frequent card (last {} periods, as of {}): {} ({})\n",
+                ROLLING_PERIODS, period, card, count
+            ));
+        }
+
+        let mut previous = None;
+        let changes: Vec<String> = self
+            .periods
+            .iter()
+            .zip(&self.leaders)
+            .filter_map(|(period, leader)| {
+                let (card, _) = leader.as_ref()?;
+                (previous.replace(card) != Some(card)).then(|| format!("{}: {}", period, card))
+            })
+            .collect();
+
+        if changes.len() > 1 {
+            out.push_str(&format!("Leader changes: {}\n", changes.join(", ")));
+        }
+
+        if self.streaks.is_empty() {
+            out.push_str("No cards came up in consecutive readings.\n");
+        } else {
+            out.push_str("\nStreaks (cards in consecutive readings):\n");
+            for streak in &self.streaks {
+                out.push_str(&format!(
+                    "  {} - {} readings, {} to {}\n",
+                    streak.card, streak.length, streak.start, streak.end
+                ));
+            }
+        }
+
+        out
+    }
+
+    /// The whole report as text, for posting to a room
+    pub fn to_text(&self) -> String {
+        if self.periods.is_empty() {
+            return format!("**{}**\n\nNo readings in this range.", self.title);
+        }
+
+        let total = vec![("readings".to_string(), self.readings.clone())];
+
+        let mut out = format!("**{}**\n\n", self.title);
+        out.push_str(&tarot_api::generate_trend_graph(&self.periods, &total));
+        out.push_str("\n\n");
+        out.push_str(&tarot_api::generate_trend_graph(&self.periods, &self.series));
+        out.push_str("\n\n");
+        out.push_str(&self.notes());
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tarot_api::CardInReading;
+
+    fn reading(date: &str, cards: &[&str]) -> ReadingHistoryItem {
+        let cards: Vec<CardInReading> = cards
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                CardInReading {
+                    position: i as i32,
+                    card_name: name.to_string(),
+                    label: None,
+                    reversed: false,
+                    dignity: None,
+                }
+            })
+            .collect();
+
+        ReadingHistoryItem {
+            reading_id: 0,
+            spread_type: cards.len().to_string(),
+            reading_date: format!("{}T12:00:00", date),
+            card_count: cards.len(),
+            cards,
+            notes: None,
+            question: None,
+            room_id: None,
+            quintessence: None,
+            moon_phase: None,
+            sun_sign: None,
+        }
+    }
+
+    fn history() -> Vec<ReadingHistoryItem> {
+        // Newest first, as the server returns them
+        vec![
+            reading("2026-03-02", &["The Star", "Two of Cups"]),
+            reading("2026-01-20", &["The Star", "Six of Swords"]),
+            reading("2026-01-05", &["The Star", "Six of Swords", "Ace of Cups"]),
+        ]
+    }
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_parse_reading_filter() {
+        let (filter, rest) =
+            parse_reading_filter(&strings(&["suits", "since", "2026-01-01"])).unwrap();
+        assert_eq!(filter.since, Some(date("2026-01-01")));
+        assert_eq!(filter.until, None);
+        assert_eq!(rest, vec!["suits"]);
+
+        assert!(parse_reading_filter(&strings(&["since"])).is_err());
+        assert!(parse_reading_filter(&strings(&["until", "yesterday"])).is_err());
+
+        let (filter, rest) =
+            parse_reading_filter(&strings(&["moon", "full", "sun", "aries"])).unwrap();
+        assert_eq!(filter.moon, Some(MoonPhase::Full));
+        assert_eq!(filter.sun, Some(0));
+        assert!(rest.is_empty());
+        assert_eq!(filter.describe(), "at Full Moon, with the Sun in Aries");
+
+        assert!(parse_reading_filter(&strings(&["moon", "blue"])).is_err());
+        assert!(parse_reading_filter(&strings(&["sun", "ophiuchus"])).is_err());
+    }
+
+    #[test]
+    fn test_sky_filter() {
+        // The Moon was full on 2026-03-03, and the Sun entered Aquarius early on 2026-01-20
+        let history = history();
+        let filter = ReadingFilter { moon: Some(MoonPhase::Full), ..Default::default() };
+        let dated = dated_readings(&history, &filter);
+        assert_eq!(dated.len(), 1);
+        assert_eq!(dated[0].0, date("2026-03-02"));
+
+        let filter = ReadingFilter {
+            sun: tarot_wheel::sign_index("Capricorn"),
+            ..Default::default()
+        };
+        let dated = dated_readings(&history, &filter);
+        assert_eq!(dated.len(), 1);
+        assert_eq!(dated[0].0, date("2026-01-05"));
+        assert_eq!(filter.describe(), "with the Sun in Capricorn");
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query =
+            TrendQuery::parse(&strings(&["elements", "weekly", "until", "2026-02-01"])).unwrap();
+        assert_eq!(query.attribute, "element");
+        assert_eq!(query.period, Period::Week);
+        assert_eq!(query.filter.until, Some(date("2026-02-01")));
+
+        let query = TrendQuery::parse(&[]).unwrap();
+        assert_eq!(query.attribute, "suit");
+        assert_eq!(query.period, Period::Month);
+
+        assert!(TrendQuery::parse(&strings(&["colours"])).is_err());
+    }
+
+    #[test]
+    fn test_attribute_frequencies() {
+        let filter = ReadingFilter {
+            since: Some(date("2026-01-10")),
+            ..Default::default()
+        };
+        let (freq, pct) = attribute_frequencies(&history(), "suit", &filter);
+
+        assert_eq!(freq.len(), 2);
+        assert_eq!(freq["Swords"], 1);
+        assert_eq!(freq["Cups"], 1);
+        assert_eq!(pct["Cups"], 50.0);
+    }
+
+    #[test]
+    fn test_trend_report() {
+        let query = TrendQuery::parse(&strings(&["suits"])).unwrap();
+        let report = trend_report(&history(), &query);
+
+        assert_eq!(report.periods, vec!["2026-01", "2026-02", "2026-03"]);
+        assert_eq!(report.readings, vec![2, 0, 1]);
+        assert_eq!(report.series, vec![
+            ("Cups".to_string(), vec![1, 0, 1]),
+            ("Swords".to_string(), vec![2, 0, 0]),
+        ]);
+        // Ties go to the alphabetically first card
+        assert_eq!(report.leaders[0], Some(("Six of Swords".to_string(), 2)));
+        assert_eq!(report.leaders[2], Some(("The Star".to_string(), 3)));
+
+        let query = TrendQuery::parse(&strings(&["arcana", "weekly"])).unwrap();
+        let report = trend_report(&history(), &query);
+        assert_eq!(report.periods.first().unwrap(), "2026-W02");
+        assert_eq!(report.periods.last().unwrap(), "2026-W10");
+        assert_eq!(report.readings.iter().sum::<i32>(), 3);
+    }
+
+    #[test]
+    fn test_card_streaks() {
+        let query = TrendQuery::parse(&[]).unwrap();
+        let report = trend_report(&history(), &query);
+
+        assert_eq!(report.streaks, vec![
+            Streak {
+                card: "The Star".into(),
+                length: 3,
+                start: date("2026-01-05"),
+                end: date("2026-03-02"),
+            },
+            Streak {
+                card: "Six of Swords".into(),
+                length: 2,
+                start: date("2026-01-05"),
+                end: date("2026-01-20"),
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_empty_report() {
+        let query = TrendQuery::parse(&strings(&["since", "2027-01-01"])).unwrap();
+        let report = trend_report(&history(), &query);
+
+        assert!(report.periods.is_empty());
+        assert!(report.to_text().contains("No readings in this range."));
+    }
+}
@@ -0,0 +1,675 @@
+//! The zodiac wheel: where the cards of a reading fall among the signs and decans
+//!
+//! The small cards each rule one decan (ten degrees) of their sign: 2–4 the decans of a
+//! cardinal sign, 5–7 of a fixed sign and 8–10 of a mutable one. Zodiacal trumps cover
+//! their whole sign. Knights, Queens and Princes rule from 20° of the sign before theirs
+//! to 20° of their own, and Princesses the three signs around theirs. Aces and the
+//! planetary and elemental trumps have no place on the wheel.
+use image::{ImageBuffer, Rgba, RgbaImage};
+
+use crate::image_upload::ImageUpload;
+use crate::tarot_api::ReadingHistoryItem;
+use crate::tarot_cards::{self, Court, Rank, TarotCard};
+use crate::tarot_composite::{draw_arc, draw_disc, draw_line};
+use crate::tarot_trends::{self, ReadingFilter};
+
+pub const SIGNS: [&str; 12] = [
+    "Aries",
+    "Taurus",
+    "Gemini",
+    "Cancer",
+    "Leo",
+    "Virgo",
+    "Libra",
+    "Scorpio",
+    "Sagittarius",
+    "Capricorn",
+    "Aquarius",
+    "Pisces",
+];
+
+const SIGN_GLYPHS: [char; 12] = [
+    '♈', '♉', '♊', '♋', '♌', '♍', '♎', '♏', '♐', '♑', '♒', '♓',
+];
+
+/// Number of busiest decans listed under an aggregate wheel
+const MAX_DECANS: usize = 5;
+
+/// Look up a sign's position in the zodiac, from 0 for Aries
+pub fn sign_index(name: &str) -> Option<usize> {
+    SIGNS.iter().position(|sign| sign.eq_ignore_ascii_case(name))
+}
+
+pub fn sign_glyph(sign: usize) -> char {
+    SIGN_GLYPHS[sign % 12]
+}
+
+pub fn planet_glyph(planet: &str) -> Option<char> {
+    let glyph = match planet {
+        "Sol" => '☉',
+        "Luna" => '☽',
+        "Mercury" => '☿',
+        "Venus" => '♀',
+        "Mars" => '♂',
+        "Jupiter" => '♃',
+        "Saturn" => '♄',
+        "Uranus" => '♅',
+        "Neptune" => '♆',
+        "Pluto" => '♇',
+        "Dragon's Head" => '☊',
+        "Dragon's Tail" => '☋',
+        "Earth" => '⊕',
+        _ => return None,
+    };
+
+    Some(glyph)
+}
+
+/// Name a decan, numbered from 0 for the first decan of Aries, e.g. "Aquarius II"
+pub fn decan_name(decan: usize) -> String {
+    let decan = decan % 36;
+    let roman = tarot_cards::roman_numeral(decan as u8 % 3 + 1);
+
+    format!("{} {}", SIGNS[decan / 3], roman)
+}
+
+/// Which decan of its sign a small card rules, from 0
+fn pip_decan(n: u8) -> Option<usize> {
+    (2..=10).contains(&n).then(|| usize::from(n - 2) % 3)
+}
+
+/// The small card ruling a decan, numbered from 0 for the first decan of Aries
+pub fn decan_card(decan: usize) -> Option<&'static TarotCard> {
+    let sign = SIGNS[(decan % 36) / 3];
+
+    tarot_cards::get_minor_arcana().into_iter().find(|card| {
+        let in_decan = matches!(card.rank(), Some(Rank::Pip(n)) if pip_decan(n) == Some(decan % 3));
+        in_decan && card.sign_1.as_deref() == Some(sign)
+    })
+}
+
+/// Format a zodiacal longitude as degrees of a sign, e.g. "20° Capricorn"
+fn format_degrees(longitude: f64) -> String {
+    let longitude = longitude.rem_euclid(360.0);
+    let sign = (longitude / 30.0) as usize;
+
+    format!("{}° {}", (longitude - sign as f64 * 30.0).round(), SIGNS[sign])
+}
+
+/// Where a card falls on the wheel
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placement {
+    pub card: String,
+    /// Start and end of the card's arc in degrees from 0° Aries; `to` may pass 360
+    pub from: f64,
+    pub to: f64,
+    pub planet: Option<String>,
+}
+
+impl Placement {
+    /// The middle of the card's arc
+    pub fn longitude(&self) -> f64 {
+        ((self.from + self.to) / 2.0).rem_euclid(360.0)
+    }
+
+    /// Every decan the card's arc covers
+    pub fn decans(&self) -> impl Iterator<Item = usize> {
+        let first = (self.from / 10.0).floor() as i64;
+        let last = (self.to / 10.0).ceil() as i64;
+
+        (first..last).map(|d| d.rem_euclid(36) as usize)
+    }
+
+    /// Describe the card's place, e.g. "Aquarius II (10°–20°)" or "20° Taurus – 20° Gemini"
+    pub fn describe(&self) -> String {
+        let aligned = |deg: f64| deg.rem_euclid(30.0) == 0.0;
+        let sign = |deg: f64| SIGNS[(deg.rem_euclid(360.0) / 30.0) as usize];
+
+        if self.to - self.from == 10.0 {
+            let start = self.from.rem_euclid(30.0);
+            format!("{} ({}°–{}°)", decan_name((self.from / 10.0) as usize), start, start + 10.0)
+        } else if aligned(self.from) && self.to - self.from == 30.0 {
+            sign(self.from).to_string()
+        } else if aligned(self.from) && aligned(self.to) {
+            format!("{} to {}", sign(self.from), sign(self.to - 30.0))
+        } else {
+            format!("{} – {}", format_degrees(self.from), format_degrees(self.to))
+        }
+    }
+}
+
+/// Place a card on the wheel by its sign and rank
+pub fn card_placement(card: &TarotCard) -> Option<Placement> {
+    let sign = card.sign_1.as_deref().and_then(sign_index)?;
+    let start = sign as f64 * 30.0;
+
+    let (from, to) = match card.rank()? {
+        Rank::Pip(n) => {
+            let decan = pip_decan(n)? as f64;
+            (start + decan * 10.0, start + decan * 10.0 + 10.0)
+        },
+        Rank::Court(Court::Princess) => (start - 30.0, start + 60.0),
+        Rank::Court(_) => (start - 10.0, start + 20.0),
+        Rank::Trump(_) => (start, start + 30.0),
+    };
+
+    Some(Placement {
+        card: card.card.clone(),
+        from: from.rem_euclid(360.0),
+        to: from.rem_euclid(360.0) + (to - from),
+        planet: card.planet_orb.clone(),
+    })
+}
+
+/// What to show on the wheel: one reading by its number, or all readings matching a filter
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WheelQuery {
+    Reading(usize),
+    History(ReadingFilter),
+}
+
+/// A wheel as shown in the `:tarothistory wheel` window or posted with a spread
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WheelView {
+    pub title: String,
+    /// Cards drawn individually on the wheel; empty for an aggregate wheel
+    pub placements: Vec<Placement>,
+    /// Cards with no place on the wheel, with their planet if they have one
+    pub unplaced: Vec<(String, Option<String>)>,
+    /// How many placed cards cover each decan, from the first decan of Aries
+    pub decans: Vec<u32>,
+    /// Number of cards placed on the wheel, and with no place on it
+    pub placed: usize,
+    pub off_wheel: usize,
+    pub readings: usize,
+    /// Whether this sums up many readings rather than showing one
+    pub aggregate: bool,
+}
+
+impl WheelView {
+    fn add_card(&mut self, card: &TarotCard, individual: bool) {
+        match card_placement(card) {
+            Some(placement) => {
+                self.placed += 1;
+
+                for decan in placement.decans() {
+                    self.decans[decan] += 1;
+                }
+
+                if individual {
+                    self.placements.push(placement);
+                }
+            },
+            None => {
+                self.off_wheel += 1;
+
+                if individual {
+                    self.unplaced.push((card.card.clone(), card.planet_orb.clone()));
+                }
+            },
+        }
+    }
+
+    /// The highest count in any decan
+    pub fn max_count(&self) -> u32 {
+        self.decans.iter().copied().max().unwrap_or(0)
+    }
+
+    /// List the cards and their places, or the busiest signs and decans of an aggregate wheel
+    pub fn legend(&self) -> String {
+        let glyph = |planet: &Option<String>| {
+            planet
+                .as_deref()
+                .and_then(planet_glyph)
+                .map(|g| format!("{} ", g))
+                .unwrap_or_default()
+        };
+
+        let mut out = String::new();
+
+        if self.aggregate {
+            out.push_str(&format!(
+                "{} readings: {} cards on the wheel, {} off it\n",
+                self.readings, self.placed, self.off_wheel
+            ));
+
+            let mut signs: Vec<(usize, u32)> =
+                (0..12).map(|s| (s, self.decans[s * 3..s * 3 + 3].iter().sum())).collect();
+            signs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+            out.push_str("\nBusiest signs:\n");
+            for (sign, count) in signs.iter().take(3).filter(|(_, c)| *c > 0) {
+                out.push_str(&format!("{} {}: {}\n", sign_glyph(*sign), SIGNS[*sign], count));
+            }
+
+            let mut decans: Vec<(usize, u32)> = self.decans.iter().copied().enumerate().collect();
+            decans.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+            out.push_str("\nBusiest decans:\n");
+            for (decan, count) in decans.iter().take(MAX_DECANS).filter(|(_, c)| *c > 0) {
+                let card = decan_card(*decan).map(|c| format!(" ({})", c.card)).unwrap_or_default();
+                out.push_str(&format!("{}{}: {}\n", decan_name(*decan), card, count));
+            }
+
+            return out;
+        }
+
+        for placement in &self.placements {
+            out.push_str(&format!(
+                "{}{}: {}\n",
+                glyph(&placement.planet),
+                placement.card,
+                placement.describe()
+            ));
+        }
+
+        if !self.unplaced.is_empty() {
+            let unplaced: Vec<String> = self
+                .unplaced
+                .iter()
+                .map(|(card, planet)| format!("{}{}", glyph(planet), card))
+                .collect();
+            out.push_str(&format!("Off the wheel: {}\n", unplaced.join(", ")));
+        }
+
+        out
+    }
+}
+
+/// Build the wheel for the cards of one reading
+pub fn reading_wheel(title: String, cards: &[&TarotCard]) -> WheelView {
+    let mut view = WheelView {
+        title,
+        decans: vec![0; 36],
+        readings: 1,
+        ..Default::default()
+    };
+
+    for card in cards {
+        view.add_card(card, true);
+    }
+
+    view
+}
+
+/// Build an aggregate wheel of every card drawn in the readings matching a filter
+pub fn history_wheel(readings: &[ReadingHistoryItem], filter: &ReadingFilter) -> WheelView {
+    let dated = tarot_trends::dated_readings(readings, filter);
+    let title = format!("Zodiac wheel, {}", filter.describe());
+    let mut view = WheelView {
+        title,
+        decans: vec![0; 36],
+        readings: dated.len(),
+        aggregate: true,
+        ..Default::default()
+    };
+
+    for (_, reading) in dated {
+        for card in reading.cards.iter().filter_map(|c| tarot_cards::find_card(&c.card_name)) {
+            view.add_card(card, false);
+        }
+    }
+
+    view
+}
+
+const BACKGROUND: Rgba<u8> = Rgba([20, 20, 30, 255]);
+const DIM: Rgba<u8> = Rgba([70, 70, 90, 255]);
+const LIT: Rgba<u8> = Rgba([235, 190, 60, 255]);
+const GLYPH: Rgba<u8> = Rgba([230, 230, 240, 255]);
+
+/// Tints for the signs of each element, in zodiac order from Aries
+const ELEMENT_TINTS: [Rgba<u8>; 4] = [
+    Rgba([90, 40, 40, 255]),
+    Rgba([45, 75, 45, 255]),
+    Rgba([85, 80, 45, 255]),
+    Rgba([40, 55, 95, 255]),
+];
+
+const SIZE: u32 = 640;
+const OUTER: f64 = 300.0;
+const SIGN_RING: f64 = 250.0;
+const HUB: f64 = 110.0;
+const BAR: f64 = 120.0;
+
+/// Convert a longitude and radius to pixels, with 0° Aries at nine o'clock
+fn to_pixels(longitude: f64, r: f64) -> (f64, f64) {
+    let angle = (180.0 + longitude).to_radians();
+    let c = f64::from(SIZE) / 2.0;
+
+    (c + r * angle.cos(), c - r * angle.sin())
+}
+
+/// One stroke of a planet glyph, in a box from -1 to 1 with y pointing up
+enum Stroke {
+    Line((f64, f64), (f64, f64)),
+    Arc((f64, f64), f64, (f64, f64)),
+    Dot((f64, f64), f64),
+}
+
+fn circle(c: (f64, f64), r: f64) -> Stroke {
+    Stroke::Arc(c, r, (0.0, 360.0))
+}
+
+fn glyph_strokes(planet: &str) -> Vec<Stroke> {
+    use Stroke::*;
+
+    match planet {
+        "Sol" => vec![circle((0.0, 0.0), 0.8), Dot((0.0, 0.0), 0.18)],
+        "Luna" => {
+            vec![
+                Arc((0.0, 0.0), 0.8, (61.0, 299.0)),
+                Arc((0.4, 0.0), 0.7, (91.0, 269.0)),
+            ]
+        },
+        "Mercury" => {
+            vec![
+                Arc((0.0, 0.85), 0.35, (180.0, 360.0)),
+                circle((0.0, 0.15), 0.4),
+                Line((0.0, -0.25), (0.0, -1.0)),
+                Line((-0.3, -0.65), (0.3, -0.65)),
+            ]
+        },
+        "Venus" => {
+            vec![
+                circle((0.0, 0.35), 0.5),
+                Line((0.0, -0.15), (0.0, -1.0)),
+                Line((-0.35, -0.6), (0.35, -0.6)),
+            ]
+        },
+        "Mars" => {
+            vec![
+                circle((-0.2, -0.2), 0.5),
+                Line((0.15, 0.15), (0.8, 0.8)),
+                Line((0.8, 0.8), (0.35, 0.8)),
+                Line((0.8, 0.8), (0.8, 0.35)),
+            ]
+        },
+        "Jupiter" => {
+            vec![
+                Arc((-0.3, 0.5), 0.4, (-60.0, 160.0)),
+                Line((-0.1, 0.15), (-0.7, -0.3)),
+                Line((-0.7, -0.3), (0.8, -0.3)),
+                Line((0.35, 0.9), (0.35, -1.0)),
+            ]
+        },
+        "Saturn" => {
+            vec![
+                Line((-0.35, 0.9), (-0.35, -0.3)),
+                Line((-0.65, 0.55), (-0.05, 0.55)),
+                Arc((0.05, -0.1), 0.4, (0.0, 180.0)),
+                Line((0.45, -0.1), (0.2, -0.9)),
+            ]
+        },
+        "Uranus" => {
+            vec![
+                circle((0.0, -0.65), 0.28),
+                Line((0.0, -0.37), (0.0, 0.9)),
+                Line((-0.6, 0.9), (-0.6, 0.1)),
+                Line((0.6, 0.9), (0.6, 0.1)),
+                Line((-0.6, 0.5), (0.6, 0.5)),
+            ]
+        },
+        "Neptune" => {
+            vec![
+                Arc((0.0, 0.5), 0.6, (180.0, 360.0)),
+                Line((0.0, 1.0), (0.0, -1.0)),
+                Line((-0.4, -0.55), (0.4, -0.55)),
+            ]
+        },
+        "Pluto" => {
+            vec![
+                circle((0.0, 0.55), 0.3),
+                Arc((0.0, 0.45), 0.55, (180.0, 360.0)),
+                Line((0.0, -0.1), (0.0, -1.0)),
+                Line((-0.35, -0.6), (0.35, -0.6)),
+            ]
+        },
+        "Dragon's Head" => {
+            vec![
+                Arc((0.0, 0.15), 0.5, (0.0, 180.0)),
+                Line((-0.5, 0.15), (-0.5, -0.35)),
+                Line((0.5, 0.15), (0.5, -0.35)),
+                circle((-0.5, -0.6), 0.25),
+                circle((0.5, -0.6), 0.25),
+            ]
+        },
+        "Dragon's Tail" => {
+            vec![
+                Arc((0.0, -0.15), 0.5, (180.0, 360.0)),
+                Line((-0.5, -0.15), (-0.5, 0.35)),
+                Line((0.5, -0.15), (0.5, 0.35)),
+                circle((-0.5, 0.6), 0.25),
+                circle((0.5, 0.6), 0.25),
+            ]
+        },
+        "Earth" => {
+            vec![
+                circle((0.0, 0.0), 0.8),
+                Line((-0.8, 0.0), (0.8, 0.0)),
+                Line((0.0, -0.8), (0.0, 0.8)),
+            ]
+        },
+        _ => vec![Dot((0.0, 0.0), 0.4)],
+    }
+}
+
+/// Draw a planet glyph of half-width `size` centred on `c`
+fn draw_glyph(img: &mut RgbaImage, planet: &str, c: (f64, f64), size: f64, color: Rgba<u8>) {
+    let at = |(x, y): (f64, f64)| (c.0 + x * size, c.1 - y * size);
+    let width = (size / 6.0).max(1.5);
+
+    for stroke in glyph_strokes(planet) {
+        match stroke {
+            Stroke::Line(a, b) => draw_line(img, at(a), at(b), width, color),
+            Stroke::Arc(centre, r, angles) => {
+                draw_arc(img, at(centre), r * size, angles, width, color)
+            },
+            Stroke::Dot(centre, r) => draw_disc(img, at(centre), r * size, color),
+        }
+    }
+}
+
+/// Draw the wheel as a PNG, with bars for the busy decans and glyphs for individual cards
+pub fn render_wheel_png(view: &WheelView) -> Result<Vec<u8>, String> {
+    let mut img: RgbaImage = ImageBuffer::from_pixel(SIZE, SIZE, BACKGROUND);
+    let centre = f64::from(SIZE) / 2.0;
+    let max = f64::from(view.max_count().max(1));
+
+    // Tint the sign ring by element and fill a bar outward from the hub for each decan
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        let (dx, dy) = (f64::from(x) - centre, centre - f64::from(y));
+        let r = (dx * dx + dy * dy).sqrt();
+        let longitude = (dy.atan2(dx).to_degrees() - 180.0).rem_euclid(360.0);
+
+        if (SIGN_RING..=OUTER).contains(&r) {
+            *pixel = ELEMENT_TINTS[(longitude / 30.0) as usize % 4];
+        } else if r >= HUB && r < SIGN_RING {
+            let count = view.decans[(longitude / 10.0) as usize % 36];
+            if r - HUB <= BAR * f64::from(count) / max {
+                *pixel = LIT;
+            }
+        }
+    }
+
+    for r in [HUB, SIGN_RING, OUTER] {
+        draw_arc(&mut img, (centre, centre), r, (0.0, 360.0), 2.0, DIM);
+    }
+
+    for decan in 0..36 {
+        let longitude = decan as f64 * 10.0;
+        let (inner, width) = if decan % 3 == 0 {
+            (HUB, 2.0)
+        } else {
+            (SIGN_RING, 1.0)
+        };
+        draw_line(&mut img, to_pixels(longitude, inner), to_pixels(longitude, OUTER), width, DIM);
+    }
+
+    // Cards sharing a decan are stacked towards the hub
+    let mut stacked = [0; 36];
+    for placement in &view.placements {
+        let decan = (placement.longitude() / 10.0) as usize % 36;
+        let r = SIGN_RING - 30.0 - 36.0 * f64::from(stacked[decan]);
+        stacked[decan] += 1;
+
+        let c = to_pixels(placement.longitude(), r);
+        draw_disc(&mut img, c, 15.0, BACKGROUND);
+        draw_glyph(&mut img, placement.planet.as_deref().unwrap_or_default(), c, 11.0, GLYPH);
+    }
+
+    // Planets of the cards with no place on the wheel sit in a row across the hub
+    let planets: Vec<&str> = view.unplaced.iter().filter_map(|(_, p)| p.as_deref()).collect();
+    let start = centre - 15.0 * (planets.len() as f64 - 1.0);
+    for (i, planet) in planets.iter().enumerate() {
+        draw_glyph(&mut img, planet, (start + 30.0 * i as f64, centre), 11.0, GLYPH);
+    }
+
+    let mut png_bytes: Vec<u8> = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut png_bytes), image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+
+    Ok(png_bytes)
+}
+
+/// Render the wheel, ready to upload
+pub fn wheel_upload(view: &WheelView) -> Result<ImageUpload, String> {
+    ImageUpload::from_bytes("tarot_wheel.png", mime::IMAGE_PNG, render_wheel_png(view)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tarot_api::CardInReading;
+    use crate::tarot_cards::find_card;
+
+    fn cards(names: &[&str]) -> Vec<&'static TarotCard> {
+        names.iter().map(|name| find_card(name).unwrap()).collect()
+    }
+
+    fn reading(date: &str, names: &[&str]) -> ReadingHistoryItem {
+        ReadingHistoryItem {
+            reading_id: 0,
+            reading_date: format!("{}T12:00:00", date),
+            spread_type: names.len().to_string(),
+            card_count: names.len(),
+            cards: names
+                .iter()
+                .enumerate()
+                .map(|(i, name)| {
+                    CardInReading {
+                        position: i as i32,
+                        card_name: find_card(name).unwrap().card.clone(),
+                        label: None,
+                        reversed: false,
+                        dignity: None,
+                    }
+                })
+                .collect(),
+            notes: None,
+            question: None,
+            room_id: None,
+            quintessence: None,
+            moon_phase: None,
+            sun_sign: None,
+        }
+    }
+
+    #[test]
+    fn test_card_placement() {
+        let six = card_placement(find_card("six of swords").unwrap()).unwrap();
+        assert_eq!((six.from, six.to), (310.0, 320.0));
+        assert_eq!(six.planet.as_deref(), Some("Mercury"));
+        assert_eq!(six.describe(), "Aquarius II (10°–20°)");
+        assert_eq!(six.decans().collect::<Vec<_>>(), vec![31]);
+
+        let knight = card_placement(find_card("knight of swords").unwrap()).unwrap();
+        assert_eq!(knight.longitude(), 65.0);
+        assert_eq!(knight.describe(), "20° Taurus – 20° Gemini");
+
+        let star = card_placement(find_card("the star").unwrap()).unwrap();
+        assert_eq!(star.describe(), "Aquarius");
+
+        // Princess of Cups covers Libra to Sagittarius
+        let princess = card_placement(find_card("princess of cups").unwrap()).unwrap();
+        assert_eq!(princess.describe(), "Libra to Sagittarius");
+
+        // The Queen of Wands rules from Pisces across 0° Aries
+        let queen = card_placement(find_card("queen of wands").unwrap()).unwrap();
+        assert_eq!(queen.decans().collect::<Vec<_>>(), vec![35, 0, 1]);
+
+        assert_eq!(card_placement(find_card("the magus").unwrap()), None);
+        assert_eq!(card_placement(find_card("ace of cups").unwrap()), None);
+    }
+
+    #[test]
+    fn test_decans() {
+        assert_eq!(decan_name(0), "Aries I");
+        assert_eq!(decan_name(31), "Aquarius II");
+        assert_eq!(decan_card(31).unwrap().card, "Six of Swords");
+        assert_eq!(decan_card(0).unwrap().card, "Two of Wands");
+        assert_eq!(decan_card(35).unwrap().card, "Ten of Cups");
+
+        // Every small card rules exactly one decan
+        let mut ruled: Vec<String> =
+            (0..36).filter_map(decan_card).map(|c| c.card.clone()).collect();
+        ruled.sort();
+        ruled.dedup();
+        assert_eq!(ruled.len(), 36);
+    }
+
+    #[test]
+    fn test_reading_wheel() {
+        let view =
+            reading_wheel("Reading".into(), &cards(&["six of swords", "the magus", "ace of cups"]));
+        assert_eq!(view.placed, 1);
+        assert_eq!(view.decans[31], 1);
+        assert_eq!(view.max_count(), 1);
+
+        let legend = view.legend();
+        assert!(legend.contains("☿ Six of Swords: Aquarius II (10°–20°)\n"));
+        assert!(legend.contains("Off the wheel: ☿ The Magus, Ace of Cups\n"));
+    }
+
+    #[test]
+    fn test_history_wheel() {
+        let readings = vec![
+            reading("2026-01-05", &["six of swords", "the star"]),
+            reading("2026-02-10", &["six of swords", "ace of wands"]),
+            reading("2026-03-15", &["six of swords"]),
+        ];
+
+        let view = history_wheel(&readings, &ReadingFilter::default());
+        assert_eq!(view.readings, 3);
+        assert_eq!((view.placed, view.off_wheel), (4, 1));
+        assert!(view.unplaced.is_empty());
+        assert_eq!(view.decans[31], 4);
+        assert_eq!(view.decans[30], 1);
+        assert!(view.placements.is_empty());
+
+        let legend = view.legend();
+        assert!(legend.contains("3 readings: 4 cards on the wheel, 1 off it\n"));
+        assert!(legend.contains("♒ Aquarius: 6\n"));
+        assert!(legend.contains("Aquarius II (Six of Swords): 4\n"));
+
+        let since = chrono::NaiveDate::from_ymd_opt(2026, 2, 1);
+        let view = history_wheel(&readings, &ReadingFilter { since, ..Default::default() });
+        assert_eq!(view.readings, 2);
+        assert_eq!(view.decans[31], 2);
+    }
+
+    #[test]
+    fn test_render_wheel_png() {
+        let view = reading_wheel("Reading".into(), &cards(&["two of wands"]));
+        let png = render_wheel_png(&view).unwrap();
+        let img = image::load_from_memory(&png).unwrap().to_rgba8();
+
+        // Two of Wands fills the bar for the first decan of Aries
+        let (x, y) = to_pixels(5.0, HUB + 10.0);
+        assert_eq!(*img.get_pixel(x as u32, y as u32), LIT);
+
+        // Nothing was drawn in Libra
+        let (x, y) = to_pixels(185.0, HUB + 10.0);
+        assert_eq!(*img.get_pixel(x as u32, y as u32), BACKGROUND);
+    }
+}
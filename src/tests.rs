@@ -1,9 +1,16 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+use matrix_sdk::room::reply::EnforceThread;
 use matrix_sdk::ruma::{
     event_id,
-    events::room::message::{OriginalRoomMessageEvent, RoomMessageEventContent},
+    events::relation::{InReplyTo, Thread},
+    events::room::message::{
+        OriginalRoomMessageEvent,
+        Relation,
+        ReplyWithinThread,
+        RoomMessageEventContent,
+    },
     server_name,
     user_id,
     EventId,
@@ -40,12 +47,16 @@ use crate::{
         Message,
         MessageEvent,
         MessageKey,
+        MessageRelation,
         MessageTimeStamp::{LocalEcho, OriginServer},
         Messages,
     },
     worker::Requester,
 };
 
+pub mod homeserver;
+pub mod tarot_server;
+
 const TEST_ROOM1_ALIAS: &str = "#room1:example.com";
 
 lazy_static! {
@@ -67,6 +78,8 @@ lazy_static! {
     pub static ref MSG3_KEY: MessageKey = (OriginServer(UInt::new(2).unwrap()), MSG3_EVID.clone());
     pub static ref MSG4_KEY: MessageKey = (OriginServer(UInt::new(2).unwrap()), MSG4_EVID.clone());
     pub static ref MSG5_KEY: MessageKey = (OriginServer(UInt::new(8).unwrap()), MSG5_EVID.clone());
+    pub static ref MSG6_EVID: OwnedEventId = EventId::new(server_name!("example.com"));
+    pub static ref MSG6_KEY: MessageKey = (OriginServer(UInt::new(9).unwrap()), MSG6_EVID.clone());
 }
 
 pub fn user_style(user: &str) -> Style {
@@ -124,6 +137,15 @@ pub fn mock_message5() -> Message {
     mock_room1_message(content, TEST_USER2.clone(), MSG4_KEY.clone())
 }
 
+/// A reply in the thread started by [mock_message3].
+pub fn mock_thread_message() -> Message {
+    let mut content = RoomMessageEventContent::text_plain("in a thread");
+    content.relates_to =
+        Some(Relation::Thread(Thread::plain(MSG3_EVID.clone(), MSG3_EVID.clone())));
+
+    mock_room1_message(content, TEST_USER1.clone(), MSG6_KEY.clone())
+}
+
 pub fn mock_keys() -> HashMap<OwnedEventId, EventLocation> {
     let mut keys = HashMap::new();
 
@@ -201,6 +223,7 @@ pub fn mock_tunables() -> TunableValues {
         image_preview: None,
         user_gutter_width: 30,
         tabstop: 4,
+        tarot: Default::default(),
     }
 }
 
@@ -211,6 +234,9 @@ pub fn mock_settings() -> ApplicationSettings {
         session_json_old: PathBuf::new(),
         sled_dir: PathBuf::new(),
         sqlite_dir: PathBuf::new(),
+        tarot_learn_json: PathBuf::new(),
+        tarot_annotations_json: PathBuf::new(),
+        tarot_history_db: PathBuf::new(),
 
         profile_name: "test".into(),
         profile: ProfileConfig {
@@ -251,3 +277,105 @@ pub async fn mock_store() -> ProgramStore {
 
     ProgramStore::new(store)
 }
+
+fn original(msg: &Message) -> &OriginalRoomMessageEvent {
+    match &msg.event {
+        MessageEvent::Original(ev) => ev,
+        _ => panic!("expected an original event"),
+    }
+}
+
+#[test]
+fn test_relation_main_timeline() {
+    let info = mock_room();
+    let relation = MessageRelation::new(None, None, None, &info);
+    assert_eq!(relation, MessageRelation::None);
+
+    let msg = relation.apply(RoomMessageEventContent::text_plain("hello"));
+    assert!(msg.relates_to.is_none());
+    assert!(relation.attachment_reply().unwrap().is_none());
+    assert!(relation.follow_up(MSG6_EVID.clone()).is_none());
+}
+
+#[test]
+fn test_relation_editing() {
+    let info = mock_room();
+    let reply_to = mock_message2();
+    let relation =
+        MessageRelation::new(Some(&MSG4_EVID), Some(&MSG3_EVID), Some(original(&reply_to)), &info);
+    assert_eq!(relation, MessageRelation::Edit(MSG4_EVID.clone()));
+
+    let msg = relation.apply(RoomMessageEventContent::text_plain("fixed"));
+    assert!(matches!(msg.relates_to, Some(Relation::Replacement(r)) if r.event_id == *MSG4_EVID));
+
+    // Attachments can't replace a message.
+    assert!(relation.attachment_reply().is_err());
+}
+
+#[test]
+fn test_relation_reply() {
+    let info = mock_room();
+    let reply_to = mock_message2();
+    let relation = MessageRelation::new(None, None, Some(original(&reply_to)), &info);
+
+    let msg = relation.apply(RoomMessageEventContent::text_plain("answer"));
+    let Some(Relation::Reply { in_reply_to: InReplyTo { event_id, .. } }) = msg.relates_to else {
+        panic!("expected a reply");
+    };
+    assert_eq!(event_id, *MSG2_EVID);
+
+    let reply = relation.attachment_reply().unwrap().unwrap();
+    assert_eq!(reply.event_id, *MSG2_EVID);
+    assert_eq!(reply.enforce_thread, EnforceThread::MaybeThreaded);
+
+    // Text after an attachment follows it in the main timeline.
+    assert!(relation.follow_up(MSG6_EVID.clone()).is_none());
+}
+
+#[test]
+fn test_relation_thread() {
+    let mut info = mock_room();
+
+    // A new thread follows on from its root.
+    let relation = MessageRelation::new(None, Some(&MSG3_EVID), None, &info);
+    let reply = relation.attachment_reply().unwrap().unwrap();
+    assert_eq!(reply.event_id, *MSG3_EVID);
+    assert_eq!(reply.enforce_thread, EnforceThread::Threaded(ReplyWithinThread::No));
+
+    // Once it has replies, messages follow on from the latest.
+    info.get_thread_mut(Some(MSG3_EVID.clone()))
+        .insert(MSG6_KEY.clone(), mock_thread_message());
+    let relation = MessageRelation::new(None, Some(&MSG3_EVID), None, &info);
+    let reply = relation.attachment_reply().unwrap().unwrap();
+    assert_eq!(reply.event_id, *MSG6_EVID);
+
+    let msg = relation.apply(RoomMessageEventContent::text_plain("more"));
+    let Some(Relation::Thread(thread)) = msg.relates_to else {
+        panic!("expected a thread message");
+    };
+    assert_eq!(thread.event_id, *MSG3_EVID);
+    assert_eq!(thread.in_reply_to.unwrap().event_id, *MSG6_EVID);
+    assert!(thread.is_falling_back);
+
+    // Text after an attachment stays in the thread, following the attachment.
+    let attachment = EventId::new(server_name!("example.com"));
+    let Some(Relation::Thread(thread)) = relation.follow_up(attachment.clone()) else {
+        panic!("expected a thread relation");
+    };
+    assert_eq!(thread.event_id, *MSG3_EVID);
+    assert_eq!(thread.in_reply_to.unwrap().event_id, attachment);
+
+    // Replying to a message inside the thread.
+    let reply_to = mock_thread_message();
+    let relation = MessageRelation::new(None, Some(&MSG3_EVID), Some(original(&reply_to)), &info);
+    let reply = relation.attachment_reply().unwrap().unwrap();
+    assert_eq!(reply.event_id, *MSG6_EVID);
+    assert_eq!(reply.enforce_thread, EnforceThread::Threaded(ReplyWithinThread::Yes));
+
+    let msg = relation.apply(RoomMessageEventContent::text_plain("quoted"));
+    let Some(Relation::Thread(thread)) = msg.relates_to else {
+        panic!("expected a thread message");
+    };
+    assert_eq!(thread.event_id, *MSG3_EVID);
+    assert!(!thread.is_falling_back);
+}
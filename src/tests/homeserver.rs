@@ -0,0 +1,322 @@
+//! A stand-in for a homeserver's account data and messaging APIs, served on an ephemeral
+//! local port
+//!
+//! It keeps whatever is stored in account data and sent to rooms, so that what a client stores
+//! and sends can be checked afterwards, and serves the earlier messages a test adds for the
+//! client to reply to. Like a real homeserver, it turns away events over the size limit.
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use matrix_sdk::authentication::matrix::MatrixSession;
+use matrix_sdk::config::SyncSettings;
+use matrix_sdk::ruma::RoomId;
+use matrix_sdk::{Client, Room};
+use serde_json::{json, Value};
+
+/// The largest event a homeserver accepts, in bytes
+pub const MAX_EVENT_BYTES: usize = 65536;
+
+/// The largest file the media repository accepts, in bytes
+const MAX_UPLOAD_BYTES: usize = 50 * 1024 * 1024;
+
+/// Account data by where it was stored: `None` for the account's, or a room's ID
+type AccountData = HashMap<(Option<String>, String), Value>;
+
+#[derive(Default)]
+struct State {
+    account_data: AccountData,
+    /// Rooms reported as joined when a client syncs
+    joined: Vec<String>,
+    /// Events that can be fetched, by room and event ID
+    events: HashMap<(String, String), Value>,
+    /// Each message sent, with the room it was sent to and the ID it was given
+    sent: Vec<(String, String, Value)>,
+    /// Whether uploads to the media repository are turned away
+    refuse_uploads: bool,
+}
+
+pub struct Homeserver {
+    pub url: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl Homeserver {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("can bind an ephemeral port");
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(State::default()));
+
+        let shared = state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let state = shared.clone();
+                thread::spawn(move || serve(stream, &state));
+            }
+        });
+
+        Homeserver { url, state }
+    }
+
+    /// A client with a session on the stand-in for `user_id`
+    pub async fn client(&self, user_id: &str) -> Client {
+        let client = Client::builder().homeserver_url(&self.url).build().await.unwrap();
+        let session: MatrixSession = serde_json::from_value(json!({
+            "access_token": "stand-in-token",
+            "user_id": user_id,
+            "device_id": "STANDIN",
+        }))
+        .unwrap();
+
+        client.restore_session(session).await.unwrap();
+        client
+    }
+
+    /// Join `client` to a room, and sync so that it knows about it
+    pub async fn join(&self, client: &Client, room_id: &str) -> Room {
+        self.state.lock().unwrap().joined.push(room_id.to_string());
+        client.sync_once(SyncSettings::default()).await.unwrap();
+
+        client.get_room(&RoomId::parse(room_id).unwrap()).unwrap()
+    }
+
+    /// Add a text message that was sent before the client joined, so that it can be fetched
+    pub fn add_message(&self, room_id: &str, event_id: &str, sender: &str, body: &str) {
+        let event = json!({
+            "type": "m.room.message",
+            "event_id": event_id,
+            "room_id": room_id,
+            "sender": sender,
+            "origin_server_ts": 0,
+            "content": {"msgtype": "m.text", "body": body},
+        });
+        let key = (room_id.to_string(), event_id.to_string());
+
+        self.state.lock().unwrap().events.insert(key, event);
+    }
+
+    /// Turn away every upload from now on, as too large to store
+    pub fn refuse_uploads(&self) {
+        self.state.lock().unwrap().refuse_uploads = true;
+    }
+
+    /// The content of every message sent to a room, in the order they were sent
+    pub fn sent(&self, room_id: &str) -> Vec<Value> {
+        let state = self.state.lock().unwrap();
+
+        state
+            .sent
+            .iter()
+            .filter(|(room, _, _)| room == room_id)
+            .map(|(_, _, content)| content.clone())
+            .collect()
+    }
+
+    /// The ID given to every message sent to a room, in the order they were sent
+    pub fn sent_ids(&self, room_id: &str) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+
+        state
+            .sent
+            .iter()
+            .filter(|(room, _, _)| room == room_id)
+            .map(|(_, event_id, _)| event_id.clone())
+            .collect()
+    }
+
+    /// The content stored under an event type, in a room's account data or the account's
+    pub fn account_data(&self, room_id: Option<&str>, event_type: &str) -> Option<Value> {
+        let key = (room_id.map(str::to_string), event_type.to_string());
+
+        self.state.lock().unwrap().account_data.get(&key).cloned()
+    }
+
+    /// Every event type stored in a room's account data or the account's
+    pub fn event_types(&self, room_id: Option<&str>) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        let mut types: Vec<String> = state
+            .account_data
+            .keys()
+            .filter(|(room, _)| room.as_deref() == room_id)
+            .map(|(_, event_type)| event_type.clone())
+            .collect();
+        types.sort();
+        types
+    }
+}
+
+/// Read a request, returning its method, path and body
+pub fn read_request(stream: &TcpStream) -> Option<(String, String, Vec<u8>)> {
+    let mut reader = BufReader::new(stream);
+
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).ok()?;
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().ok()?;
+            }
+        }
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+
+    Some((method, path, body))
+}
+
+pub fn respond(mut stream: TcpStream, status: u16, body: &str) {
+    let reason = match status {
+        200 => "OK",
+        404 => "Not Found",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        _ => "Error",
+    };
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+
+    let _ = stream.write_all(response.as_bytes());
+}
+
+/// Undo the percent-encoding of a path segment
+fn decode(segment: &str) -> String {
+    let mut bytes = vec![];
+    let mut iter = segment.bytes();
+
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hex = [iter.next().unwrap(), iter.next().unwrap()];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).unwrap(), 16).unwrap());
+        } else {
+            bytes.push(byte);
+        }
+    }
+
+    String::from_utf8(bytes).unwrap()
+}
+
+fn error(errcode: &str, message: &str) -> String {
+    json!({"errcode": errcode, "error": message}).to_string()
+}
+
+fn serve(stream: TcpStream, state: &Mutex<State>) {
+    let Some((method, path, body)) = read_request(&stream) else {
+        return;
+    };
+
+    let path = path.split('?').next().unwrap_or_default();
+    if path == "/_matrix/media/v3/upload" {
+        if state.lock().unwrap().refuse_uploads {
+            return respond(stream, 413, &error("M_TOO_LARGE", "Upload too large"));
+        }
+
+        let uploaded = json!({"content_uri": "mxc://example.com/upload"});
+        return respond(stream, 200, &uploaded.to_string());
+    }
+
+    let path = path.trim_start_matches("/_matrix/client/");
+    let segments: Vec<String> = path.split('/').map(decode).collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    match segments.as_slice() {
+        ["versions"] => {
+            let versions = json!({"versions": ["v1.1", "v1.5", "v1.11"], "unstable_features": {}});
+            respond(stream, 200, &versions.to_string())
+        },
+        ["v1", "media", "config"] => {
+            let config = json!({"m.upload.size": MAX_UPLOAD_BYTES});
+            respond(stream, 200, &config.to_string())
+        },
+        ["v3", "sync"] => {
+            let joined: serde_json::Map<String, Value> = state
+                .lock()
+                .unwrap()
+                .joined
+                .iter()
+                .map(|room_id| (room_id.clone(), json!({})))
+                .collect();
+            let sync = json!({"next_batch": "stand-in", "rooms": {"join": joined}});
+            respond(stream, 200, &sync.to_string())
+        },
+        ["v3", "rooms", room_id, "send", _, txn_id] if method == "PUT" => {
+            let Ok(content) = serde_json::from_slice::<Value>(&body) else {
+                return respond(stream, 400, &error("M_NOT_JSON", "Content is not JSON"));
+            };
+
+            let event_id = format!("${txn_id}");
+            let sent = json!({"event_id": event_id});
+            state.lock().unwrap().sent.push((room_id.to_string(), event_id, content));
+            respond(stream, 200, &sent.to_string())
+        },
+        ["v3", "rooms", room_id, "event", event_id] => {
+            let key = (room_id.to_string(), event_id.to_string());
+
+            match state.lock().unwrap().events.get(&key) {
+                Some(event) => respond(stream, 200, &event.to_string()),
+                None => respond(stream, 404, &error("M_NOT_FOUND", "Event not found")),
+            }
+        },
+        ["v3", "rooms", _, "state", ..] => {
+            respond(stream, 404, &error("M_NOT_FOUND", "Event not found"))
+        },
+        ["v3", "user", _, "account_data", event_type] => {
+            let key = (None, event_type.to_string());
+            serve_account_data(stream, state, &method, key, &body)
+        },
+        ["v3", "user", _, "rooms", room_id, "account_data", event_type] => {
+            let key = (Some(room_id.to_string()), event_type.to_string());
+            serve_account_data(stream, state, &method, key, &body)
+        },
+        _ => respond(stream, 404, &error("M_UNRECOGNIZED", "Unrecognized request")),
+    }
+}
+
+fn serve_account_data(
+    stream: TcpStream,
+    state: &Mutex<State>,
+    method: &str,
+    key: (Option<String>, String),
+    body: &[u8],
+) {
+    match method {
+        "GET" => {
+            match state.lock().unwrap().account_data.get(&key) {
+                Some(content) => respond(stream, 200, &content.to_string()),
+                None => respond(stream, 404, &error("M_NOT_FOUND", "Account data not found")),
+            }
+        },
+        "PUT" if body.len() > MAX_EVENT_BYTES => {
+            respond(stream, 413, &error("M_TOO_LARGE", "Event too large"))
+        },
+        "PUT" => {
+            let Ok(content) = serde_json::from_slice::<Value>(body) else {
+                return respond(stream, 400, &error("M_NOT_JSON", "Content is not JSON"));
+            };
+
+            state.lock().unwrap().account_data.insert(key, content);
+            respond(stream, 200, "{}")
+        },
+        _ => respond(stream, 405, &error("M_UNRECOGNIZED", "Method not allowed")),
+    }
+}
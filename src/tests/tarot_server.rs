@@ -0,0 +1,232 @@
+//! A stand-in for the tarot API, served on an ephemeral local port
+//!
+//! It answers the routes [HttpStore](crate::tarot_api::HttpStore) uses with fixture data,
+//! keeps the readings saved to it, and can be told to fail in the ways a real server does.
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use super::homeserver::{read_request, respond};
+
+/// The user the fixture readings belong to
+pub const FIXTURE_USER: &str = "@querent:example.com";
+
+/// How the stand-in misbehaves
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Fault {
+    /// Answer every request with this status
+    Status(u16),
+    /// Answer successfully, but with a body that isn't JSON
+    MalformedJson,
+    /// Wait this long before answering
+    Stall(Duration),
+}
+
+#[derive(Default)]
+struct State {
+    /// Saved readings as they will be listed, oldest first
+    readings: Vec<Value>,
+    fault: Option<Fault>,
+}
+
+pub struct StandIn {
+    pub base_url: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl StandIn {
+    /// Start serving, with two fixture readings for [FIXTURE_USER]
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("can bind an ephemeral port");
+        let base_url = format!("http://{}/api", listener.local_addr().unwrap());
+
+        let readings = vec![
+            fixture_reading(1, "2026-03-01T09:30:00.123456", "3", &[
+                "Six of Swords",
+                "The Star",
+                "Ace of Cups",
+            ]),
+            fixture_reading(2, "2026-03-02T21:15:00.654321", "daily", &["The Fool"]),
+        ];
+        let state = Arc::new(Mutex::new(State { readings, fault: None }));
+
+        let shared = state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let state = shared.clone();
+                thread::spawn(move || serve(stream, &state));
+            }
+        });
+
+        StandIn { base_url, state }
+    }
+
+    /// Misbehave from now on
+    pub fn fail(&self, fault: Fault) {
+        self.state.lock().unwrap().fault = Some(fault);
+    }
+
+    /// Every reading posted to `/readings`, as it was sent
+    pub fn saved(&self) -> Vec<Value> {
+        let state = self.state.lock().unwrap();
+
+        state
+            .readings
+            .iter()
+            .filter(|r| r["fixture"] != true)
+            .map(|r| r["posted"].clone())
+            .collect()
+    }
+}
+
+fn fixture_reading(id: i64, date: &str, spread: &str, cards: &[&str]) -> Value {
+    let cards: Vec<Value> = cards
+        .iter()
+        .enumerate()
+        .map(|(i, name)| json!({"position": i, "card_name": name, "label": format!("Card {}", i + 1)}))
+        .collect();
+
+    json!({
+        "fixture": true,
+        "matrix_id": FIXTURE_USER,
+        "reading_id": id,
+        "spread_type": spread,
+        "reading_date": date,
+        "card_count": cards.len(),
+        "cards": cards,
+        "notes": null,
+        "question": "Should I take the job?",
+    })
+}
+
+/// A reading as the history lists it
+fn history_item(reading: &Value) -> Value {
+    let mut item = reading.clone();
+    let obj = item.as_object_mut().unwrap();
+    obj.remove("fixture");
+    obj.remove("posted");
+    obj.remove("matrix_id");
+    item
+}
+
+fn serve(stream: TcpStream, state: &Mutex<State>) {
+    let Some((method, path, body)) = read_request(&stream) else {
+        return;
+    };
+
+    let fault = state.lock().unwrap().fault;
+    match fault {
+        Some(Fault::Status(status)) => {
+            return respond(stream, status, r#"{"detail": "Internal error"}"#)
+        },
+        Some(Fault::MalformedJson) => return respond(stream, 200, "<html>Bad gateway</html>"),
+        Some(Fault::Stall(delay)) => thread::sleep(delay),
+        None => {},
+    }
+
+    let (status, body) = route(&method, &path, &body, &mut state.lock().unwrap());
+    respond(stream, status, &body.to_string());
+}
+
+fn route(method: &str, path: &str, body: &[u8], state: &mut State) -> (u16, Value) {
+    let segments: Vec<&str> = path.trim_start_matches("/api/").split('/').collect();
+
+    match (method, segments.as_slice()) {
+        ("POST", ["readings"]) => {
+            let Ok(posted) = serde_json::from_slice::<Value>(body) else {
+                return (422, json!({"detail": "Invalid reading"}));
+            };
+
+            let reading_id = state.readings.len() as i64 + 1;
+            let cards: Vec<Value> = posted["cards"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|card| {
+                    json!({
+                        "position": card["position"],
+                        "card_name": card["card_name"],
+                        "label": card["card_label"],
+                        "reversed": card["is_reversed"],
+                        "dignity": card["dignity"],
+                    })
+                })
+                .collect();
+
+            state.readings.push(json!({
+                "posted": posted,
+                "matrix_id": posted["matrix_id"],
+                "reading_id": reading_id,
+                "spread_type": posted["spread_type"],
+                "reading_date": posted["reading_date"].as_str().unwrap_or("2026-04-01T12:00:00"),
+                "card_count": cards.len(),
+                "cards": cards,
+                "notes": posted["notes"],
+                "question": posted["question"],
+            }));
+
+            (200, json!({"reading_id": reading_id}))
+        },
+        ("GET", ["readings", "user", user, "history"]) => {
+            let readings: Vec<Value> = state
+                .readings
+                .iter()
+                .rev()
+                .filter(|r| r["matrix_id"] == *user)
+                .map(history_item)
+                .collect();
+
+            (200, json!({"total_readings": readings.len(), "readings": readings}))
+        },
+        ("GET", ["readings", id, "details"]) => {
+            let id: i64 = id.parse().unwrap_or_default();
+            let Some(reading) = state.readings.iter().find(|r| r["reading_id"] == id) else {
+                return (404, json!({"detail": "Reading not found"}));
+            };
+
+            let cards: Vec<Value> = reading["cards"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|card| {
+                    let mut card = card.clone();
+                    card["info"] = json!("Fixture meaning");
+                    card["deepinfo"] = Value::Null;
+                    card
+                })
+                .collect();
+
+            let mut details = history_item(reading);
+            details["cards"] = json!(cards);
+            details["numerology"] = json!("Quintessence: The Star");
+            details["attributes"] = json!({"suit": {"Swords": 1, "Cups": 1}});
+
+            (200, details)
+        },
+        ("GET", ["analytics", "user", _, "attributes", attribute]) => {
+            let body = json!({
+                "attribute_type": attribute,
+                "total_count": 4,
+                "frequencies": {"Swords": 3, "Cups": 1},
+                "percentages": {"Swords": 75.0, "Cups": 25.0},
+            });
+
+            (200, body)
+        },
+        ("GET", ["analytics", "user", _, "summary"]) => {
+            let body = json!({
+                "total_readings": 2,
+                "total_cards_drawn": 4,
+                "spread_types": [{"type": "3", "count": 1}, {"type": "daily", "count": 1}],
+                "top_attributes": {"suit": [{"value": "Swords", "count": 3}]},
+            });
+
+            (200, body)
+        },
+        _ => (404, json!({"detail": "Not Found"})),
+    }
+}
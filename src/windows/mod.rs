@@ -80,11 +80,26 @@ use crate::base::{
     UnreadInfo,
 };
 
-use self::{room::RoomState, welcome::WelcomeState};
+use self::{
+    room::RoomState,
+    tarot_learn::TarotLearnState,
+    tarot_pick::TarotPickState,
+    tarot_search::TarotSearchState,
+    tarot_tree::TarotTreeState,
+    tarot_trends::TarotTrendsState,
+    tarot_wheel::TarotWheelState,
+    welcome::WelcomeState,
+};
 use crate::message::MessageTimeStamp;
 use feruca::Collator;
 
 pub mod room;
+pub mod tarot_learn;
+pub mod tarot_pick;
+pub mod tarot_search;
+pub mod tarot_tree;
+pub mod tarot_trends;
+pub mod tarot_wheel;
 pub mod welcome;
 
 type MatrixRoomInfo = Arc<(MatrixRoom, Option<Tags>)>;
@@ -330,6 +345,12 @@ macro_rules! delegate {
             IambWindow::Welcome($id) => $e,
             IambWindow::ChatList($id) => $e,
             IambWindow::UnreadList($id) => $e,
+            IambWindow::TarotTrends($id) => $e,
+            IambWindow::TarotTree($id) => $e,
+            IambWindow::TarotWheel($id) => $e,
+            IambWindow::TarotPick($id) => $e,
+            IambWindow::TarotLearn($id) => $e,
+            IambWindow::TarotSearch($id) => $e,
         }
     };
 }
@@ -344,6 +365,12 @@ pub enum IambWindow {
     Welcome(WelcomeState),
     ChatList(ChatListState),
     UnreadList(UnreadListState),
+    TarotTrends(TarotTrendsState),
+    TarotTree(TarotTreeState),
+    TarotWheel(TarotWheelState),
+    TarotPick(TarotPickState),
+    TarotLearn(TarotLearnState),
+    TarotSearch(TarotSearchState),
 }
 
 impl IambWindow {
@@ -458,6 +485,42 @@ impl From<WelcomeState> for IambWindow {
     }
 }
 
+impl From<TarotTrendsState> for IambWindow {
+    fn from(win: TarotTrendsState) -> Self {
+        IambWindow::TarotTrends(win)
+    }
+}
+
+impl From<TarotTreeState> for IambWindow {
+    fn from(win: TarotTreeState) -> Self {
+        IambWindow::TarotTree(win)
+    }
+}
+
+impl From<TarotWheelState> for IambWindow {
+    fn from(win: TarotWheelState) -> Self {
+        IambWindow::TarotWheel(win)
+    }
+}
+
+impl From<TarotPickState> for IambWindow {
+    fn from(win: TarotPickState) -> Self {
+        IambWindow::TarotPick(win)
+    }
+}
+
+impl From<TarotLearnState> for IambWindow {
+    fn from(win: TarotLearnState) -> Self {
+        IambWindow::TarotLearn(win)
+    }
+}
+
+impl From<TarotSearchState> for IambWindow {
+    fn from(win: TarotSearchState) -> Self {
+        IambWindow::TarotSearch(win)
+    }
+}
+
 impl Editable<ProgramContext, ProgramStore, IambInfo> for IambWindow {
     fn editor_command(
         &mut self,
@@ -683,6 +746,12 @@ impl WindowOps<IambInfo> for IambWindow {
                     .render(area, buf, state);
             },
             IambWindow::Welcome(state) => state.draw(area, buf, focused, store),
+            IambWindow::TarotTrends(state) => state.draw(area, buf, focused, store),
+            IambWindow::TarotTree(state) => state.draw(area, buf, focused, store),
+            IambWindow::TarotWheel(state) => state.draw(area, buf, focused, store),
+            IambWindow::TarotPick(state) => state.draw(area, buf, focused, store),
+            IambWindow::TarotLearn(state) => state.draw(area, buf, focused, store),
+            IambWindow::TarotSearch(state) => state.draw(area, buf, focused, store),
         }
     }
 
@@ -699,6 +768,12 @@ impl WindowOps<IambInfo> for IambWindow {
             IambWindow::Welcome(w) => w.dup(store).into(),
             IambWindow::ChatList(w) => w.dup(store).into(),
             IambWindow::UnreadList(w) => w.dup(store).into(),
+            IambWindow::TarotTrends(w) => w.dup(store).into(),
+            IambWindow::TarotTree(w) => w.dup(store).into(),
+            IambWindow::TarotWheel(w) => w.dup(store).into(),
+            IambWindow::TarotPick(w) => w.dup(store).into(),
+            IambWindow::TarotLearn(w) => w.dup(store).into(),
+            IambWindow::TarotSearch(w) => w.dup(store).into(),
         }
     }
 
@@ -740,6 +815,12 @@ impl Window<IambInfo> for IambWindow {
             IambWindow::Welcome(_) => IambId::Welcome,
             IambWindow::ChatList(_) => IambId::ChatList,
             IambWindow::UnreadList(_) => IambId::UnreadList,
+            IambWindow::TarotTrends(_) => IambId::TarotTrends,
+            IambWindow::TarotTree(_) => IambId::TarotTree,
+            IambWindow::TarotWheel(_) => IambId::TarotWheel,
+            IambWindow::TarotPick(_) => IambId::TarotPick,
+            IambWindow::TarotLearn(_) => IambId::TarotLearn,
+            IambWindow::TarotSearch(_) => IambId::TarotSearch,
         }
     }
 
@@ -752,6 +833,12 @@ impl Window<IambInfo> for IambWindow {
             IambWindow::Welcome(_) => bold_spans("Welcome to iamb"),
             IambWindow::ChatList(_) => bold_spans("DMs & Rooms"),
             IambWindow::UnreadList(_) => bold_spans("Unread Messages"),
+            IambWindow::TarotTrends(_) => bold_spans("Tarot Trends"),
+            IambWindow::TarotTree(_) => bold_spans("Tree of Life"),
+            IambWindow::TarotWheel(_) => bold_spans("Zodiac Wheel"),
+            IambWindow::TarotPick(_) => bold_spans("Pick Your Cards"),
+            IambWindow::TarotLearn(_) => bold_spans("Learn the Deck"),
+            IambWindow::TarotSearch(_) => bold_spans("Card Search"),
 
             IambWindow::Room(w) => {
                 let title = store.application.get_room_title(w.id());
@@ -780,6 +867,12 @@ impl Window<IambInfo> for IambWindow {
             IambWindow::Welcome(_) => bold_spans("Welcome to iamb"),
             IambWindow::ChatList(_) => bold_spans("DMs & Rooms"),
             IambWindow::UnreadList(_) => bold_spans("Unread Messages"),
+            IambWindow::TarotTrends(_) => bold_spans("Tarot Trends"),
+            IambWindow::TarotTree(_) => bold_spans("Tree of Life"),
+            IambWindow::TarotWheel(_) => bold_spans("Zodiac Wheel"),
+            IambWindow::TarotPick(_) => bold_spans("Pick Your Cards"),
+            IambWindow::TarotLearn(_) => bold_spans("Learn the Deck"),
+            IambWindow::TarotSearch(_) => bold_spans("Card Search"),
 
             IambWindow::Room(w) => w.get_title(store),
             IambWindow::MemberList(state, room_id, _) => {
@@ -846,6 +939,36 @@ impl Window<IambInfo> for IambWindow {
 
                 Ok(IambWindow::UnreadList(list))
             },
+            IambId::TarotTrends => {
+                let win = TarotTrendsState::new(store);
+
+                Ok(win.into())
+            },
+            IambId::TarotTree => {
+                let win = TarotTreeState::new(store);
+
+                Ok(win.into())
+            },
+            IambId::TarotWheel => {
+                let win = TarotWheelState::new(store);
+
+                Ok(win.into())
+            },
+            IambId::TarotPick => {
+                let win = TarotPickState::new(store);
+
+                Ok(win.into())
+            },
+            IambId::TarotLearn => {
+                let win = TarotLearnState::new(store);
+
+                Ok(win.into())
+            },
+            IambId::TarotSearch => {
+                let win = TarotSearchState::new(store);
+
+                Ok(win.into())
+            },
         }
     }
 
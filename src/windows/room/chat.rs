@@ -13,15 +13,15 @@ use tokio;
 use url::Url;
 
 use matrix_sdk::{
-    attachment::AttachmentConfig,
+    attachment::{AttachmentConfig, AttachmentInfo, BaseImageInfo, Thumbnail},
     media::{MediaFormat, MediaRequestParameters},
-    room::Room as MatrixRoom,
+    room::{reply::Reply, Room as MatrixRoom},
     ruma::{
         events::reaction::ReactionEventContent,
         events::relation::{Annotation, Replacement},
         events::room::message::{
             AddMentions,
-            ForwardThread,
+            ImageMessageEventContent,
             MessageType,
             OriginalRoomMessageEvent,
             Relation,
@@ -29,9 +29,13 @@ use matrix_sdk::{
             RoomMessageEventContent,
             TextMessageEventContent,
         },
+        events::room::{ImageInfo, MediaSource, ThumbnailInfo},
+        MilliSecondsSinceUnixEpoch,
         OwnedEventId,
         OwnedRoomId,
+        OwnedUserId,
         RoomId,
+        UInt,
     },
     RoomState,
 };
@@ -86,14 +90,18 @@ use crate::base::{
     SendAction,
 };
 
+use crate::image_upload::ImageUpload;
 use crate::message::{
     text_to_message,
+    text_to_message_content,
     Message,
     MessageEvent,
     MessageKey,
+    MessageRelation,
     MessageTimeStamp,
     TreeGenState,
 };
+use crate::tarot_spreads::SpreadThread;
 use crate::worker::Requester;
 
 use super::scrollback::{Scrollback, ScrollbackState};
@@ -166,6 +174,24 @@ impl ChatState {
         }
     }
 
+    /// How a message sent now relates to the others, given what the message bar is doing.
+    fn relation(&self, info: &RoomInfo) -> MessageRelation {
+        let editing = self.editing.as_ref().map(|(_, event_id)| event_id);
+
+        MessageRelation::new(editing, self.scrollback.thread(), self.get_reply_to(info), info)
+    }
+
+    /// The relation for a message that isn't the one being composed, which can follow the
+    /// thread or reply being composed, but never replaces the message being edited.
+    fn generated_relation(&self, info: &RoomInfo) -> MessageRelation {
+        MessageRelation::new(None, self.scrollback.thread(), self.get_reply_to(info), info)
+    }
+
+    /// The reply for a file uploaded with `:upload`, which is refused while editing a message.
+    fn attachment_reply(&self, info: &RoomInfo) -> IambResult<Option<Reply>> {
+        self.relation(info).attachment_reply().map_err(UIError::Failure)
+    }
+
     fn reset(&mut self) -> EditRope {
         self.reply_to = None;
         self.editing = None;
@@ -517,12 +543,13 @@ impl ChatState {
     pub async fn send_command(
         &mut self,
         act: SendAction,
-        _: ProgramContext,
+        ctx: ProgramContext,
         store: &mut ProgramStore,
     ) -> IambResult<EditInfo> {
         let room = self.get_joined(&store.application.worker)?;
         let info = store.application.rooms.get_or_default(self.id().to_owned());
         let mut show_echo = true;
+        let mut finale = vec![];
 
         let (event_id, msg) = match act {
             SendAction::Submit | SendAction::SubmitFromEditor => {
@@ -545,26 +572,9 @@ impl ChatState {
                     msg.trim_end().to_string()
                 };
 
-                let mut msg = text_to_message(msg);
-
-                if let Some((_, event_id)) = &self.editing {
-                    msg.relates_to = Some(Relation::Replacement(Replacement::new(
-                        event_id.clone(),
-                        msg.msgtype.clone().into(),
-                    )));
-
-                    show_echo = false;
-                } else if let Some(thread_root) = self.scrollback.thread() {
-                    if let Some(m) = self.get_reply_to(info) {
-                        msg = msg.make_for_thread(m, ReplyWithinThread::Yes, AddMentions::No);
-                    } else if let Some(m) = info.get_thread_last(thread_root) {
-                        msg = msg.make_for_thread(m, ReplyWithinThread::No, AddMentions::No);
-                    } else {
-                        // Internal state is wonky?
-                    }
-                } else if let Some(m) = self.get_reply_to(info) {
-                    msg = msg.make_reply_to(m, ForwardThread::Yes, AddMentions::No);
-                }
+                let relation = self.relation(info);
+                let msg = relation.apply(text_to_message(msg));
+                show_echo = !matches!(relation, MessageRelation::Edit(_));
 
                 // XXX: second parameter can be a locally unique transaction id.
                 // Useful for doing retries.
@@ -585,12 +595,13 @@ impl ChatState {
                     .file_name()
                     .map(OsStr::to_string_lossy)
                     .unwrap_or_else(|| Cow::from("Attachment"));
-                let config = AttachmentConfig::new();
+                let config = AttachmentConfig::new().reply(self.attachment_reply(info)?);
 
                 let resp = room
                     .send_attachment(name.as_ref(), &mime, bytes, config)
                     .await
                     .map_err(IambError::from)?;
+                self.reply_to = None;
 
                 // Mock up the local echo message for the scrollback.
                 let msg = TextMessageEventContent::plain(format!("[Attached File: {name}]"));
@@ -614,12 +625,14 @@ impl ChatState {
                 let mime = mime::IMAGE_PNG;
 
                 let name = "Clipboard.png";
-                let config = AttachmentConfig::new();
+                let reply = self.generated_relation(info).attachment_reply();
+                let config = AttachmentConfig::new().reply(reply.map_err(UIError::Failure)?);
 
                 let resp = room
                     .send_attachment(name, &mime, bytes, config)
                     .await
                     .map_err(IambError::from)?;
+                self.reply_to = None;
 
                 // Mock up the local echo message for the scrollback.
                 let msg = TextMessageEventContent::plain(format!("[Attached File: {name}]"));
@@ -628,38 +641,30 @@ impl ChatState {
 
                 (resp.event_id, msg)
             },
-            SendAction::TarotSpread(cards) => {
-                // Upload multiple tarot cards with labels
-                for (label, card_path) in cards {
-                    // Send label as text message
-                    if !label.is_empty() {
-                        let label_msg = text_to_message(label);
-                        let _ = room.send(label_msg).await.map_err(IambError::from)?;
-                    }
+            SendAction::TarotSpread(spread) => {
+                let SpreadThread { root, replies } = *spread;
+                let sender = store.application.settings.profile.user_id.clone();
+
+                let name = root.image.name.clone();
+                let msg = upload_image(&room, &root.image, &root.caption).await?;
+                let resp = room.send(msg.clone()).await.map_err(IambError::from)?;
+                let root_id = resp.event_id;
 
-                    // Upload the card image
-                    let path = Path::new(card_path.as_str());
-                    let mime = mime_guess::from_path(path).first_or(mime::APPLICATION_OCTET_STREAM);
-                    let bytes = fs::read(path)?;
-                    let name = path
-                        .file_name()
-                        .map(OsStr::to_string_lossy)
-                        .unwrap_or_else(|| Cow::from("Tarot Card"));
-                    let config = AttachmentConfig::new();
-
-                    let _ = room
-                        .send_attachment(name.as_ref(), &mime, bytes, config)
-                        .await
-                        .map_err(IambError::from)?;
+                // Each reply continues the thread from the one sent before it.
+                let mut last = sent_event(root_id.clone(), msg, sender.clone(), self.id());
+
+                for post in replies {
+                    let msg = upload_image(&room, &post.image, &post.caption).await?;
+                    let msg = msg.make_for_thread(&last, ReplyWithinThread::No, AddMentions::No);
+                    let resp = room.send(msg.clone()).await.map_err(IambError::from)?;
+
+                    last = sent_event(resp.event_id, msg, sender.clone(), self.id());
                 }
 
-                // Return a dummy response for the last card
-                let msg = TextMessageEventContent::plain("[Tarot Spread Complete]");
-                let msg = MessageType::Text(msg);
-                let msg = RoomMessageEventContent::new(msg);
-                let resp = room.send(msg.clone()).await.map_err(IambError::from)?;
+                // Mock up the local echo message for the scrollback.
+                let msg = text_to_message(format!("[Attached File: {name}]\n\n{}", root.caption));
 
-                (resp.event_id, msg)
+                (root_id, msg)
             },
             SendAction::UploadWithText(file, text) => {
                 // Upload the file first
@@ -670,28 +675,150 @@ impl ChatState {
                     .file_name()
                     .map(OsStr::to_string_lossy)
                     .unwrap_or_else(|| Cow::from("Attachment"));
-                let config = AttachmentConfig::new();
+                let relation = self.generated_relation(info);
+                let reply = relation.attachment_reply().map_err(UIError::Failure)?;
+                let config = AttachmentConfig::new().reply(reply);
 
-                let _ = room
+                let resp = room
                     .send_attachment(name.as_ref(), &mime, bytes, config)
                     .await
                     .map_err(IambError::from)?;
+                self.reply_to = None;
 
                 // Then send the text message
-                let text_msg = text_to_message(text);
+                let mut text_msg = text_to_message(text);
+                text_msg.relates_to = relation.follow_up(resp.event_id);
                 let resp = room.send(text_msg.clone()).await.map_err(IambError::from)?;
 
                 (resp.event_id, text_msg)
             },
+            SendAction::UploadWithCaption(file, caption, text) => {
+                let path = Path::new(file.as_str());
+                let mime = mime_guess::from_path(path).first_or(mime::APPLICATION_OCTET_STREAM);
+                let bytes = fs::read(path)?;
+                let name = path
+                    .file_name()
+                    .map(OsStr::to_string_lossy)
+                    .unwrap_or_else(|| Cow::from("Attachment"));
+                let relation = self.generated_relation(info);
+                let reply = relation.attachment_reply().map_err(UIError::Failure)?;
+                let config = AttachmentConfig::new().caption(Some(caption.clone())).reply(reply);
+
+                let resp = room
+                    .send_attachment(name.as_ref(), &mime, bytes, config)
+                    .await
+                    .map_err(IambError::from)?;
+                self.reply_to = None;
+
+                if let Some(text) = text {
+                    // Then send the text message
+                    let mut text_msg = text_to_message(text);
+                    text_msg.relates_to = relation.follow_up(resp.event_id);
+                    let resp = room.send(text_msg.clone()).await.map_err(IambError::from)?;
+
+                    (resp.event_id, text_msg)
+                } else {
+                    // Mock up the local echo message for the scrollback.
+                    let msg = format!("[Attached File: {name}] {caption}");
+                    let msg = TextMessageEventContent::plain(msg);
+                    let msg = MessageType::Text(msg);
+                    let msg = RoomMessageEventContent::new(msg);
+
+                    (resp.event_id, msg)
+                }
+            },
+            SendAction::UploadBytes(image, caption, text) => {
+                let relation = self.generated_relation(info);
+                let reply = relation.attachment_reply().map_err(UIError::Failure)?;
+                let config = attachment_config(&image).caption(caption.clone()).reply(reply);
+
+                let resp = room
+                    .send_attachment(&image.name, &image.mime, image.bytes, config)
+                    .await
+                    .map_err(IambError::from)?;
+                self.reply_to = None;
+
+                if let Some(text) = text {
+                    // Then send the text message
+                    let mut text_msg = text_to_message(text);
+                    text_msg.relates_to = relation.follow_up(resp.event_id);
+                    let resp = room.send(text_msg.clone()).await.map_err(IambError::from)?;
+
+                    (resp.event_id, text_msg)
+                } else {
+                    // Mock up the local echo message for the scrollback.
+                    let name = &image.name;
+                    let msg = match caption {
+                        Some(caption) => format!("[Attached File: {name}] {caption}"),
+                        None => format!("[Attached File: {name}]"),
+                    };
+                    let msg = TextMessageEventContent::plain(msg);
+                    let msg = MessageType::Text(msg);
+                    let msg = RoomMessageEventContent::new(msg);
+
+                    (resp.event_id, msg)
+                }
+            },
             SendAction::SendText(text) => {
                 // Send formatted text as a message
-                let text_msg = text_to_message(text);
+                let text_msg = self.generated_relation(info).apply(text_to_message(text));
                 let resp = room.send(text_msg.clone()).await.map_err(IambError::from)?;
 
                 self.reset();
 
                 (resp.event_id, text_msg)
             },
+            SendAction::UploadFaceDown(image, mut facedown) => {
+                let name = image.name.clone();
+                let caption = facedown.caption();
+                let reply = self.generated_relation(info).attachment_reply();
+                let config = attachment_config(&image)
+                    .caption(Some(caption.clone()))
+                    .reply(reply.map_err(UIError::Failure)?);
+
+                let resp = room
+                    .send_attachment(&name, &image.mime, image.bytes, config)
+                    .await
+                    .map_err(IambError::from)?;
+
+                // Remember the message, so that `:tarot reveal` can edit it.
+                facedown.event_id = Some(resp.event_id.clone());
+                store.application.tarot_facedown.insert(self.id().to_owned(), *facedown);
+
+                // Mock up the local echo message for the scrollback.
+                let msg = format!("[Attached File: {name}] {caption}");
+                let msg = TextMessageEventContent::plain(msg);
+                let msg = MessageType::Text(msg);
+                let msg = RoomMessageEventContent::new(msg);
+
+                (resp.event_id, msg)
+            },
+            SendAction::RevealFaceDown(image, mut facedown) => {
+                let Some(event_id) = facedown.event_id.clone() else {
+                    return Err(UIError::Failure(
+                        "The face-down spread hasn't been posted yet".into(),
+                    ));
+                };
+                let msgtype = upload_image(&room, &image, &facedown.caption()).await?.msgtype;
+
+                let mut msg = RoomMessageEventContent::new(msgtype.clone());
+                msg.relates_to =
+                    Some(Relation::Replacement(Replacement::new(event_id, msgtype.into())));
+
+                let resp = room.send(msg.clone()).await.map_err(IambError::from)?;
+                show_echo = false;
+
+                // Only now that the edit is out is the card counted as turned over, and with
+                // the last one, the rest of the reading is posted.
+                if facedown.is_complete() {
+                    store.application.tarot_facedown.remove(self.id());
+                    finale = std::mem::take(&mut facedown.finale);
+                } else {
+                    store.application.tarot_facedown.insert(self.id().to_owned(), *facedown);
+                }
+
+                (resp.event_id, msg)
+            },
         };
 
         if show_echo {
@@ -706,6 +833,10 @@ impl ChatState {
         // Jump to the end of the scrollback to show the message.
         self.scrollback.goto_latest();
 
+        for act in finale {
+            Box::pin(self.send_command(act, ctx.clone(), store)).await?;
+        }
+
         Ok(None)
     }
 
@@ -1053,6 +1184,97 @@ impl StatefulWidget for Chat<'_> {
     }
 }
 
+fn byte_size(bytes: &[u8]) -> UInt {
+    UInt::new_saturating(bytes.len() as u64)
+}
+
+/// Describe an image to be sent as an attachment, so clients can lay it out before downloading it.
+fn attachment_config(image: &ImageUpload) -> AttachmentConfig {
+    let info = BaseImageInfo {
+        width: Some(UInt::from(image.width)),
+        height: Some(UInt::from(image.height)),
+        size: Some(byte_size(&image.bytes)),
+        blurhash: Some(image.blurhash.clone()),
+        ..Default::default()
+    };
+    let thumbnail = image.thumbnail.as_ref().map(|thumbnail| {
+        Thumbnail {
+            data: thumbnail.bytes.clone(),
+            content_type: mime::IMAGE_JPEG,
+            width: UInt::from(thumbnail.width),
+            height: UInt::from(thumbnail.height),
+            size: byte_size(&thumbnail.bytes),
+        }
+    });
+
+    AttachmentConfig::new()
+        .info(AttachmentInfo::Image(info))
+        .thumbnail(thumbnail)
+}
+
+/// Upload an image and build the message that shows it, with a Markdown caption.
+async fn upload_image(
+    room: &MatrixRoom,
+    upload: &ImageUpload,
+    caption: &str,
+) -> IambResult<RoomMessageEventContent> {
+    let media = room.client().media();
+    let url = media
+        .upload(&upload.mime, upload.bytes.clone(), None)
+        .await
+        .map_err(IambError::from)?
+        .content_uri;
+
+    let mut info = ImageInfo::new();
+    info.width = Some(UInt::from(upload.width));
+    info.height = Some(UInt::from(upload.height));
+    info.size = Some(byte_size(&upload.bytes));
+    info.mimetype = Some(upload.mime.to_string());
+    info.blurhash = Some(upload.blurhash.clone());
+
+    if let Some(thumbnail) = &upload.thumbnail {
+        let url = media
+            .upload(&mime::IMAGE_JPEG, thumbnail.bytes.clone(), None)
+            .await
+            .map_err(IambError::from)?
+            .content_uri;
+
+        let mut thumbnail_info = ThumbnailInfo::new();
+        thumbnail_info.width = Some(UInt::from(thumbnail.width));
+        thumbnail_info.height = Some(UInt::from(thumbnail.height));
+        thumbnail_info.size = Some(byte_size(&thumbnail.bytes));
+        thumbnail_info.mimetype = Some(mime::IMAGE_JPEG.to_string());
+
+        info.thumbnail_source = Some(MediaSource::Plain(url));
+        info.thumbnail_info = Some(Box::new(thumbnail_info));
+    }
+
+    let caption = text_to_message_content(caption.to_string());
+    let mut image = ImageMessageEventContent::plain(caption.body, url);
+    image.formatted = caption.formatted;
+    image.filename = Some(upload.name.clone());
+    image.info = Some(Box::new(info));
+
+    Ok(RoomMessageEventContent::new(MessageType::Image(image)))
+}
+
+/// Mock up the event for a message that was just sent, so that later ones can relate to it.
+fn sent_event(
+    event_id: OwnedEventId,
+    content: RoomMessageEventContent,
+    sender: OwnedUserId,
+    room_id: &RoomId,
+) -> OriginalRoomMessageEvent {
+    OriginalRoomMessageEvent {
+        content,
+        event_id,
+        sender,
+        origin_server_ts: MilliSecondsSinceUnixEpoch::now(),
+        room_id: room_id.to_owned(),
+        unsigned: Default::default(),
+    }
+}
+
 fn open_command(open_command: Option<&Vec<String>>, target: OsString) -> IambResult<()> {
     if let Some(mut cmd) = open_command.and_then(cmd) {
         cmd.arg(target);
@@ -1155,9 +1377,76 @@ pub fn auto_toggle_focus(
 mod tests {
     use super::*;
 
+    use image::DynamicImage;
     use modalkit::actions::{EditAction, InsertTextAction};
 
-    use crate::tests::{mock_store, TEST_ROOM1_ID};
+    use crate::image_upload::ImageUpload;
+    use crate::tarot_cards;
+    use crate::tarot_spreads::FaceDown;
+    use crate::tests::homeserver::Homeserver;
+    use crate::tests::{
+        mock_store,
+        MSG1_EVID,
+        MSG1_KEY,
+        MSG2_EVID,
+        MSG2_KEY,
+        MSG3_EVID,
+        TEST_ROOM1_ID,
+        TEST_USER1,
+        TEST_USER2,
+    };
+
+    /// A store and a chat for a room joined on the stand-in homeserver, showing a thread if given
+    async fn joined_chat(
+        server: &Homeserver,
+        thread: Option<OwnedEventId>,
+    ) -> (ProgramStore, ChatState) {
+        let mut store = mock_store().await;
+        let client = server.client(TEST_USER1.as_str()).await;
+        let room = server.join(&client, TEST_ROOM1_ID.as_str()).await;
+        store.application.worker.client = client;
+
+        let chat = ChatState::new(room, thread, &mut store);
+        (store, chat)
+    }
+
+    fn mock_facedown() -> FaceDown {
+        FaceDown {
+            cards: vec!["fool.jpg".into(), "star.jpg".into()],
+            names: vec!["The Fool".into(), "The Star".into()],
+            labels: vec!["Card 1".into(), "Card 2".into()],
+            revealed: vec![false; 2],
+            question: None,
+            event_id: Some(MSG1_EVID.clone()),
+            finale: vec![SendAction::SendText("The reading".into())],
+        }
+    }
+
+    fn mock_image() -> Box<ImageUpload> {
+        let img = DynamicImage::new_rgb8(4, 4);
+        Box::new(ImageUpload::new("spread.png", mime::IMAGE_PNG, vec![0; 16], &img))
+    }
+
+    fn card_path() -> String {
+        tarot_cards::card_back_path().to_string_lossy().to_string()
+    }
+
+    /// A card uploaded with `:upload`, and one uploaded as generated output with text after it
+    fn upload_actions() -> [SendAction; 2] {
+        [
+            SendAction::Upload(card_path()),
+            SendAction::UploadWithText(card_path(), "The Fool".into()),
+        ]
+    }
+
+    /// Turn over the next card of the room's face-down spread
+    async fn reveal_next(chat: &mut ChatState, store: &mut ProgramStore) -> IambResult<EditInfo> {
+        let mut facedown = store.application.tarot_facedown[chat.id()].clone();
+        facedown.reveal(None).unwrap();
+
+        let act = SendAction::RevealFaceDown(mock_image(), Box::new(facedown));
+        chat.send_command(act, ProgramContext::default(), store).await
+    }
 
     macro_rules! move_line {
         ($dir: expr, $count: expr) => {
@@ -1229,4 +1518,155 @@ mod tests {
         assert_eq!(focused, RoomFocus::MessageBar);
         assert_eq!(res, Some(move_line!(MoveDir1D::Next, 2)));
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reveal_facedown() {
+        let server = Homeserver::start();
+        let (mut store, mut chat) = joined_chat(&server, None).await;
+        let room_id = TEST_ROOM1_ID.clone();
+        store.application.tarot_facedown.insert(room_id.clone(), mock_facedown());
+
+        reveal_next(&mut chat, &mut store).await.unwrap();
+        assert_eq!(store.application.tarot_facedown[&room_id].revealed, vec![true, false]);
+
+        let sent = server.sent(room_id.as_str());
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0]["m.relates_to"]["rel_type"], "m.replace");
+        assert_eq!(sent[0]["m.relates_to"]["event_id"], MSG1_EVID.as_str());
+        assert_eq!(sent[0]["m.new_content"]["body"], "Face-down spread | Card 1: The Fool");
+
+        // Turning over the last card posts the rest of the reading, and forgets the spread
+        reveal_next(&mut chat, &mut store).await.unwrap();
+        assert!(!store.application.tarot_facedown.contains_key(&room_id));
+
+        let sent = server.sent(room_id.as_str());
+        assert_eq!(sent.len(), 3);
+        assert_eq!(sent[2]["body"], "The reading");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reveal_facedown_upload_fails() {
+        let server = Homeserver::start();
+        let (mut store, mut chat) = joined_chat(&server, None).await;
+        let room_id = TEST_ROOM1_ID.clone();
+        store.application.tarot_facedown.insert(room_id.clone(), mock_facedown());
+
+        server.refuse_uploads();
+        assert!(reveal_next(&mut chat, &mut store).await.is_err());
+
+        // The card stays face down, so that revealing it can be tried again
+        assert_eq!(store.application.tarot_facedown[&room_id], mock_facedown());
+        assert!(server.sent(room_id.as_str()).is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_send_text_while_editing() {
+        let server = Homeserver::start();
+        let (mut store, mut chat) = joined_chat(&server, None).await;
+        chat.editing = Some(MSG1_KEY.clone());
+
+        let act = SendAction::SendText("**Card of the Day:** The Star".into());
+        chat.send_command(act, ProgramContext::default(), &mut store)
+            .await
+            .unwrap();
+
+        // The text is a message of its own, rather than a replacement for the one being edited
+        let sent = server.sent(TEST_ROOM1_ID.as_str());
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0]["body"], "**Card of the Day:** The Star");
+        assert!(sent[0].get("m.relates_to").is_none());
+        assert!(sent[0].get("m.new_content").is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_upload_while_replying() {
+        let room_id = TEST_ROOM1_ID.as_str();
+
+        for act in upload_actions() {
+            let count = if matches!(act, SendAction::Upload(_)) {
+                1
+            } else {
+                2
+            };
+            let server = Homeserver::start();
+            server.add_message(room_id, MSG2_EVID.as_str(), TEST_USER2.as_str(), "helium");
+
+            let (mut store, mut chat) = joined_chat(&server, None).await;
+            chat.reply_to = Some(MSG2_KEY.clone());
+            chat.send_command(act, ProgramContext::default(), &mut store)
+                .await
+                .unwrap();
+
+            let sent = server.sent(room_id);
+            assert_eq!(sent.len(), count);
+            assert_eq!(sent[0]["m.relates_to"]["m.in_reply_to"]["event_id"], MSG2_EVID.as_str());
+
+            // Text after the card follows it in the main timeline
+            assert!(sent[1..].iter().all(|content| content.get("m.relates_to").is_none()));
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_upload_in_thread() {
+        let room_id = TEST_ROOM1_ID.as_str();
+
+        for act in upload_actions() {
+            let count = if matches!(act, SendAction::Upload(_)) {
+                1
+            } else {
+                2
+            };
+            let server = Homeserver::start();
+            server.add_message(room_id, MSG3_EVID.as_str(), TEST_USER2.as_str(), "a thread");
+
+            let (mut store, mut chat) = joined_chat(&server, Some(MSG3_EVID.clone())).await;
+            chat.send_command(act, ProgramContext::default(), &mut store)
+                .await
+                .unwrap();
+
+            let sent = server.sent(room_id);
+            assert_eq!(sent.len(), count);
+            for content in &sent {
+                assert_eq!(content["m.relates_to"]["rel_type"], "m.thread");
+                assert_eq!(content["m.relates_to"]["event_id"], MSG3_EVID.as_str());
+            }
+
+            // Text after the card stays in the thread, following the card
+            if let Some(text) = sent.get(1) {
+                let card_id = &server.sent_ids(room_id)[0];
+                assert_eq!(text["body"], "The Fool");
+                assert_eq!(text["m.relates_to"]["m.in_reply_to"]["event_id"], card_id.as_str());
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_upload_while_editing() {
+        let server = Homeserver::start();
+        let room_id = TEST_ROOM1_ID.as_str();
+        let (mut store, mut chat) = joined_chat(&server, None).await;
+        chat.editing = Some(MSG1_KEY.clone());
+
+        // A file uploaded with `:upload` can't replace the message being edited
+        let res = chat
+            .send_command(SendAction::Upload(card_path()), ProgramContext::default(), &mut store)
+            .await;
+        let msg = "Cannot send an attachment as an edit of a message";
+        assert!(matches!(res, Err(UIError::Failure(s)) if s == msg));
+        assert!(server.sent(room_id).is_empty());
+
+        // Generated uploads are sent as messages of their own
+        let act = SendAction::UploadWithText(card_path(), "The Fool".into());
+        chat.send_command(act, ProgramContext::default(), &mut store)
+            .await
+            .unwrap();
+
+        let sent = server.sent(room_id);
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[1]["body"], "The Fool");
+        for content in &sent {
+            assert!(content.get("m.relates_to").is_none());
+            assert!(content.get("m.new_content").is_none());
+        }
+    }
 }
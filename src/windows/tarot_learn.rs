@@ -0,0 +1,238 @@
+//! Tarot Learning Window
+use std::ops::{Deref, DerefMut};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier as StyleModifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, StatefulWidget, Widget, Wrap},
+};
+use ratatui_image::{picker::Picker, protocol::Protocol, Image, Resize};
+
+use modalkit_ratatui::{
+    textbox::{TextBox, TextBoxState},
+    TermOffset,
+    TerminalCursor,
+    WindowOps,
+};
+
+use modalkit::actions::{PromptAction, Promptable};
+use modalkit::editing::completion::CompletionList;
+use modalkit::errors::{EditError, EditResult};
+use modalkit::prelude::*;
+
+use crate::base::{
+    IambBufferId,
+    IambInfo,
+    IambResult,
+    ProgramAction,
+    ProgramContext,
+    ProgramStore,
+};
+use crate::tarot_learn::{Flashcard, Grade, SuitStats};
+
+/// Cells in each suit's mastery bar
+const BAR_WIDTH: usize = 10;
+
+/// A card's image, fitted to the area it was last drawn in
+struct CardImage {
+    key: String,
+    area: Rect,
+    protocol: Protocol,
+}
+
+pub struct TarotLearnState {
+    /// The answer being typed.
+    tbox: TextBoxState<IambInfo>,
+
+    image: Option<CardImage>,
+}
+
+impl TarotLearnState {
+    pub fn new(store: &mut ProgramStore) -> Self {
+        let buf = store.buffers.load_str(IambBufferId::TarotLearn, "");
+        let tbox = TextBoxState::new(buf);
+
+        TarotLearnState { tbox, image: None }
+    }
+
+    fn draw_card(&mut self, fc: &Flashcard, area: Rect, picker: Option<&Picker>, buf: &mut Buffer) {
+        let key = fc.card.search_key();
+        let shown = self.image.as_ref().map(|image| (image.key.as_str(), image.area));
+
+        if shown != Some((key.as_str(), area)) {
+            self.image = picker.and_then(|picker| {
+                let img = image::open(fc.card.image_path()).ok()?;
+                let protocol = picker.new_protocol(img, area, Resize::Fit(None)).ok()?;
+
+                Some(CardImage { key, area, protocol })
+            });
+        }
+
+        match &self.image {
+            Some(image) => Image::new(&image.protocol).render(area, buf),
+            None => {
+                let msg = "Turn on image_preview in your config to see the cards here.";
+                Paragraph::new(msg).wrap(Wrap { trim: true }).render(area, buf);
+            },
+        }
+    }
+}
+
+fn grade_style(grade: Grade) -> Style {
+    match grade {
+        Grade::Correct => Style::default().fg(Color::Green),
+        Grade::Partial => Style::default().fg(Color::Yellow),
+        Grade::Wrong | Grade::Blank => Style::default().fg(Color::Red),
+    }
+}
+
+fn stats_line(stats: &SuitStats) -> Line<'static> {
+    let filled = stats.mastery() * BAR_WIDTH / 100;
+    let bar = format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled));
+
+    Line::from(vec![
+        Span::raw(format!("{:<13}", stats.suit)),
+        Span::styled(bar, Style::default().fg(Color::Green)),
+        Span::raw(format!(
+            " {:>3}%  {}/{} seen, {} due",
+            stats.mastery(),
+            stats.seen,
+            stats.total,
+            stats.due
+        )),
+    ])
+}
+
+impl Deref for TarotLearnState {
+    type Target = TextBoxState<IambInfo>;
+
+    fn deref(&self) -> &Self::Target {
+        return &self.tbox;
+    }
+}
+
+impl DerefMut for TarotLearnState {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        return &mut self.tbox;
+    }
+}
+
+impl TerminalCursor for TarotLearnState {
+    fn get_term_cursor(&self) -> Option<TermOffset> {
+        self.tbox.get_term_cursor()
+    }
+}
+
+impl Promptable<ProgramContext, ProgramStore, IambInfo> for TarotLearnState {
+    fn prompt(
+        &mut self,
+        act: &PromptAction,
+        _: &ProgramContext,
+        store: &mut ProgramStore,
+    ) -> EditResult<Vec<(ProgramAction, ProgramContext)>, IambInfo> {
+        match act {
+            PromptAction::Submit => {
+                let Some(session) = store.application.tarot_learn.as_mut() else {
+                    let msg = "No flashcards are loaded. Start with :tarot learn";
+                    return Err(EditError::Failure(msg.into()));
+                };
+
+                let answer = self.tbox.reset_text();
+                session.answer(&answer).map_err(EditError::Failure)?;
+
+                Ok(vec![])
+            },
+            PromptAction::Abort(_) => {
+                self.tbox.reset();
+
+                Ok(vec![])
+            },
+            PromptAction::Recall(..) => {
+                let msg = "Cannot recall history inside the learning window";
+                Err(EditError::Failure(msg.into()))
+            },
+        }
+    }
+}
+
+impl WindowOps<IambInfo> for TarotLearnState {
+    fn draw(&mut self, area: Rect, buf: &mut Buffer, _: bool, store: &mut ProgramStore) {
+        let Some(session) = &store.application.tarot_learn else {
+            let msg = "Use :tarot learn to start learning the deck.";
+            Paragraph::new(msg).render(area, buf);
+            return;
+        };
+
+        let [card, side] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Min(30)]).areas(area);
+        let [quiz, answer, stats] = Layout::vertical([
+            Constraint::Length(5),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ])
+        .areas(side);
+
+        let mut lines = vec![];
+
+        if let Some((grade, feedback)) = &session.feedback {
+            lines.push(Line::styled(feedback.clone(), grade_style(*grade)));
+        }
+
+        match &session.current {
+            Some(fc) => {
+                let bold = Style::default().add_modifier(StyleModifier::BOLD);
+                lines.push(Line::styled(fc.field.question(), bold));
+                lines
+                    .push(Line::raw("Type your answer and press Enter; leave it empty to see it."));
+            },
+            None => {
+                lines.push(Line::raw("Every flashcard has been reviewed. Come back tomorrow!"));
+            },
+        }
+
+        Paragraph::new(lines).wrap(Wrap { trim: true }).render(quiz, buf);
+
+        let block = Block::default().borders(Borders::TOP).title("Mastery");
+        let rows: Vec<Line> = session.stats().iter().map(stats_line).collect();
+        Paragraph::new(rows).block(block).render(stats, buf);
+
+        if let Some(fc) = session.current {
+            self.draw_card(&fc, card, store.application.picker.as_ref(), buf);
+        }
+
+        TextBox::new().prompt("> ").render(answer, buf, &mut self.tbox);
+    }
+
+    fn dup(&self, store: &mut ProgramStore) -> Self {
+        let tbox = self.tbox.dup(store);
+
+        TarotLearnState { tbox, image: None }
+    }
+
+    fn close(&mut self, flags: CloseFlags, store: &mut ProgramStore) -> bool {
+        self.tbox.close(flags, store)
+    }
+
+    fn write(
+        &mut self,
+        path: Option<&str>,
+        flags: WriteFlags,
+        store: &mut ProgramStore,
+    ) -> IambResult<EditInfo> {
+        self.tbox.write(path, flags, store)
+    }
+
+    fn get_completions(&self) -> Option<CompletionList> {
+        None
+    }
+
+    fn get_cursor_word(&self, style: &WordStyle) -> Option<String> {
+        self.tbox.get_cursor_word(style)
+    }
+
+    fn get_selected_word(&self) -> Option<String> {
+        self.tbox.get_selected_word()
+    }
+}
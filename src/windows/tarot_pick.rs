@@ -0,0 +1,209 @@
+//! Tarot Pick-Your-Cards Window
+use std::ops::{Deref, DerefMut};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier as StyleModifier, Style},
+    text::{Line, Span},
+    widgets::{Paragraph, Widget},
+};
+
+use modalkit_ratatui::{textbox::TextBoxState, TermOffset, TerminalCursor, WindowOps};
+
+use modalkit::actions::{PromptAction, Promptable, WindowAction};
+use modalkit::editing::completion::CompletionList;
+use modalkit::errors::{EditError, EditResult};
+use modalkit::prelude::*;
+
+use crate::base::{
+    IambAction,
+    IambBufferId,
+    IambId,
+    IambInfo,
+    IambResult,
+    ProgramAction,
+    ProgramContext,
+    ProgramStore,
+    TarotAction,
+};
+use crate::tarot_spreads::PickSession;
+
+/// Character standing for each face-down card in the text buffer
+const CARD: char = '▒';
+
+/// Columns taken up by each card in the fan, including the gap after it
+const CARD_WIDTH: u16 = 3;
+
+/// Rows taken up by each row of the fan, including the gap after it
+const CARD_HEIGHT: u16 = 3;
+
+pub struct TarotPickState {
+    /// One character per card, so that the usual motions move between them.
+    tbox: TextBoxState<IambInfo>,
+}
+
+impl TarotPickState {
+    pub fn new(store: &mut ProgramStore) -> Self {
+        let buf = store.buffers.load_str(IambBufferId::TarotPick, "");
+        let mut tbox = TextBoxState::new(buf);
+        tbox.set_readonly(true);
+
+        let mut state = TarotPickState { tbox };
+        state.refresh(store);
+        state
+    }
+
+    /// Lay out one character per card, if the deck has changed since it was last drawn.
+    fn refresh(&mut self, store: &ProgramStore) {
+        let cards = store.application.tarot_pick.as_ref().map(|s| s.deck.len()).unwrap_or(0);
+
+        if self.tbox.get_text().trim_end().chars().count() != cards {
+            self.tbox.set_text(CARD.to_string().repeat(cards));
+        }
+    }
+}
+
+fn draw_fan(session: &PickSession, cursor: usize, area: Rect, buf: &mut Buffer) {
+    let per_row = usize::from((area.width / CARD_WIDTH).max(1));
+
+    for (idx, _) in session.deck.iter().enumerate() {
+        let x = area.x + (idx % per_row) as u16 * CARD_WIDTH;
+        let y = area.y + (idx / per_row) as u16 * CARD_HEIGHT;
+
+        if y + 1 >= area.bottom() || x + 1 >= area.right() {
+            break;
+        }
+
+        let (text, mut style) = match session.position_of(idx) {
+            Some(pos) => {
+                (format!("{:>2}", pos + 1), Style::default().fg(Color::Black).bg(Color::Green))
+            },
+            None => ("▒▒".to_string(), Style::default().fg(Color::Blue)),
+        };
+
+        if idx == cursor {
+            style = style.fg(Color::Yellow).add_modifier(StyleModifier::REVERSED);
+        }
+
+        // Each card is two cells wide and two tall, with its number on the top row
+        buf.set_string(x, y, &text, style);
+        buf.set_string(x, y + 1, "▒▒", style);
+    }
+}
+
+impl Deref for TarotPickState {
+    type Target = TextBoxState<IambInfo>;
+
+    fn deref(&self) -> &Self::Target {
+        return &self.tbox;
+    }
+}
+
+impl DerefMut for TarotPickState {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        return &mut self.tbox;
+    }
+}
+
+impl TerminalCursor for TarotPickState {
+    fn get_term_cursor(&self) -> Option<TermOffset> {
+        None
+    }
+}
+
+impl Promptable<ProgramContext, ProgramStore, IambInfo> for TarotPickState {
+    fn prompt(
+        &mut self,
+        act: &PromptAction,
+        ctx: &ProgramContext,
+        store: &mut ProgramStore,
+    ) -> EditResult<Vec<(ProgramAction, ProgramContext)>, IambInfo> {
+        match act {
+            PromptAction::Submit => {
+                let idx = self.tbox.get_cursor().x;
+                let Some(session) = store.application.tarot_pick.as_mut() else {
+                    let msg = "No spread is being picked. Start one with :tarot <spread> pick";
+                    return Err(EditError::Failure(msg.into()));
+                };
+
+                session.toggle(idx).map_err(EditError::Failure)?;
+
+                if !session.is_complete() {
+                    return Ok(vec![]);
+                }
+
+                // Go back to the room and post the reading there
+                let room = IambId::Room(session.room_id.clone(), None);
+                let switch = WindowAction::Switch(OpenTarget::Application(room));
+                let post = IambAction::from(TarotAction::Picked);
+
+                Ok(vec![(switch.into(), ctx.clone()), (post.into(), ctx.clone())])
+            },
+            PromptAction::Abort(_) => {
+                let msg = "Cannot abort entry inside the card fan";
+                Err(EditError::Failure(msg.into()))
+            },
+            PromptAction::Recall(..) => {
+                let msg = "Cannot recall history inside the card fan";
+                Err(EditError::Failure(msg.into()))
+            },
+        }
+    }
+}
+
+impl WindowOps<IambInfo> for TarotPickState {
+    fn draw(&mut self, area: Rect, buf: &mut Buffer, _: bool, store: &mut ProgramStore) {
+        self.refresh(store);
+
+        let Some(session) = &store.application.tarot_pick else {
+            let msg = "Use :tarot <spread> pick to choose a spread's cards yourself.";
+            Paragraph::new(msg).render(area, buf);
+            return;
+        };
+
+        let [header, fan] =
+            Layout::vertical([Constraint::Length(2), Constraint::Min(CARD_HEIGHT)]).areas(area);
+
+        let help = "h/l to move, Enter to choose or put back a card";
+        let status = Line::from(vec![
+            Span::styled(session.status(), Style::default().add_modifier(StyleModifier::BOLD)),
+            Span::raw(" · "),
+            Span::raw(help),
+        ]);
+        Paragraph::new(status).render(header, buf);
+
+        draw_fan(session, self.tbox.get_cursor().x, fan, buf);
+    }
+
+    fn dup(&self, store: &mut ProgramStore) -> Self {
+        let tbox = self.tbox.dup(store);
+
+        TarotPickState { tbox }
+    }
+
+    fn close(&mut self, flags: CloseFlags, store: &mut ProgramStore) -> bool {
+        self.tbox.close(flags, store)
+    }
+
+    fn write(
+        &mut self,
+        path: Option<&str>,
+        flags: WriteFlags,
+        store: &mut ProgramStore,
+    ) -> IambResult<EditInfo> {
+        self.tbox.write(path, flags, store)
+    }
+
+    fn get_completions(&self) -> Option<CompletionList> {
+        None
+    }
+
+    fn get_cursor_word(&self, _: &WordStyle) -> Option<String> {
+        None
+    }
+
+    fn get_selected_word(&self) -> Option<String> {
+        None
+    }
+}
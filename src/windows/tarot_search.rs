@@ -0,0 +1,181 @@
+//! Tarot Card Search Window
+use std::ops::{Deref, DerefMut};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier as StyleModifier, Style},
+    text::{Line, Span},
+    widgets::{Paragraph, Widget, Wrap},
+};
+
+use modalkit_ratatui::{textbox::TextBoxState, TermOffset, TerminalCursor, WindowOps};
+
+use modalkit::editing::completion::CompletionList;
+use modalkit::prelude::*;
+
+use crate::base::{IambBufferId, IambInfo, IambResult, ProgramStore};
+use crate::tarot_search::{SearchHit, Snippet};
+
+/// Rows given to each card found: its name, two of snippet and a gap
+const HIT_HEIGHT: u16 = 4;
+
+pub struct TarotSearchState {
+    /// One line per card found, so that the usual motions move between them.
+    tbox: TextBoxState<IambInfo>,
+
+    /// The query whose results are in the text buffer.
+    shown: Option<String>,
+}
+
+impl TarotSearchState {
+    pub fn new(store: &mut ProgramStore) -> Self {
+        let buf = store.buffers.load_str(IambBufferId::TarotSearch, "");
+        let mut tbox = TextBoxState::new(buf);
+        tbox.set_readonly(true);
+
+        TarotSearchState { tbox, shown: None }
+    }
+
+    /// Load the latest search's cards if they have changed since they were last drawn.
+    fn refresh(&mut self, store: &ProgramStore) {
+        let results = store.application.tarot_search.as_ref();
+
+        if self.shown.as_ref() == results.map(|r| &r.query) {
+            return;
+        }
+
+        self.shown = results.map(|r| r.query.clone());
+
+        let text = results
+            .map(|r| {
+                r.hits
+                    .iter()
+                    .map(|hit| hit.card.card.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default();
+        self.tbox.set_text(text);
+    }
+}
+
+fn snippet_line(snippet: &Snippet) -> Line<'_> {
+    let marked = Style::default().fg(Color::Yellow).add_modifier(StyleModifier::BOLD);
+    let mut spans = vec![];
+    let mut at = 0;
+
+    for range in &snippet.highlights {
+        spans.push(Span::raw(&snippet.text[at..range.start]));
+        spans.push(Span::styled(&snippet.text[range.clone()], marked));
+        at = range.end;
+    }
+
+    spans.push(Span::raw(&snippet.text[at..]));
+
+    Line::from(spans)
+}
+
+fn draw_hit(rank: usize, hit: &SearchHit, selected: bool, area: Rect, buf: &mut Buffer) {
+    let mut style = Style::default().add_modifier(StyleModifier::BOLD);
+    if selected {
+        style = style.add_modifier(StyleModifier::REVERSED);
+    }
+
+    let name = match &hit.card.title {
+        Some(title) => format!("{}. {} ({})", rank, hit.card.card, title),
+        None => format!("{}. {}", rank, hit.card.card),
+    };
+    let lines = vec![Line::styled(name, style), snippet_line(&hit.snippet)];
+
+    Paragraph::new(lines).wrap(Wrap { trim: true }).render(area, buf);
+}
+
+impl Deref for TarotSearchState {
+    type Target = TextBoxState<IambInfo>;
+
+    fn deref(&self) -> &Self::Target {
+        return &self.tbox;
+    }
+}
+
+impl DerefMut for TarotSearchState {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        return &mut self.tbox;
+    }
+}
+
+impl TerminalCursor for TarotSearchState {
+    fn get_term_cursor(&self) -> Option<TermOffset> {
+        None
+    }
+}
+
+impl WindowOps<IambInfo> for TarotSearchState {
+    fn draw(&mut self, area: Rect, buf: &mut Buffer, _: bool, store: &mut ProgramStore) {
+        self.refresh(store);
+
+        let Some(results) = &store.application.tarot_search else {
+            let msg = "Use :tarot search <words> to find cards by their meanings and your notes.";
+            Paragraph::new(msg).render(area, buf);
+            return;
+        };
+
+        let header = match results.hits.len() {
+            0 => format!("No cards match \"{}\"", results.query),
+            1 => format!("1 card matches \"{}\"", results.query),
+            n => format!("{} cards match \"{}\"", n, results.query),
+        };
+        let header_area = Rect { height: area.height.min(2), ..area };
+        Paragraph::new(header).render(header_area, buf);
+
+        // Scroll just far enough to keep the selected card in view
+        let selected = self.tbox.get_cursor().y;
+        let list = Rect {
+            y: area.y + header_area.height,
+            height: area.height - header_area.height,
+            ..area
+        };
+        let visible = usize::from((list.height / HIT_HEIGHT).max(1));
+        let first = (selected + 1).saturating_sub(visible);
+
+        for (i, hit) in results.hits.iter().enumerate().skip(first).take(visible) {
+            let y = list.y + (i - first) as u16 * HIT_HEIGHT;
+            let height = (HIT_HEIGHT - 1).min(list.bottom().saturating_sub(y));
+            let hit_area = Rect { y, height, ..list };
+
+            draw_hit(i + 1, hit, i == selected, hit_area, buf);
+        }
+    }
+
+    fn dup(&self, store: &mut ProgramStore) -> Self {
+        let tbox = self.tbox.dup(store);
+
+        TarotSearchState { tbox, shown: self.shown.clone() }
+    }
+
+    fn close(&mut self, flags: CloseFlags, store: &mut ProgramStore) -> bool {
+        self.tbox.close(flags, store)
+    }
+
+    fn write(
+        &mut self,
+        path: Option<&str>,
+        flags: WriteFlags,
+        store: &mut ProgramStore,
+    ) -> IambResult<EditInfo> {
+        self.tbox.write(path, flags, store)
+    }
+
+    fn get_completions(&self) -> Option<CompletionList> {
+        None
+    }
+
+    fn get_cursor_word(&self, _: &WordStyle) -> Option<String> {
+        None
+    }
+
+    fn get_selected_word(&self) -> Option<String> {
+        None
+    }
+}
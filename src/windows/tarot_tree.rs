@@ -0,0 +1,183 @@
+//! Tarot Tree of Life Window
+use std::ops::{Deref, DerefMut};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::Span,
+    widgets::{
+        canvas::{Canvas, Circle, Line},
+        Block,
+        Borders,
+        Widget,
+    },
+};
+
+use modalkit_ratatui::{textbox::TextBoxState, TermOffset, TerminalCursor, WindowOps};
+
+use modalkit::editing::completion::CompletionList;
+use modalkit::prelude::*;
+
+use crate::base::{IambBufferId, IambInfo, IambResult, ProgramStore};
+use crate::tarot_tree::{self, TreeView, PATHS, SEPHIROTH};
+
+const LIT: Color = Color::Yellow;
+const DIM: Color = Color::DarkGray;
+
+pub struct TarotTreeState {
+    tbox: TextBoxState<IambInfo>,
+
+    /// The tree whose legend is currently in the text buffer.
+    shown: Option<TreeView>,
+}
+
+impl TarotTreeState {
+    pub fn new(store: &mut ProgramStore) -> Self {
+        let buf = store.buffers.load_str(IambBufferId::TarotTree, "");
+        let mut tbox = TextBoxState::new(buf);
+        tbox.set_readonly(true);
+
+        TarotTreeState { tbox, shown: None }
+    }
+
+    /// Load the legend for the latest tree if it has changed since it was last drawn.
+    fn refresh(&mut self, store: &ProgramStore) {
+        if self.shown == store.application.tarot_tree {
+            return;
+        }
+
+        self.shown = store.application.tarot_tree.clone();
+
+        let text = match &self.shown {
+            Some(view) if view.highlights.is_empty() => {
+                format!("{}\n\nNone of these cards sit on the tree.", view.title)
+            },
+            Some(view) => format!("{}\n\n{}", view.title, view.highlights.legend()),
+            None => {
+                "Use :tarothistory tree <number> to see a reading on the Tree of Life.".to_string()
+            },
+        };
+
+        self.tbox.set_text(text);
+    }
+}
+
+fn draw_tree(view: &TreeView, area: Rect, buf: &mut Buffer) {
+    let highlights = &view.highlights;
+
+    Canvas::default()
+        .block(Block::default().borders(Borders::ALL).title("Tree of Life"))
+        .x_bounds([-1.5, 1.5])
+        .y_bounds([-8.5, 0.5])
+        .paint(|ctx| {
+            // Unlit paths first, so lit ones are drawn over them where they cross
+            for lit in [false, true] {
+                for p in PATHS.iter().filter(|p| highlights.has_path(p.number) == lit) {
+                    let (Some(from), Some(to)) =
+                        (tarot_tree::sephira(p.from), tarot_tree::sephira(p.to))
+                    else {
+                        continue;
+                    };
+
+                    ctx.draw(&Line {
+                        x1: from.x,
+                        y1: -from.y,
+                        x2: to.x,
+                        y2: -to.y,
+                        color: if lit { LIT } else { DIM },
+                    });
+                }
+            }
+
+            ctx.layer();
+
+            for s in SEPHIROTH.iter() {
+                let lit = highlights.has_sephira(s.number);
+                let color = if lit { LIT } else { DIM };
+
+                ctx.draw(&Circle { x: s.x, y: -s.y, radius: 0.25, color });
+            }
+
+            ctx.layer();
+
+            for s in SEPHIROTH.iter() {
+                let style = if highlights.has_sephira(s.number) {
+                    Style::default().fg(LIT)
+                } else {
+                    Style::default()
+                };
+
+                ctx.print(s.x - 0.1, -s.y, Span::styled(s.number.to_string(), style));
+            }
+        })
+        .render(area, buf);
+}
+
+impl Deref for TarotTreeState {
+    type Target = TextBoxState<IambInfo>;
+
+    fn deref(&self) -> &Self::Target {
+        return &self.tbox;
+    }
+}
+
+impl DerefMut for TarotTreeState {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        return &mut self.tbox;
+    }
+}
+
+impl TerminalCursor for TarotTreeState {
+    fn get_term_cursor(&self) -> Option<TermOffset> {
+        self.tbox.get_term_cursor()
+    }
+}
+
+impl WindowOps<IambInfo> for TarotTreeState {
+    fn draw(&mut self, area: Rect, buf: &mut Buffer, focused: bool, store: &mut ProgramStore) {
+        self.refresh(store);
+
+        let view = match &self.shown {
+            Some(view) => view,
+            None => return self.tbox.draw(area, buf, focused, store),
+        };
+
+        let [tree, legend] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Min(20)]).areas(area);
+
+        draw_tree(view, tree, buf);
+        self.tbox.draw(legend, buf, focused, store)
+    }
+
+    fn dup(&self, store: &mut ProgramStore) -> Self {
+        let tbox = self.tbox.dup(store);
+
+        TarotTreeState { tbox, shown: self.shown.clone() }
+    }
+
+    fn close(&mut self, flags: CloseFlags, store: &mut ProgramStore) -> bool {
+        self.tbox.close(flags, store)
+    }
+
+    fn write(
+        &mut self,
+        path: Option<&str>,
+        flags: WriteFlags,
+        store: &mut ProgramStore,
+    ) -> IambResult<EditInfo> {
+        self.tbox.write(path, flags, store)
+    }
+
+    fn get_completions(&self) -> Option<CompletionList> {
+        self.tbox.get_completions()
+    }
+
+    fn get_cursor_word(&self, style: &WordStyle) -> Option<String> {
+        self.tbox.get_cursor_word(style)
+    }
+
+    fn get_selected_word(&self) -> Option<String> {
+        self.tbox.get_selected_word()
+    }
+}
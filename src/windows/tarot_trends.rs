@@ -0,0 +1,198 @@
+//! Tarot Trends Window
+use std::ops::{Deref, DerefMut};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    symbols::Marker,
+    text::Span,
+    widgets::{Axis, Block, Borders, Chart, Dataset, GraphType, Sparkline, Widget},
+};
+
+use modalkit_ratatui::{textbox::TextBoxState, TermOffset, TerminalCursor, WindowOps};
+
+use modalkit::editing::completion::CompletionList;
+use modalkit::prelude::*;
+
+use crate::base::{IambBufferId, IambInfo, IambResult, ProgramStore};
+use crate::tarot_trends::TrendReport;
+
+/// Colours given to each series in the chart, in turn
+const SERIES_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Green,
+    Color::Red,
+    Color::Blue,
+];
+
+pub struct TarotTrendsState {
+    tbox: TextBoxState<IambInfo>,
+
+    /// The report whose notes are currently in the text buffer.
+    shown: Option<TrendReport>,
+}
+
+impl TarotTrendsState {
+    pub fn new(store: &mut ProgramStore) -> Self {
+        let buf = store.buffers.load_str(IambBufferId::TarotTrends, "");
+        let mut tbox = TextBoxState::new(buf);
+        tbox.set_readonly(true);
+
+        TarotTrendsState { tbox, shown: None }
+    }
+
+    /// Load the notes for the latest report if it has changed since it was last drawn.
+    fn refresh(&mut self, store: &ProgramStore) {
+        if self.shown == store.application.tarot_trends {
+            return;
+        }
+
+        self.shown = store.application.tarot_trends.clone();
+
+        let text = match &self.shown {
+            Some(report) if report.periods.is_empty() => {
+                format!("{}\n\nNo readings in this range.", report.title)
+            },
+            Some(report) => format!("{}\n\n{}", report.title, report.notes()),
+            None => "Use :tarothistory chart to chart your readings.".to_string(),
+        };
+
+        self.tbox.set_text(text);
+    }
+}
+
+fn draw_chart(report: &TrendReport, area: Rect, buf: &mut Buffer) {
+    let points: Vec<Vec<(f64, f64)>> = report
+        .series
+        .iter()
+        .map(|(_, counts)| {
+            counts
+                .iter()
+                .enumerate()
+                .map(|(i, &count)| (i as f64, count as f64))
+                .collect()
+        })
+        .collect();
+
+    let datasets = report
+        .series
+        .iter()
+        .zip(&points)
+        .enumerate()
+        .map(|(i, ((name, _), points))| {
+            Dataset::default()
+                .name(name.as_str())
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(SERIES_COLORS[i % SERIES_COLORS.len()]))
+                .data(points)
+        })
+        .collect();
+
+    let last = report.periods.len().saturating_sub(1);
+    let max = report
+        .series
+        .iter()
+        .flat_map(|(_, c)| c.iter().copied())
+        .max()
+        .unwrap_or(0);
+
+    let x_labels = vec![
+        Span::raw(report.periods[0].as_str()),
+        Span::raw(report.periods[last / 2].as_str()),
+        Span::raw(report.periods[last].as_str()),
+    ];
+    let y_labels = vec![Span::raw("0"), Span::raw(max.to_string())];
+
+    Chart::new(datasets)
+        .block(Block::default().borders(Borders::ALL).title(report.title.as_str()))
+        .x_axis(Axis::default().bounds([0.0, last.max(1) as f64]).labels(x_labels))
+        .y_axis(Axis::default().bounds([0.0, max.max(1) as f64]).labels(y_labels))
+        .render(area, buf);
+}
+
+fn draw_sparkline(report: &TrendReport, area: Rect, buf: &mut Buffer) {
+    let data: Vec<u64> = report.readings.iter().map(|&n| n.max(0) as u64).collect();
+
+    Sparkline::default()
+        .block(Block::default().borders(Borders::ALL).title("Readings per period"))
+        .style(Style::default().fg(Color::Cyan))
+        .data(&data)
+        .render(area, buf);
+}
+
+impl Deref for TarotTrendsState {
+    type Target = TextBoxState<IambInfo>;
+
+    fn deref(&self) -> &Self::Target {
+        return &self.tbox;
+    }
+}
+
+impl DerefMut for TarotTrendsState {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        return &mut self.tbox;
+    }
+}
+
+impl TerminalCursor for TarotTrendsState {
+    fn get_term_cursor(&self) -> Option<TermOffset> {
+        self.tbox.get_term_cursor()
+    }
+}
+
+impl WindowOps<IambInfo> for TarotTrendsState {
+    fn draw(&mut self, area: Rect, buf: &mut Buffer, focused: bool, store: &mut ProgramStore) {
+        self.refresh(store);
+
+        let report = match &self.shown {
+            Some(report) if !report.periods.is_empty() => report,
+            _ => return self.tbox.draw(area, buf, focused, store),
+        };
+
+        let [chart, sparkline, notes] = Layout::vertical([
+            Constraint::Percentage(60),
+            Constraint::Length(5),
+            Constraint::Min(3),
+        ])
+        .areas(area);
+
+        draw_chart(report, chart, buf);
+        draw_sparkline(report, sparkline, buf);
+        self.tbox.draw(notes, buf, focused, store)
+    }
+
+    fn dup(&self, store: &mut ProgramStore) -> Self {
+        let tbox = self.tbox.dup(store);
+
+        TarotTrendsState { tbox, shown: self.shown.clone() }
+    }
+
+    fn close(&mut self, flags: CloseFlags, store: &mut ProgramStore) -> bool {
+        self.tbox.close(flags, store)
+    }
+
+    fn write(
+        &mut self,
+        path: Option<&str>,
+        flags: WriteFlags,
+        store: &mut ProgramStore,
+    ) -> IambResult<EditInfo> {
+        self.tbox.write(path, flags, store)
+    }
+
+    fn get_completions(&self) -> Option<CompletionList> {
+        self.tbox.get_completions()
+    }
+
+    fn get_cursor_word(&self, style: &WordStyle) -> Option<String> {
+        self.tbox.get_cursor_word(style)
+    }
+
+    fn get_selected_word(&self) -> Option<String> {
+        self.tbox.get_selected_word()
+    }
+}
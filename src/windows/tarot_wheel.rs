@@ -0,0 +1,216 @@
+//! Tarot Zodiac Wheel Window
+use std::ops::{Deref, DerefMut};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::Span,
+    widgets::{
+        canvas::{Canvas, Circle, Context, Line},
+        Block,
+        Borders,
+        Widget,
+    },
+};
+
+use modalkit_ratatui::{textbox::TextBoxState, TermOffset, TerminalCursor, WindowOps};
+
+use modalkit::editing::completion::CompletionList;
+use modalkit::prelude::*;
+
+use crate::base::{IambBufferId, IambInfo, IambResult, ProgramStore};
+use crate::tarot_wheel::{self, WheelView};
+
+const LIT: Color = Color::Yellow;
+const DIM: Color = Color::DarkGray;
+
+/// Colours for the signs of each element, in zodiac order from Aries
+const ELEMENT_COLORS: [Color; 4] = [Color::Red, Color::Green, Color::Yellow, Color::Blue];
+
+/// Radii of the hub, the inside of the sign ring and the outside of the wheel
+const HUB: f64 = 0.35;
+const SIGN_RING: f64 = 0.8;
+const OUTER: f64 = 1.0;
+
+pub struct TarotWheelState {
+    tbox: TextBoxState<IambInfo>,
+
+    /// The wheel whose legend is currently in the text buffer.
+    shown: Option<WheelView>,
+}
+
+impl TarotWheelState {
+    pub fn new(store: &mut ProgramStore) -> Self {
+        let buf = store.buffers.load_str(IambBufferId::TarotWheel, "");
+        let mut tbox = TextBoxState::new(buf);
+        tbox.set_readonly(true);
+
+        TarotWheelState { tbox, shown: None }
+    }
+
+    /// Load the legend for the latest wheel if it has changed since it was last drawn.
+    fn refresh(&mut self, store: &ProgramStore) {
+        if self.shown == store.application.tarot_wheel {
+            return;
+        }
+
+        self.shown = store.application.tarot_wheel.clone();
+
+        let text = match &self.shown {
+            Some(view) if view.placed == 0 && view.off_wheel == 0 => {
+                format!("{}\n\nNo cards in this range.", view.title)
+            },
+            Some(view) => format!("{}\n\n{}", view.title, view.legend()),
+            None => {
+                "Use :tarothistory wheel [number] to see readings on the zodiac wheel.".to_string()
+            },
+        };
+
+        self.tbox.set_text(text);
+    }
+}
+
+/// A point `r` from the centre at `longitude`, with 0° Aries at nine o'clock
+fn point(longitude: f64, r: f64) -> (f64, f64) {
+    let angle = (180.0 + longitude).to_radians();
+    (r * angle.cos(), r * angle.sin())
+}
+
+fn radial(ctx: &mut Context<'_>, longitude: f64, inner: f64, outer: f64, color: Color) {
+    let (x1, y1) = point(longitude, inner);
+    let (x2, y2) = point(longitude, outer);
+
+    ctx.draw(&Line { x1, y1, x2, y2, color });
+}
+
+fn draw_wheel(view: &WheelView, area: Rect, buf: &mut Buffer) {
+    let max = f64::from(view.max_count().max(1));
+
+    Canvas::default()
+        .block(Block::default().borders(Borders::ALL).title("Zodiac"))
+        .x_bounds([-1.2, 1.2])
+        .y_bounds([-1.2, 1.2])
+        .paint(|ctx| {
+            for radius in [HUB, SIGN_RING, OUTER] {
+                ctx.draw(&Circle { x: 0.0, y: 0.0, radius, color: DIM });
+            }
+
+            for sign in 0..12 {
+                radial(ctx, sign as f64 * 30.0, HUB, OUTER, DIM);
+            }
+
+            // Each decan's bar grows from the hub with the number of cards covering it
+            for (decan, count) in view.decans.iter().enumerate().filter(|(_, c)| **c > 0) {
+                let length = (SIGN_RING - HUB - 0.05) * f64::from(*count) / max;
+
+                for offset in [2.5, 5.0, 7.5] {
+                    radial(ctx, decan as f64 * 10.0 + offset, HUB, HUB + length, LIT);
+                }
+            }
+
+            ctx.layer();
+
+            for sign in 0..12 {
+                let (x, y) = point(sign as f64 * 30.0 + 15.0, (SIGN_RING + OUTER) / 2.0);
+                let style = Style::default().fg(ELEMENT_COLORS[sign % 4]);
+
+                ctx.print(x, y, Span::styled(tarot_wheel::sign_glyph(sign).to_string(), style));
+            }
+
+            // Cards sharing a decan are stacked towards the hub
+            let mut stacked = [0; 36];
+            for placement in &view.placements {
+                let decan = (placement.longitude() / 10.0) as usize % 36;
+                let (x, y) =
+                    point(placement.longitude(), SIGN_RING - 0.1 - 0.12 * stacked[decan] as f64);
+                stacked[decan] += 1;
+
+                let glyph = placement
+                    .planet
+                    .as_deref()
+                    .and_then(tarot_wheel::planet_glyph)
+                    .unwrap_or('●');
+                ctx.print(x, y, Span::styled(glyph.to_string(), Style::default().fg(Color::Cyan)));
+            }
+
+            let planets: String = view
+                .unplaced
+                .iter()
+                .filter_map(|(_, planet)| planet.as_deref().and_then(tarot_wheel::planet_glyph))
+                .collect();
+            if !planets.is_empty() {
+                let x = -0.04 * planets.chars().count() as f64;
+                ctx.print(x, 0.0, Span::styled(planets, Style::default().fg(Color::Cyan)));
+            }
+        })
+        .render(area, buf);
+}
+
+impl Deref for TarotWheelState {
+    type Target = TextBoxState<IambInfo>;
+
+    fn deref(&self) -> &Self::Target {
+        return &self.tbox;
+    }
+}
+
+impl DerefMut for TarotWheelState {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        return &mut self.tbox;
+    }
+}
+
+impl TerminalCursor for TarotWheelState {
+    fn get_term_cursor(&self) -> Option<TermOffset> {
+        self.tbox.get_term_cursor()
+    }
+}
+
+impl WindowOps<IambInfo> for TarotWheelState {
+    fn draw(&mut self, area: Rect, buf: &mut Buffer, focused: bool, store: &mut ProgramStore) {
+        self.refresh(store);
+
+        let view = match &self.shown {
+            Some(view) => view,
+            None => return self.tbox.draw(area, buf, focused, store),
+        };
+
+        let [wheel, legend] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Min(20)]).areas(area);
+
+        draw_wheel(view, wheel, buf);
+        self.tbox.draw(legend, buf, focused, store)
+    }
+
+    fn dup(&self, store: &mut ProgramStore) -> Self {
+        let tbox = self.tbox.dup(store);
+
+        TarotWheelState { tbox, shown: self.shown.clone() }
+    }
+
+    fn close(&mut self, flags: CloseFlags, store: &mut ProgramStore) -> bool {
+        self.tbox.close(flags, store)
+    }
+
+    fn write(
+        &mut self,
+        path: Option<&str>,
+        flags: WriteFlags,
+        store: &mut ProgramStore,
+    ) -> IambResult<EditInfo> {
+        self.tbox.write(path, flags, store)
+    }
+
+    fn get_completions(&self) -> Option<CompletionList> {
+        self.tbox.get_completions()
+    }
+
+    fn get_cursor_word(&self, style: &WordStyle) -> Option<String> {
+        self.tbox.get_cursor_word(style)
+    }
+
+    fn get_selected_word(&self) -> Option<String> {
+        self.tbox.get_selected_word()
+    }
+}
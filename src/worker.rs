@@ -89,7 +89,7 @@ use modalkit::errors::UIError;
 use modalkit::prelude::{EditInfo, InfoMessage};
 
 use crate::base::Need;
-use crate::notifications::register_notifications;
+use crate::notifications::{notify_decan_changes, register_notifications, remind_daily_draw};
 use crate::{
     base::{
         AsyncProgramStore,
@@ -1252,7 +1252,10 @@ impl ClientWorker {
                 let rcpt = send_receipts_forever(&client, &store);
                 let room = refresh_rooms_forever(&client, &store);
                 let notifications = register_notifications(&client, &settings, &store);
-                let ((), (), (), ()) = tokio::join!(load, rcpt, room, notifications);
+                let decans = notify_decan_changes(&settings, &store);
+                let daily = remind_daily_draw(&settings, &store);
+                let ((), (), (), (), (), ()) =
+                    tokio::join!(load, rcpt, room, notifications, decans, daily);
             }
         })
         .into();